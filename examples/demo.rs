@@ -952,6 +952,12 @@ pub struct DemoApp {
     snarl: Snarl<DemoNode>,
     style: SnarlStyle,
     snarl_ui_id: Option<Id>,
+
+    /// Whether graph was changed since it was last saved.
+    unsaved: bool,
+
+    /// Whether title bar shows unsaved changes.
+    title_unsaved: bool,
 }
 
 const fn default_style() -> SnarlStyle {
@@ -1010,6 +1016,8 @@ impl DemoApp {
             snarl,
             style,
             snarl_ui_id: None,
+            unsaved: false,
+            title_unsaved: false,
         }
     }
 }
@@ -1081,8 +1089,20 @@ impl App for DemoApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             self.snarl_ui_id = Some(ui.id());
 
-            self.snarl.show(&mut DemoViewer, &self.style, "snarl", ui);
+            let response = self.snarl.show(&mut DemoViewer, &self.style, "snarl", ui);
+            self.unsaved |= response.changed;
         });
+
+        // Unsaved changes are marked in the title bar.
+        if self.title_unsaved != self.unsaved {
+            self.title_unsaved = self.unsaved;
+            let title = if self.unsaved {
+                "egui-snarl demo *"
+            } else {
+                "egui-snarl demo"
+            };
+            ctx.send_viewport_cmd(egui::ViewportCommand::Title(title.to_owned()));
+        }
     }
 
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
@@ -1091,6 +1111,8 @@ impl App for DemoApp {
 
         let style = serde_json::to_string(&self.style).unwrap();
         storage.set_string("style", style);
+
+        self.unsaved = false;
     }
 }
 
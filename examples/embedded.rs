@@ -0,0 +1,118 @@
+//! Example of a graph embedded into a scrolling layout.
+//!
+//! Graph is placed between paragraphs of a vertical [`egui::ScrollArea`].
+//! With [`SnarlStyle::capture_scroll`] disabled, scrolling over the graph
+//! scrolls the page, while holding modifier zooms the graph.
+
+use eframe::App;
+use egui::{vec2, Color32, Id, Ui};
+use egui_snarl::{
+    ui::{PinInfo, SnarlStyle, SnarlViewer},
+    InPin, OutPin, Snarl,
+};
+
+const PIN_COLOR: Color32 = Color32::from_rgb(0xb0, 0x00, 0xb0);
+
+/// Height of the graph in the page.
+const GRAPH_HEIGHT: f32 = 300.0;
+
+struct Node;
+
+struct Viewer;
+
+impl SnarlViewer<Node> for Viewer {
+    fn title(&mut self, _node: &Node) -> String {
+        "Node".to_owned()
+    }
+
+    fn inputs(&mut self, _node: &Node) -> usize {
+        1
+    }
+
+    fn outputs(&mut self, _node: &Node) -> usize {
+        1
+    }
+
+    fn show_input(
+        &mut self,
+        _pin: &InPin,
+        _ui: &mut Ui,
+        _scale: f32,
+        _snarl: &mut Snarl<Node>,
+    ) -> PinInfo {
+        PinInfo::circle().with_fill(PIN_COLOR)
+    }
+
+    fn show_output(
+        &mut self,
+        _pin: &OutPin,
+        _ui: &mut Ui,
+        _scale: f32,
+        _snarl: &mut Snarl<Node>,
+    ) -> PinInfo {
+        PinInfo::circle().with_fill(PIN_COLOR)
+    }
+}
+
+struct EmbeddedApp {
+    snarl: Snarl<Node>,
+    style: SnarlStyle,
+}
+
+impl EmbeddedApp {
+    fn new() -> Self {
+        let mut snarl = Snarl::new();
+        snarl.insert_node(egui::pos2(0.0, 0.0), Node);
+        snarl.insert_node(egui::pos2(150.0, 50.0), Node);
+
+        let mut style = SnarlStyle::new();
+        style.capture_scroll = Some(false);
+
+        EmbeddedApp { snarl, style }
+    }
+}
+
+fn paragraphs(ui: &mut Ui, count: usize) {
+    for _ in 0..count {
+        ui.label(
+            "Lorem ipsum dolor sit amet, consectetur adipiscing elit, \
+             sed do eiusmod tempor incididunt ut labore et dolore magna aliqua.",
+        );
+        ui.add_space(ui.spacing().item_spacing.y);
+    }
+}
+
+impl App for EmbeddedApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                paragraphs(ui, 20);
+
+                // Graph takes all of its rect, so it is shown in a child ui of fixed size.
+                let size = vec2(ui.available_width(), GRAPH_HEIGHT);
+                ui.allocate_ui(size, |ui| {
+                    ui.set_min_size(size);
+                    self.snarl
+                        .show(&mut Viewer, &self.style, Id::new("snarl"), ui);
+                });
+
+                paragraphs(ui, 20);
+            });
+        });
+    }
+}
+
+fn main() -> eframe::Result<()> {
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([800.0, 600.0])
+            .with_min_inner_size([300.0, 220.0]),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "egui-snarl embedded",
+        native_options,
+        Box::new(|_cx| Ok(Box::new(EmbeddedApp::new()))),
+    )
+}
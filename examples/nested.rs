@@ -0,0 +1,300 @@
+//! Example of a node that contains its own graph.
+//!
+//! Subgraph node shows nested [`Snarl`] in its body.
+//! Inner graph is panned and zoomed independently from the outer one.
+//! Subgraph node's pins are mapped to `Input` and `Output` nodes of the inner graph.
+
+use eframe::App;
+use egui::{vec2, Color32, Id, Sense, Ui, UiBuilder};
+use egui_snarl::{
+    ui::{PinInfo, SnarlStyle, SnarlViewer},
+    InPin, NodeId, OutPin, Snarl,
+};
+
+const PIN_COLOR: Color32 = Color32::from_rgb(0x00, 0xb0, 0xb0);
+
+/// Size of the nested graph viewport at scale `1.0`.
+const SUBGRAPH_SIZE: egui::Vec2 = vec2(300.0, 200.0);
+
+/// Node of the nested graph.
+enum InnerNode {
+    /// Exposed as input pin of the subgraph node.
+    Input,
+
+    /// Exposed as output pin of the subgraph node.
+    Output,
+
+    /// Node with single input and single output.
+    Pass,
+}
+
+/// Node of the outer graph.
+enum OuterNode {
+    /// Node with single input and single output.
+    Pass,
+
+    /// Node with nested graph.
+    Subgraph(Snarl<InnerNode>),
+}
+
+/// Returns number of external inputs of the nested graph.
+fn subgraph_inputs(snarl: &Snarl<InnerNode>) -> usize {
+    snarl
+        .nodes()
+        .filter(|node| matches!(node, InnerNode::Input))
+        .count()
+}
+
+/// Returns number of external outputs of the nested graph.
+fn subgraph_outputs(snarl: &Snarl<InnerNode>) -> usize {
+    snarl
+        .nodes()
+        .filter(|node| matches!(node, InnerNode::Output))
+        .count()
+}
+
+/// Returns id of the `Input` node of the nested graph
+/// that is mapped to parent node's input pin with index `idx`.
+fn subgraph_input_node(snarl: &Snarl<InnerNode>, idx: usize) -> Option<NodeId> {
+    snarl
+        .node_ids()
+        .filter(|(_, node)| matches!(node, InnerNode::Input))
+        .nth(idx)
+        .map(|(id, _)| id)
+}
+
+/// Returns id of the `Output` node of the nested graph
+/// that is mapped to parent node's output pin with index `idx`.
+fn subgraph_output_node(snarl: &Snarl<InnerNode>, idx: usize) -> Option<NodeId> {
+    snarl
+        .node_ids()
+        .filter(|(_, node)| matches!(node, InnerNode::Output))
+        .nth(idx)
+        .map(|(id, _)| id)
+}
+
+struct InnerViewer;
+
+impl SnarlViewer<InnerNode> for InnerViewer {
+    fn title(&mut self, node: &InnerNode) -> String {
+        match node {
+            InnerNode::Input => "Input".to_owned(),
+            InnerNode::Output => "Output".to_owned(),
+            InnerNode::Pass => "Pass".to_owned(),
+        }
+    }
+
+    fn inputs(&mut self, node: &InnerNode) -> usize {
+        match node {
+            InnerNode::Input => 0,
+            InnerNode::Output | InnerNode::Pass => 1,
+        }
+    }
+
+    fn outputs(&mut self, node: &InnerNode) -> usize {
+        match node {
+            InnerNode::Output => 0,
+            InnerNode::Input | InnerNode::Pass => 1,
+        }
+    }
+
+    fn show_input(
+        &mut self,
+        _pin: &InPin,
+        _ui: &mut Ui,
+        _scale: f32,
+        _snarl: &mut Snarl<InnerNode>,
+    ) -> PinInfo {
+        PinInfo::circle().with_fill(PIN_COLOR)
+    }
+
+    fn show_output(
+        &mut self,
+        _pin: &OutPin,
+        _ui: &mut Ui,
+        _scale: f32,
+        _snarl: &mut Snarl<InnerNode>,
+    ) -> PinInfo {
+        PinInfo::circle().with_fill(PIN_COLOR)
+    }
+
+    fn has_graph_menu(&mut self, _pos: egui::Pos2, _snarl: &mut Snarl<InnerNode>) -> bool {
+        true
+    }
+
+    fn show_graph_menu(
+        &mut self,
+        pos: egui::Pos2,
+        ui: &mut Ui,
+        _scale: f32,
+        snarl: &mut Snarl<InnerNode>,
+    ) {
+        ui.label("Add node");
+        if ui.button("Input").clicked() {
+            snarl.insert_node(pos, InnerNode::Input);
+            ui.close_menu();
+        }
+        if ui.button("Output").clicked() {
+            snarl.insert_node(pos, InnerNode::Output);
+            ui.close_menu();
+        }
+        if ui.button("Pass").clicked() {
+            snarl.insert_node(pos, InnerNode::Pass);
+            ui.close_menu();
+        }
+    }
+}
+
+struct OuterViewer {
+    inner_style: SnarlStyle,
+}
+
+impl SnarlViewer<OuterNode> for OuterViewer {
+    fn title(&mut self, node: &OuterNode) -> String {
+        match node {
+            OuterNode::Pass => "Pass".to_owned(),
+            OuterNode::Subgraph(_) => "Subgraph".to_owned(),
+        }
+    }
+
+    fn inputs(&mut self, node: &OuterNode) -> usize {
+        match node {
+            OuterNode::Pass => 1,
+            OuterNode::Subgraph(snarl) => subgraph_inputs(snarl),
+        }
+    }
+
+    fn outputs(&mut self, node: &OuterNode) -> usize {
+        match node {
+            OuterNode::Pass => 1,
+            OuterNode::Subgraph(snarl) => subgraph_outputs(snarl),
+        }
+    }
+
+    fn show_input(
+        &mut self,
+        pin: &InPin,
+        ui: &mut Ui,
+        _scale: f32,
+        snarl: &mut Snarl<OuterNode>,
+    ) -> PinInfo {
+        if let OuterNode::Subgraph(inner) = &snarl[pin.id.node] {
+            if let Some(node) = subgraph_input_node(inner, pin.id.input) {
+                ui.label(format!("in {}", node.0));
+            }
+        }
+        PinInfo::circle().with_fill(PIN_COLOR)
+    }
+
+    fn show_output(
+        &mut self,
+        pin: &OutPin,
+        ui: &mut Ui,
+        _scale: f32,
+        snarl: &mut Snarl<OuterNode>,
+    ) -> PinInfo {
+        if let OuterNode::Subgraph(inner) = &snarl[pin.id.node] {
+            if let Some(node) = subgraph_output_node(inner, pin.id.output) {
+                ui.label(format!("out {}", node.0));
+            }
+        }
+        PinInfo::circle().with_fill(PIN_COLOR)
+    }
+
+    fn has_body(&mut self, node: &OuterNode) -> bool {
+        matches!(node, OuterNode::Subgraph(_))
+    }
+
+    fn show_body(
+        &mut self,
+        node: NodeId,
+        _inputs: &[InPin],
+        _outputs: &[OutPin],
+        ui: &mut Ui,
+        scale: f32,
+        snarl: &mut Snarl<OuterNode>,
+    ) {
+        if let OuterNode::Subgraph(inner) = &mut snarl[node] {
+            let (rect, _) = ui.allocate_exact_size(SUBGRAPH_SIZE * scale, Sense::hover());
+            let inner_ui = &mut ui.new_child(UiBuilder::new().max_rect(rect));
+
+            // Inner state is keyed by the node's ui id, so it does not clobber outer state.
+            inner.show(&mut InnerViewer, &self.inner_style, "subgraph", inner_ui);
+        }
+    }
+
+    fn has_graph_menu(&mut self, _pos: egui::Pos2, _snarl: &mut Snarl<OuterNode>) -> bool {
+        true
+    }
+
+    fn show_graph_menu(
+        &mut self,
+        pos: egui::Pos2,
+        ui: &mut Ui,
+        _scale: f32,
+        snarl: &mut Snarl<OuterNode>,
+    ) {
+        ui.label("Add node");
+        if ui.button("Pass").clicked() {
+            snarl.insert_node(pos, OuterNode::Pass);
+            ui.close_menu();
+        }
+        if ui.button("Subgraph").clicked() {
+            snarl.insert_node(pos, OuterNode::Subgraph(new_subgraph()));
+            ui.close_menu();
+        }
+    }
+}
+
+fn new_subgraph() -> Snarl<InnerNode> {
+    let mut snarl = Snarl::new();
+    snarl.insert_node(egui::pos2(0.0, 0.0), InnerNode::Input);
+    snarl.insert_node(egui::pos2(150.0, 0.0), InnerNode::Output);
+    snarl
+}
+
+struct NestedApp {
+    snarl: Snarl<OuterNode>,
+    viewer: OuterViewer,
+    style: SnarlStyle,
+}
+
+impl NestedApp {
+    fn new() -> Self {
+        let mut snarl = Snarl::new();
+        snarl.insert_node(egui::pos2(0.0, 0.0), OuterNode::Pass);
+        snarl.insert_node(egui::pos2(200.0, 0.0), OuterNode::Subgraph(new_subgraph()));
+
+        NestedApp {
+            snarl,
+            viewer: OuterViewer {
+                inner_style: SnarlStyle::new(),
+            },
+            style: SnarlStyle::new(),
+        }
+    }
+}
+
+impl App for NestedApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.snarl
+                .show(&mut self.viewer, &self.style, Id::new("snarl"), ui);
+        });
+    }
+}
+
+fn main() -> eframe::Result<()> {
+    let native_options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([800.0, 600.0])
+            .with_min_inner_size([300.0, 220.0]),
+        ..Default::default()
+    };
+
+    eframe::run_native(
+        "egui-snarl nested",
+        native_options,
+        Box::new(|_cx| Ok(Box::new(NestedApp::new()))),
+    )
+}
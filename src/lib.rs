@@ -12,11 +12,19 @@
 
 pub mod ui;
 
-use std::ops::{Index, IndexMut};
-
-use egui::{ahash::HashSet, Pos2};
+use std::{
+    ops::{Index, IndexMut},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use egui::{
+    ahash::{HashMap, HashSet},
+    Pos2,
+};
 use slab::Slab;
 
+use crate::ui::{SnarlStyle, SnarlViewer, WireDrag};
+
 impl<T> Default for Snarl<T> {
     fn default() -> Self {
         Snarl::new()
@@ -50,6 +58,15 @@ pub struct Node<T> {
 
     /// Flag indicating that the node is open - not collapsed.
     pub open: bool,
+
+    /// Size of the node set by resizing it.
+    /// This does not include frame margin.
+    /// If `None`, node is sized to fit its content.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub size: Option<egui::Vec2>,
 }
 
 /// Output pin identifier.
@@ -87,9 +104,144 @@ struct Wire {
     in_pin: InPinId,
 }
 
+/// Error returned by [`Snarl::try_connect`] when connection can't be made.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ConnectError {
+    /// Node referenced by the pin does not exist.
+    NodeNotFound(NodeId),
+
+    /// Output pin index is out of range of node's outputs.
+    OutputOutOfRange(OutPinId),
+
+    /// Input pin index is out of range of node's inputs.
+    InputOutOfRange(InPinId),
+
+    /// Pins are already connected.
+    AlreadyConnected,
+
+    /// Connection would link node to itself
+    /// while [`SnarlStyle::allow_self_loops`] is disabled.
+    SelfLoop,
+
+    /// Connection would create a cycle
+    /// while [`SnarlStyle::prevent_cycles`] is enabled.
+    Cycle,
+
+    /// Connection was rejected by [`SnarlViewer::can_connect`].
+    Rejected,
+}
+
+impl std::fmt::Display for ConnectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConnectError::NodeNotFound(node) => write!(f, "node {node:?} does not exist"),
+            ConnectError::OutputOutOfRange(pin) => {
+                write!(
+                    f,
+                    "output {} of node {:?} is out of range",
+                    pin.output, pin.node
+                )
+            }
+            ConnectError::InputOutOfRange(pin) => {
+                write!(
+                    f,
+                    "input {} of node {:?} is out of range",
+                    pin.input, pin.node
+                )
+            }
+            ConnectError::AlreadyConnected => f.write_str("pins are already connected"),
+            ConnectError::SelfLoop => f.write_str("node can't be connected to itself"),
+            ConnectError::Cycle => f.write_str("connection would create a cycle"),
+            ConnectError::Rejected => f.write_str("connection was rejected by the viewer"),
+        }
+    }
+}
+
+impl std::error::Error for ConnectError {}
+
+/// Outcome of [`Snarl::toggle_wire`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WireToggle {
+    /// Pins were not connected and are connected now.
+    Connected,
+
+    /// Pins were connected and are disconnected now.
+    Disconnected,
+
+    /// Pins were not connected and connection was rejected.
+    Rejected(ConnectError),
+}
+
 #[derive(Clone, Debug)]
 struct Wires {
     wires: HashSet<Wire>,
+
+    /// Wires of each node.
+    /// Allows to look up wires of a node without iterating over all wires.
+    by_node: HashMap<NodeId, Vec<Wire>>,
+
+    /// Changes whenever set of wires changes.
+    /// Unique across all graphs, so data cached for one graph is not used for another.
+    revision: u64,
+}
+
+/// Returns rect of the node's frame in graph space.
+///
+/// Frame is taken from `measurement`, or built around set size of the node,
+/// or node is treated as a point.
+fn measured_node_rect<T>(id: NodeId, node: &Node<T>, measurement: &SnarlMeasurement) -> egui::Rect {
+    match (measurement.nodes.get(&id), node.size) {
+        (Some(m), _) => egui::Rect::from_min_size(node.pos + m.offset, m.size),
+        (None, Some(size)) => egui::Rect::from_min_size(node.pos, size) + measurement.node_margin,
+        (None, None) => egui::Rect::from_min_size(node.pos, egui::Vec2::ZERO),
+    }
+}
+
+/// Checks connection against rules configured in the style.
+///
+/// `is_reachable` checks if the second node can be reached from the first one following wires.
+fn check_rules_with(
+    from: OutPinId,
+    to: InPinId,
+    style: &SnarlStyle,
+    is_reachable: impl FnOnce(NodeId, NodeId) -> bool,
+) -> Result<(), ConnectError> {
+    if !style.get_allow_self_loops() && from.node == to.node {
+        return Err(ConnectError::SelfLoop);
+    }
+
+    // New wire closes a cycle if its source is reachable from its target.
+    if style.get_prevent_cycles() && (from.node == to.node || is_reachable(to.node, from.node)) {
+        return Err(ConnectError::Cycle);
+    }
+
+    Ok(())
+}
+
+/// Checks if `target` node can be reached from `source` node in map of node successors.
+fn reaches(successors: &HashMap<NodeId, Vec<NodeId>>, source: NodeId, target: NodeId) -> bool {
+    let mut visited = HashSet::default();
+    let mut stack = vec![source];
+
+    while let Some(node) = stack.pop() {
+        if node == target {
+            return true;
+        }
+        if !visited.insert(node) {
+            continue;
+        }
+        if let Some(next) = successors.get(&node) {
+            stack.extend(next.iter().copied());
+        }
+    }
+
+    false
+}
+
+/// Returns revision of wires that was not used before.
+fn next_wires_revision() -> u64 {
+    static REVISION: AtomicU64 = AtomicU64::new(0);
+    REVISION.fetch_add(1, Ordering::Relaxed)
 }
 
 #[cfg(feature = "serde")]
@@ -117,7 +269,7 @@ impl<'de> serde::Deserialize<'de> for Wires {
         struct Visitor;
 
         impl<'de> serde::de::Visitor<'de> for Visitor {
-            type Value = HashSet<Wire>;
+            type Value = Wires;
 
             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
                 formatter.write_str("a sequence of wires")
@@ -127,7 +279,7 @@ impl<'de> serde::Deserialize<'de> for Wires {
             where
                 A: serde::de::SeqAccess<'de>,
             {
-                let mut wires = HashSet::with_hasher(egui::ahash::RandomState::new());
+                let mut wires = Wires::new();
                 while let Some(wire) = seq.next_element()? {
                     wires.insert(wire);
                 }
@@ -135,8 +287,7 @@ impl<'de> serde::Deserialize<'de> for Wires {
             }
         }
 
-        let wires = deserializer.deserialize_seq(Visitor)?;
-        Ok(Wires { wires })
+        deserializer.deserialize_seq(Visitor)
     }
 }
 
@@ -144,46 +295,138 @@ impl Wires {
     fn new() -> Self {
         Wires {
             wires: HashSet::with_hasher(egui::ahash::RandomState::new()),
+            by_node: HashMap::with_hasher(egui::ahash::RandomState::new()),
+            revision: next_wires_revision(),
         }
     }
 
+    fn reserve(&mut self, additional: usize) {
+        self.wires.reserve(additional);
+    }
+
+    /// Inserts many wires, changing revision once.
+    fn extend(&mut self, wires: impl IntoIterator<Item = Wire>) {
+        let len = self.wires.len();
+
+        for wire in wires {
+            if self.wires.insert(wire) {
+                self.link(wire);
+            }
+        }
+
+        if self.wires.len() != len {
+            self.revision = next_wires_revision();
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.wires.len()
+    }
+
     fn insert(&mut self, wire: Wire) -> bool {
-        self.wires.insert(wire)
+        if !self.wires.insert(wire) {
+            return false;
+        }
+
+        self.link(wire);
+        self.revision = next_wires_revision();
+        true
+    }
+
+    /// Adds wire to wires lists of its nodes.
+    fn link(&mut self, wire: Wire) {
+        self.by_node
+            .entry(wire.out_pin.node)
+            .or_default()
+            .push(wire);
+
+        if wire.in_pin.node != wire.out_pin.node {
+            self.by_node.entry(wire.in_pin.node).or_default().push(wire);
+        }
     }
 
     fn remove(&mut self, wire: &Wire) -> bool {
-        self.wires.remove(wire)
+        if !self.wires.remove(wire) {
+            return false;
+        }
+
+        self.unlink(wire.out_pin.node, wire);
+        self.unlink(wire.in_pin.node, wire);
+        self.revision = next_wires_revision();
+        true
     }
 
-    fn drop_node(&mut self, node: NodeId) -> usize {
-        let count = self.wires.len();
-        self.wires
-            .retain(|wire| wire.out_pin.node != node && wire.in_pin.node != node);
-        count - self.wires.len()
+    /// Removes wire from the node's wires list.
+    fn unlink(&mut self, node: NodeId, wire: &Wire) {
+        if let Some(wires) = self.by_node.get_mut(&node) {
+            wires.retain(|w| w != wire);
+            if wires.is_empty() {
+                self.by_node.remove(&node);
+            }
+        }
+    }
+
+    fn contains(&self, wire: &Wire) -> bool {
+        self.wires.contains(wire)
+    }
+
+    /// Iterates over wires connected to the node.
+    fn node_wires(&self, node: NodeId) -> impl Iterator<Item = Wire> + '_ {
+        self.by_node.get(&node).into_iter().flatten().copied()
+    }
+
+    fn drop_node(&mut self, node: NodeId) -> Vec<Wire> {
+        let Some(wires) = self.by_node.remove(&node) else {
+            return Vec::new();
+        };
+
+        for wire in &wires {
+            self.wires.remove(wire);
+
+            let other = if wire.out_pin.node == node {
+                wire.in_pin.node
+            } else {
+                wire.out_pin.node
+            };
+            self.unlink(other, wire);
+        }
+
+        self.revision = next_wires_revision();
+        wires
     }
 
     fn drop_inputs(&mut self, pin: InPinId) -> usize {
-        let count = self.wires.len();
-        self.wires.retain(|wire| wire.in_pin != pin);
-        count - self.wires.len()
+        let wires = self
+            .node_wires(pin.node)
+            .filter(|wire| wire.in_pin == pin)
+            .collect::<Vec<_>>();
+
+        for wire in &wires {
+            self.remove(wire);
+        }
+        wires.len()
     }
 
     fn drop_outputs(&mut self, pin: OutPinId) -> usize {
-        let count = self.wires.len();
-        self.wires.retain(|wire| wire.out_pin != pin);
-        count - self.wires.len()
+        let wires = self
+            .node_wires(pin.node)
+            .filter(|wire| wire.out_pin == pin)
+            .collect::<Vec<_>>();
+
+        for wire in &wires {
+            self.remove(wire);
+        }
+        wires.len()
     }
 
     fn wired_inputs(&self, out_pin: OutPinId) -> impl Iterator<Item = InPinId> + '_ {
-        self.wires
-            .iter()
+        self.node_wires(out_pin.node)
             .filter(move |wire| wire.out_pin == out_pin)
             .map(|wire| (wire.in_pin))
     }
 
     fn wired_outputs(&self, in_pin: InPinId) -> impl Iterator<Item = OutPinId> + '_ {
-        self.wires
-            .iter()
+        self.node_wires(in_pin.node)
             .filter(move |wire| wire.in_pin == in_pin)
             .map(|wire| (wire.out_pin))
     }
@@ -191,18 +434,330 @@ impl Wires {
     fn iter(&self) -> impl Iterator<Item = Wire> + '_ {
         self.wires.iter().copied()
     }
+
+    /// Collects nodes directly connected to outputs of each node.
+    fn successors(&self) -> HashMap<NodeId, Vec<NodeId>> {
+        let mut successors = HashMap::<NodeId, Vec<NodeId>>::default();
+        for wire in &self.wires {
+            successors
+                .entry(wire.out_pin.node)
+                .or_default()
+                .push(wire.in_pin.node);
+        }
+        successors
+    }
+}
+
+/// Placement of a node in [`SnarlLayout`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodePlacement {
+    /// Position of the top-left corner of the node.
+    pub pos: Pos2,
+
+    /// Flag indicating that the node is open - not collapsed.
+    pub open: bool,
+
+    /// Size of the node set by resizing it.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub size: Option<egui::Vec2>,
+}
+
+/// Layout of the graph independent of node values.
+///
+/// Allows persisting graph structure and layout separately from nodes.
+/// See [`Snarl::layout_snapshot`] and [`Snarl::apply_layout`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnarlLayout {
+    /// Placement of each node.
+    pub nodes: HashMap<NodeId, NodePlacement>,
+
+    /// Wires between nodes.
+    pub wires: Vec<(OutPinId, InPinId)>,
+}
+
+/// Nodes and wires of the graph without their placement.
+///
+/// Positions change with every edit of the layout,
+/// so leaving them out keeps diffs of version-controlled graphs limited to logical changes.
+/// Nodes and wires are sorted, so serialized form does not depend on insertion order.
+/// See [`Snarl::serialize_topology`] and [`Snarl::merge_topology`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnarlTopology<T> {
+    /// Values of nodes, sorted by their ids.
+    pub nodes: Vec<(NodeId, T)>,
+
+    /// Wires between nodes, sorted.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wires: Vec<(OutPinId, InPinId)>,
+}
+
+/// Error returned by [`Snarl::merge_topology`] when topology is invalid.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TopologyError {
+    /// Node is listed more than once.
+    DuplicateNode {
+        /// Id of the node.
+        node: NodeId,
+    },
+}
+
+impl std::fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            TopologyError::DuplicateNode { node } => {
+                write!(f, "node {} is listed more than once", node.0)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TopologyError {}
+
+/// Distance between nodes placed by [`Snarl::merge_topology`].
+const TOPOLOGY_NODE_SPACING: egui::Vec2 = egui::vec2(200.0, 100.0);
+
+/// Node of [`GraphDescription`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeDescription {
+    /// Kind of the node, passed to the constructor of node value.
+    pub kind: String,
+
+    /// Position of the top-left corner of the node.
+    pub pos: Pos2,
+
+    /// Number of inputs of the node.
+    ///
+    /// If set, wires to inputs beyond it are rejected before node value is made.
+    /// Otherwise number of inputs is taken from [`SnarlViewer::inputs`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub inputs: Option<usize>,
+
+    /// Number of outputs of the node.
+    ///
+    /// If set, wires from outputs beyond it are rejected before node value is made.
+    /// Otherwise number of outputs is taken from [`SnarlViewer::outputs`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub outputs: Option<usize>,
+}
+
+/// Wire of [`GraphDescription`].
+///
+/// Nodes are referenced by their index in [`GraphDescription::nodes`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WireDescription {
+    /// Index of the node the wire starts from.
+    pub from_node: usize,
+
+    /// Output of the node the wire starts from.
+    pub output: usize,
+
+    /// Index of the node the wire ends at.
+    pub to_node: usize,
+
+    /// Input of the node the wire ends at.
+    pub input: usize,
+}
+
+/// Description of the graph independent of node type.
+///
+/// Allows loading graphs authored elsewhere.
+/// See [`Snarl::from_description`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GraphDescription {
+    /// Nodes of the graph.
+    pub nodes: Vec<NodeDescription>,
+
+    /// Wires between nodes.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub wires: Vec<WireDescription>,
+}
+
+/// Error returned by [`Snarl::from_description`] when description is invalid.
+///
+/// Wires are referenced by their index in [`GraphDescription::wires`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DescriptionError {
+    /// Wire references node that is not in the description.
+    NodeNotFound {
+        /// Index of the wire.
+        wire: usize,
+
+        /// Index of the missing node.
+        node: usize,
+    },
+
+    /// Wire starts from output beyond number of node's outputs.
+    OutputOutOfRange {
+        /// Index of the wire.
+        wire: usize,
+
+        /// Index of the node.
+        node: usize,
+
+        /// Index of the output.
+        output: usize,
+    },
+
+    /// Wire ends at input beyond number of node's inputs.
+    InputOutOfRange {
+        /// Index of the wire.
+        wire: usize,
+
+        /// Index of the node.
+        node: usize,
+
+        /// Index of the input.
+        input: usize,
+    },
+}
+
+impl std::fmt::Display for DescriptionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            DescriptionError::NodeNotFound { wire, node } => {
+                write!(f, "wire {wire} references missing node {node}")
+            }
+            DescriptionError::OutputOutOfRange { wire, node, output } => {
+                write!(
+                    f,
+                    "wire {wire} starts from output {output} of node {node} that is out of range"
+                )
+            }
+            DescriptionError::InputOutOfRange { wire, node, input } => {
+                write!(
+                    f,
+                    "wire {wire} ends at input {input} of node {node} that is out of range"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for DescriptionError {}
+
+/// Measured layout of a node.
+///
+/// See [`Snarl::measure`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NodeMeasurement {
+    /// Offset of the node's frame from position of the node.
+    ///
+    /// Position of the node is the corner of its content,
+    /// so the frame starts before it by the frame margin.
+    pub offset: egui::Vec2,
+
+    /// Size of the node including frame margin, in graph space.
+    pub size: egui::Vec2,
+
+    /// Offsets of input pins' centers from position of the node.
+    pub inputs: Vec<egui::Vec2>,
+
+    /// Offsets of output pins' centers from position of the node.
+    pub outputs: Vec<egui::Vec2>,
+}
+
+/// Measured layout of the graph.
+///
+/// See [`Snarl::measure`].
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SnarlMeasurement {
+    /// Measurement of each node.
+    pub nodes: HashMap<NodeId, NodeMeasurement>,
+
+    /// Margin of the default node frame.
+    ///
+    /// Added around set size of nodes missing in `nodes`.
+    pub node_margin: egui::epaint::MarginF32,
+}
+
+/// Counts of graph elements.
+///
+/// See [`Snarl::stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SnarlStats {
+    /// Number of nodes in the graph.
+    pub nodes: usize,
+
+    /// Number of wires in the graph.
+    pub wires: usize,
 }
 
 /// Snarl is generic node-graph container.
 ///
 /// It holds graph state - positioned nodes and wires between their pins.
 /// It can be rendered using [`Snarl::show`].
+///
+/// Serialized form includes position, collapsed state and size of each node,
+/// so that graph is shown the same way after loading.
+/// Wires referencing missing nodes are discarded on deserialization.
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "SerializedSnarl<T>"))]
 pub struct Snarl<T> {
     // #[cfg_attr(feature = "serde", serde(with = "serde_nodes"))]
     nodes: Slab<Node<T>>,
     wires: Wires,
+
+    /// Wire being dragged, set only during [`Snarl::show`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    wire_drag: Option<WireDrag>,
+
+    /// Animation started by [`Snarl::animate_to_layout`].
+    #[cfg_attr(feature = "serde", serde(skip))]
+    layout_animation: Option<LayoutAnimation>,
+}
+
+/// Deserialized form of [`Snarl`] before wires are validated.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct SerializedSnarl<T> {
+    nodes: Slab<Node<T>>,
+    wires: Wires,
+}
+
+#[cfg(feature = "serde")]
+impl<T> From<SerializedSnarl<T>> for Snarl<T> {
+    fn from(data: SerializedSnarl<T>) -> Self {
+        let mut wires = Wires::new();
+        for wire in data.wires.wires {
+            if data.nodes.contains(wire.out_pin.node.0) && data.nodes.contains(wire.in_pin.node.0) {
+                wires.insert(wire);
+            }
+        }
+
+        Snarl {
+            nodes: data.nodes,
+            wires,
+            wire_drag: None,
+            layout_animation: None,
+        }
+    }
+}
+
+/// Nodes moving to new positions over time.
+#[derive(Clone, Debug)]
+struct LayoutAnimation {
+    /// Start and target positions of animated nodes.
+    nodes: HashMap<NodeId, (egui::Pos2, egui::Pos2)>,
+    duration: f32,
+    elapsed: f32,
 }
 
 impl<T> Snarl<T> {
@@ -219,6 +774,8 @@ impl<T> Snarl<T> {
         Snarl {
             nodes: Slab::new(),
             wires: Wires::new(),
+            wire_drag: None,
+            layout_animation: None,
         }
     }
 
@@ -237,6 +794,7 @@ impl<T> Snarl<T> {
             value: node,
             pos,
             open: true,
+            size: None,
         });
 
         NodeId(idx)
@@ -257,11 +815,35 @@ impl<T> Snarl<T> {
             value: node,
             pos,
             open: false,
+            size: None,
         });
 
         NodeId(idx)
     }
 
+    /// Moves nodes by the same graph-space delta.
+    ///
+    /// Nodes that do not exist are skipped.
+    fn move_nodes(&mut self, nodes: &[NodeId], delta: egui::Vec2) {
+        for node in nodes {
+            if let Some(node) = self.nodes.get_mut(node.0) {
+                node.pos += delta;
+            }
+        }
+    }
+
+    /// Places nodes at their origin positions offset by the same graph-space delta.
+    ///
+    /// Unlike moving by per-frame deltas, rounding errors do not accumulate over a long drag.
+    /// Nodes that do not exist are skipped.
+    fn place_nodes(&mut self, origin: &[(NodeId, egui::Pos2)], delta: egui::Vec2) {
+        for &(node, pos) in origin {
+            if let Some(node) = self.nodes.get_mut(node.0) {
+                node.pos = pos + delta;
+            }
+        }
+    }
+
     /// Opens or collapses a node.
     ///
     /// # Panics
@@ -272,558 +854,4740 @@ impl<T> Snarl<T> {
         self.nodes[node.0].open = open;
     }
 
-    /// Removes a node from the Snarl.
-    /// Returns the node if it was removed.
+    /// Sets size of a node, or resets it to fit content if `None`.
+    ///
+    /// Size does not include frame margin.
+    /// When height is smaller than content, node body becomes scrollable.
     ///
     /// # Panics
     ///
     /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn set_node_size(&mut self, node: NodeId, size: Option<egui::Vec2>) {
+        self.nodes[node.0].size = size;
+    }
+
+    /// Returns wire being dragged.
+    ///
+    /// This is available only during [`Snarl::show`],
+    /// so viewer methods can react to the drag, e.g. to implement custom drop zones.
+    /// Returns `None` outside of [`Snarl::show`] or if no wire is dragged.
+    #[must_use]
+    pub const fn current_wire_drag(&self) -> Option<&WireDrag> {
+        self.wire_drag.as_ref()
+    }
+
+    /// Returns layout of the graph independent of node values.
     ///
     /// # Examples
     ///
     /// ```
     /// # use egui_snarl::Snarl;
     /// let mut snarl = Snarl::<()>::new();
-    /// let node = snarl.insert_node(egui::pos2(0.0, 0.0), ());
-    /// snarl.remove_node(node);
+    /// let node = snarl.insert_node(egui::pos2(1.0, 2.0), ());
+    ///
+    /// let layout = snarl.layout_snapshot();
+    /// assert_eq!(layout.nodes[&node].pos, egui::pos2(1.0, 2.0));
     /// ```
-    #[track_caller]
-    pub fn remove_node(&mut self, idx: NodeId) -> T {
-        let value = self.nodes.remove(idx.0).value;
-        self.wires.drop_node(idx);
-        value
+    #[must_use]
+    pub fn layout_snapshot(&self) -> SnarlLayout {
+        SnarlLayout {
+            nodes: self
+                .nodes
+                .iter()
+                .map(|(idx, node)| {
+                    (
+                        NodeId(idx),
+                        NodePlacement {
+                            pos: node.pos,
+                            open: node.open,
+                            size: node.size,
+                        },
+                    )
+                })
+                .collect(),
+            wires: self
+                .wires
+                .iter()
+                .map(|wire| (wire.out_pin, wire.in_pin))
+                .collect(),
+        }
     }
 
-    /// Connects two nodes.
-    /// Returns true if the connection was successful.
-    /// Returns false if the connection already exists.
-    ///
-    /// # Panics
+    /// Applies layout to the graph.
     ///
-    /// Panics if either node does not exist.
-    #[track_caller]
-    pub fn connect(&mut self, from: OutPinId, to: InPinId) -> bool {
-        assert!(self.nodes.contains(from.node.0));
-        assert!(self.nodes.contains(to.node.0));
+    /// Nodes not present in the graph are skipped,
+    /// and nodes not present in the layout keep their placement.
+    /// Wires are replaced with wires from the layout, except those connecting missing nodes.
+    pub fn apply_layout(&mut self, layout: &SnarlLayout) {
+        for (id, placement) in &layout.nodes {
+            if let Some(node) = self.nodes.get_mut(id.0) {
+                node.pos = placement.pos;
+                node.open = placement.open;
+                node.size = placement.size;
+            }
+        }
 
-        let wire = Wire {
-            out_pin: from,
-            in_pin: to,
-        };
-        self.wires.insert(wire)
+        self.wires = Wires::new();
+
+        for &(out_pin, in_pin) in &layout.wires {
+            if self.nodes.contains(out_pin.node.0) && self.nodes.contains(in_pin.node.0) {
+                self.wires.insert(Wire { out_pin, in_pin });
+            }
+        }
     }
 
-    /// Disconnects two nodes.
-    /// Returns true if the connection was removed.
+    /// Returns nodes and wires of the graph without their placement.
     ///
-    /// # Panics
+    /// Serialize it to track logical changes of the graph
+    /// and apply them back with [`Snarl::merge_topology`].
     ///
-    /// Panics if either node does not exist.
-    #[track_caller]
-    pub fn disconnect(&mut self, from: OutPinId, to: InPinId) -> bool {
-        assert!(self.nodes.contains(from.node.0));
-        assert!(self.nodes.contains(to.node.0));
-
-        let wire = Wire {
-            out_pin: from,
-            in_pin: to,
-        };
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::new();
+    /// let a = snarl.insert_node(egui::pos2(10.0, 0.0), "a");
+    /// let b = snarl.insert_node(egui::pos2(0.0, 10.0), "b");
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    ///
+    /// let topology = snarl.serialize_topology();
+    /// assert_eq!(topology.nodes, [(a, "a"), (b, "b")]);
+    /// assert_eq!(topology.wires.len(), 1);
+    /// ```
+    #[must_use]
+    pub fn serialize_topology(&self) -> SnarlTopology<T>
+    where
+        T: Clone,
+    {
+        let mut nodes = self
+            .nodes
+            .iter()
+            .map(|(idx, node)| (NodeId(idx), node.value.clone()))
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|&(id, _)| id);
 
-        self.wires.remove(&wire)
+        let mut wires = self
+            .wires
+            .iter()
+            .map(|wire| (wire.out_pin, wire.in_pin))
+            .collect::<Vec<_>>();
+        wires.sort();
+
+        SnarlTopology { nodes, wires }
     }
 
-    /// Removes all connections to the node's pin.
+    /// Replaces nodes and wires of the graph with ones from `topology`, keeping placement of nodes.
     ///
-    /// Returns number of removed connections.
+    /// Nodes present in both keep their position, openness and size and get value from `topology`.
+    /// Nodes missing in `topology` are removed.
+    /// New nodes are placed next to already placed nodes wired to them,
+    /// or below the graph if there are none.
+    /// Use [`Snarl::resolve_overlaps`] to separate them after they are measured.
+    /// Wires connecting nodes missing in `topology` are skipped.
+    ///
+    /// New nodes keep their ids from `topology`, unless the id is so large
+    /// that most of the graph's id space would stay vacant.
+    /// Such nodes get free ids instead and their wires follow them.
+    ///
+    /// Returns ids of new nodes in the graph.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`TopologyError::DuplicateNode`] if `topology` lists the same node twice.
+    /// The graph is not changed in this case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::new();
+    /// let a = snarl.insert_node(egui::pos2(10.0, 0.0), 1);
+    /// let mut topology = snarl.serialize_topology();
+    /// topology.nodes[0].1 = 2;
+    ///
+    /// snarl.get_node_info_mut(a).unwrap().pos = egui::pos2(50.0, 50.0);
+    /// assert!(snarl.merge_topology(topology).unwrap().is_empty());
+    /// assert_eq!(snarl[a], 2);
+    /// assert_eq!(snarl.get_node_info(a).unwrap().pos, egui::pos2(50.0, 50.0));
+    /// ```
+    pub fn merge_topology(
+        &mut self,
+        topology: SnarlTopology<T>,
+    ) -> Result<Vec<NodeId>, TopologyError> {
+        let mut seen = HashSet::default();
+        for &(id, _) in &topology.nodes {
+            if !seen.insert(id) {
+                return Err(TopologyError::DuplicateNode { node: id });
+            }
+        }
+
+        // Ids beyond this leave most of the slab vacant.
+        let old_bound = self.nodes.iter().map(|(idx, _)| idx + 1).max().unwrap_or(0);
+        let max_id = (old_bound + topology.nodes.len()) * 2;
+
+        let mut old = std::mem::take(&mut self.nodes);
+        let mut placed = HashMap::<NodeId, Pos2>::default();
+        let mut nodes = Vec::with_capacity(topology.nodes.len());
+        let mut remapped = Vec::new();
+        let mut added = Vec::new();
+
+        for (id, value) in topology.nodes {
+            match old.contains(id.0).then(|| old.remove(id.0)) {
+                Some(node) => {
+                    placed.insert(id, node.pos);
+                    nodes.push((id.0, Node { value, ..node }));
+                }
+                None => {
+                    let node = Node {
+                        value,
+                        pos: Pos2::ZERO,
+                        open: true,
+                        size: None,
+                    };
+
+                    if id.0 < max_id {
+                        added.push(id);
+                        nodes.push((id.0, node));
+                    } else {
+                        remapped.push((id, node));
+                    }
+                }
+            }
+        }
+
+        self.nodes = nodes.into_iter().collect();
+
+        let mut ids = HashMap::<NodeId, NodeId>::default();
+        for (id, node) in remapped {
+            let new_id = NodeId(self.nodes.insert(node));
+            ids.insert(id, new_id);
+            added.push(new_id);
+        }
+
+        // Wires are filtered by ids from `topology`,
+        // as remapped nodes may take ids of nodes missing in it.
+        let wires = topology
+            .wires
+            .into_iter()
+            .filter(|(out_pin, in_pin)| seen.contains(&out_pin.node) && seen.contains(&in_pin.node))
+            .map(|(mut out_pin, mut in_pin)| {
+                out_pin.node = ids.get(&out_pin.node).copied().unwrap_or(out_pin.node);
+                in_pin.node = ids.get(&in_pin.node).copied().unwrap_or(in_pin.node);
+                (out_pin, in_pin)
+            })
+            .collect::<Vec<_>>();
+
+        // New nodes go below the graph, unless they are wired to placed nodes.
+        let mut below = placed
+            .values()
+            .fold(None, |bottom: Option<Pos2>, pos| {
+                Some(bottom.map_or(*pos, |bottom| {
+                    egui::pos2(bottom.x.min(pos.x), bottom.y.max(pos.y))
+                }))
+            })
+            .map_or(Pos2::ZERO, |bottom| {
+                bottom + egui::vec2(0.0, TOPOLOGY_NODE_SPACING.y)
+            });
+
+        added.sort();
+        for &id in &added {
+            let source = wires
+                .iter()
+                .filter(|(out_pin, in_pin)| in_pin.node == id && out_pin.node != id)
+                .find_map(|(out_pin, _)| placed.get(&out_pin.node));
+            let target = wires
+                .iter()
+                .filter(|(out_pin, in_pin)| out_pin.node == id && in_pin.node != id)
+                .find_map(|(_, in_pin)| placed.get(&in_pin.node));
+
+            let mut pos = match (source, target) {
+                (Some(&pos), _) => pos + egui::vec2(TOPOLOGY_NODE_SPACING.x, 0.0),
+                (None, Some(&pos)) => pos - egui::vec2(TOPOLOGY_NODE_SPACING.x, 0.0),
+                (None, None) => {
+                    let pos = below;
+                    below.y += TOPOLOGY_NODE_SPACING.y;
+                    pos
+                }
+            };
+
+            // Nodes placed next to the same node are stacked.
+            while placed.values().any(|&other| other == pos) {
+                pos.y += TOPOLOGY_NODE_SPACING.y;
+            }
+
+            placed.insert(id, pos);
+            self.nodes[id.0].pos = pos;
+        }
+
+        self.wires = Wires::new();
+        for (out_pin, in_pin) in wires {
+            self.wires.insert(Wire { out_pin, in_pin });
+        }
+
+        Ok(added)
+    }
+
+    /// Builds graph from description, creating node values with `make`.
+    ///
+    /// Nodes are created in order of the description,
+    /// and `make` is called with kind of each node.
+    /// Wires are checked against declared numbers of pins before any node value is made.
+    /// Pins of nodes without declared numbers are checked against
+    /// [`SnarlViewer::inputs`] and [`SnarlViewer::outputs`] of made values.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if a wire references a node missing in the description,
+    /// or a pin beyond node's number of inputs or outputs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{ui::{PinInfo, SnarlViewer}, GraphDescription, InPin, NodeDescription, OutPin, Snarl, WireDescription};
+    /// # struct Viewer;
+    /// # impl SnarlViewer<String> for Viewer {
+    /// #     fn title(&mut self, node: &String) -> String { node.clone() }
+    /// #     fn inputs(&mut self, node: &String) -> usize { usize::from(node == "sink") }
+    /// #     fn outputs(&mut self, node: &String) -> usize { usize::from(node == "number") }
+    /// #     fn show_input(&mut self, _: &InPin, _: &mut egui::Ui, _: f32, _: &mut Snarl<String>) -> PinInfo { PinInfo::default() }
+    /// #     fn show_output(&mut self, _: &OutPin, _: &mut egui::Ui, _: f32, _: &mut Snarl<String>) -> PinInfo { PinInfo::default() }
+    /// # }
+    /// let node = |kind: &str| NodeDescription {
+    ///     kind: kind.to_owned(),
+    ///     pos: egui::pos2(0.0, 0.0),
+    ///     inputs: None,
+    ///     outputs: None,
+    /// };
+    /// let mut desc = GraphDescription {
+    ///     nodes: vec![node("number"), node("sink")],
+    ///     wires: vec![WireDescription { from_node: 0, output: 0, to_node: 1, input: 0 }],
+    /// };
+    ///
+    /// let snarl = Snarl::from_description(&desc, &mut Viewer, str::to_owned).unwrap();
+    /// assert_eq!(snarl.wire_count(), 1);
+    ///
+    /// // "sink" has only one input.
+    /// desc.wires[0].input = 1;
+    /// assert!(Snarl::from_description(&desc, &mut Viewer, str::to_owned).is_err());
+    /// ```
+    pub fn from_description<V, F>(
+        desc: &GraphDescription,
+        viewer: &mut V,
+        mut make: F,
+    ) -> Result<Self, DescriptionError>
+    where
+        V: SnarlViewer<T>,
+        F: FnMut(&str) -> T,
+    {
+        for (idx, wire) in desc.wires.iter().enumerate() {
+            let from = desc
+                .nodes
+                .get(wire.from_node)
+                .ok_or(DescriptionError::NodeNotFound {
+                    wire: idx,
+                    node: wire.from_node,
+                })?;
+            let to = desc
+                .nodes
+                .get(wire.to_node)
+                .ok_or(DescriptionError::NodeNotFound {
+                    wire: idx,
+                    node: wire.to_node,
+                })?;
+
+            if from.outputs.is_some_and(|outputs| wire.output >= outputs) {
+                return Err(DescriptionError::OutputOutOfRange {
+                    wire: idx,
+                    node: wire.from_node,
+                    output: wire.output,
+                });
+            }
+            if to.inputs.is_some_and(|inputs| wire.input >= inputs) {
+                return Err(DescriptionError::InputOutOfRange {
+                    wire: idx,
+                    node: wire.to_node,
+                    input: wire.input,
+                });
+            }
+        }
+
+        let values = desc
+            .nodes
+            .iter()
+            .map(|node| make(&node.kind))
+            .collect::<Vec<_>>();
+
+        // Nodes without declared pins have as many as viewer shows.
+        for (idx, wire) in desc.wires.iter().enumerate() {
+            if desc.nodes[wire.from_node].outputs.is_none()
+                && wire.output >= viewer.outputs(&values[wire.from_node])
+            {
+                return Err(DescriptionError::OutputOutOfRange {
+                    wire: idx,
+                    node: wire.from_node,
+                    output: wire.output,
+                });
+            }
+            if desc.nodes[wire.to_node].inputs.is_none()
+                && wire.input >= viewer.inputs(&values[wire.to_node])
+            {
+                return Err(DescriptionError::InputOutOfRange {
+                    wire: idx,
+                    node: wire.to_node,
+                    input: wire.input,
+                });
+            }
+        }
+
+        let mut snarl = Snarl::new();
+        let ids = desc
+            .nodes
+            .iter()
+            .zip(values)
+            .map(|(node, value)| snarl.insert_node(node.pos, value))
+            .collect::<Vec<_>>();
+
+        for wire in &desc.wires {
+            snarl.connect(
+                OutPinId {
+                    node: ids[wire.from_node],
+                    output: wire.output,
+                },
+                InPinId {
+                    node: ids[wire.to_node],
+                    input: wire.input,
+                },
+            );
+        }
+
+        Ok(snarl)
+    }
+
+    /// Computes positions that push overlapping nodes apart
+    /// until there is at least `min_gap` between each pair of them.
+    ///
+    /// Node frames are taken from `measurement`, see [`Snarl::measure`].
+    /// Nodes missing in it use their set size with [`SnarlMeasurement::node_margin`],
+    /// or are treated as points.
+    ///
+    /// Overlapping nodes are separated along the axis of the smallest overlap.
+    /// Result is deterministic, and the number of iterations is bounded,
+    /// so some overlaps may remain in very dense graphs.
+    ///
+    /// Returns new positions of moved nodes only.
+    /// Apply them directly or with [`Snarl::animate_to_layout`].
+    #[must_use]
+    pub fn resolve_overlaps(
+        &self,
+        measurement: &SnarlMeasurement,
+        min_gap: f32,
+    ) -> HashMap<NodeId, egui::Pos2> {
+        const MAX_ITERATIONS: usize = 64;
+
+        let gap = min_gap.max(0.0);
+
+        // Slab iterates in order of ids, which keeps result deterministic.
+        let mut rects = self
+            .nodes
+            .iter()
+            .map(|(idx, node)| {
+                let id = NodeId(idx);
+                (id, measured_node_rect(id, node, measurement))
+            })
+            .collect::<Vec<_>>();
+
+        for _ in 0..MAX_ITERATIONS {
+            let mut moved = false;
+
+            for i in 0..rects.len() {
+                for j in i + 1..rects.len() {
+                    let a = rects[i].1;
+                    let b = rects[j].1;
+
+                    let overlap_x = a.max.x.min(b.max.x) - a.min.x.max(b.min.x) + gap;
+                    let overlap_y = a.max.y.min(b.max.y) - a.min.y.max(b.min.y) + gap;
+
+                    if overlap_x <= 0.0 || overlap_y <= 0.0 {
+                        continue;
+                    }
+
+                    // Nodes at the same place are separated by their order.
+                    let dir = b.center() - a.center();
+                    let push = if overlap_x <= overlap_y {
+                        let sign = if dir.x < 0.0 { -1.0 } else { 1.0 };
+                        egui::vec2(overlap_x * 0.5 * sign, 0.0)
+                    } else {
+                        let sign = if dir.y < 0.0 { -1.0 } else { 1.0 };
+                        egui::vec2(0.0, overlap_y * 0.5 * sign)
+                    };
+
+                    rects[i].1 = a.translate(-push);
+                    rects[j].1 = b.translate(push);
+                    moved = true;
+                }
+            }
+
+            if !moved {
+                break;
+            }
+        }
+
+        // Frames of nodes start before their positions.
+        rects
+            .into_iter()
+            .filter_map(|(id, rect)| {
+                let node = &self.nodes[id.0];
+                let pos = node.pos + (rect.min - measured_node_rect(id, node, measurement).min);
+                (pos != node.pos).then_some((id, pos))
+            })
+            .collect()
+    }
+
+    /// Moves nodes to `targets` smoothly over `duration` seconds.
+    ///
+    /// Positions are interpolated in [`Snarl::show`], which requests repaint until animation ends.
+    /// Nodes not present in `targets` stay in place, and dragging a node stops its animation.
+    /// New animation replaces one in progress, starting from current positions.
+    /// Animated nodes are not reported to [`SnarlViewer::on_nodes_moved`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<()>::new();
+    /// let node = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    ///
+    /// let mut targets = egui::ahash::HashMap::default();
+    /// targets.insert(node, egui::pos2(100.0, 0.0));
+    ///
+    /// // Zero duration moves nodes immediately.
+    /// snarl.animate_to_layout(&targets, 0.0);
+    /// assert_eq!(snarl.get_node_info(node).unwrap().pos, egui::pos2(100.0, 0.0));
+    /// ```
+    pub fn animate_to_layout(&mut self, targets: &HashMap<NodeId, egui::Pos2>, duration: f32) {
+        let nodes = targets
+            .iter()
+            .filter_map(|(&id, &target)| Some((id, (self.nodes.get(id.0)?.pos, target))))
+            .collect();
+
+        self.layout_animation = Some(LayoutAnimation {
+            nodes,
+            duration,
+            elapsed: 0.0,
+        });
+
+        if duration <= 0.0 {
+            self.advance_layout_animation(0.0);
+        }
+    }
+
+    /// Advances layout animation by `dt` seconds.
+    /// Returns `true` if animation is still in progress.
+    fn advance_layout_animation(&mut self, dt: f32) -> bool {
+        let Some(animation) = &mut self.layout_animation else {
+            return false;
+        };
+
+        animation.elapsed += dt;
+
+        let t = if animation.duration > 0.0 {
+            (animation.elapsed / animation.duration).min(1.0)
+        } else {
+            1.0
+        };
+        let t = egui::emath::easing::cubic_in_out(t);
+
+        animation.nodes.retain(|id, &mut (start, target)| {
+            let Some(node) = self.nodes.get_mut(id.0) else {
+                return false;
+            };
+            node.pos = start.lerp(target, t);
+            true
+        });
+
+        if t >= 1.0 || animation.nodes.is_empty() {
+            self.layout_animation = None;
+            return false;
+        }
+
+        true
+    }
+
+    /// Stops layout animation of given nodes, leaving them at current positions.
+    fn stop_layout_animation(&mut self, nodes: &[NodeId]) {
+        if let Some(animation) = &mut self.layout_animation {
+            for node in nodes {
+                animation.nodes.remove(node);
+            }
+        }
+    }
+
+    /// Removes a node from the Snarl.
+    /// Returns the node if it was removed.
     ///
     /// # Panics
     ///
     /// Panics if the node does not exist.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// let mut snarl = Snarl::<()>::new();
+    /// let node = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// snarl.remove_node(node);
+    /// ```
     #[track_caller]
-    pub fn drop_inputs(&mut self, pin: InPinId) -> usize {
-        assert!(self.nodes.contains(pin.node.0));
-        self.wires.drop_inputs(pin)
+    pub fn remove_node(&mut self, idx: NodeId) -> T {
+        let value = self.nodes.remove(idx.0).value;
+        self.wires.drop_node(idx);
+        value
     }
 
-    /// Removes all connections from the node's pin.
-    /// Returns number of removed connections.
+    /// Checks if output pin is connected to input pin.
+    #[must_use]
+    pub fn has_wire(&self, from: OutPinId, to: InPinId) -> bool {
+        self.wires.contains(&Wire {
+            out_pin: from,
+            in_pin: to,
+        })
+    }
+
+    /// Connects two nodes.
+    /// Returns true if the connection was successful.
+    /// Returns false if the connection already exists.
     ///
     /// # Panics
     ///
-    /// Panics if the node does not exist.
+    /// Panics if either node does not exist.
     #[track_caller]
-    pub fn drop_outputs(&mut self, pin: OutPinId) -> usize {
-        assert!(self.nodes.contains(pin.node.0));
-        self.wires.drop_outputs(pin)
+    pub fn connect(&mut self, from: OutPinId, to: InPinId) -> bool {
+        assert!(self.nodes.contains(from.node.0));
+        assert!(self.nodes.contains(to.node.0));
+
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+        self.wires.insert(wire)
+    }
+
+    /// Connects two nodes after validating the connection.
+    ///
+    /// Unlike [`Snarl::connect`] this method does not panic and does not silently ignore
+    /// duplicate connections.
+    /// Pin indices are checked against pin counts reported by the viewer,
+    /// connection is checked with [`SnarlViewer::can_connect`]
+    /// and against rules configured in the style.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConnectError`] describing why the connection was rejected.
+    /// Graph is not modified in this case.
+    pub fn try_connect<V>(
+        &mut self,
+        from: OutPinId,
+        to: InPinId,
+        viewer: &mut V,
+        style: &SnarlStyle,
+    ) -> Result<(), ConnectError>
+    where
+        V: SnarlViewer<T>,
+    {
+        self.check_connect(from, to, viewer)?;
+        self.check_rules(from, to, style)?;
+
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+        self.wires.insert(wire);
+        Ok(())
+    }
+
+    /// Connects output `out_idx` of `from_node` to input `in_idx` of `to_node`
+    /// after validating the connection.
+    ///
+    /// Pin indices are as declared by [`SnarlViewer::outputs`] and [`SnarlViewer::inputs`]
+    /// for the nodes' values.
+    /// Validated the same way as [`Snarl::try_connect`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ConnectError`] describing why the connection was rejected.
+    /// Graph is not modified in this case.
+    pub fn connect_idx<V>(
+        &mut self,
+        from_node: NodeId,
+        out_idx: usize,
+        to_node: NodeId,
+        in_idx: usize,
+        viewer: &mut V,
+        style: &SnarlStyle,
+    ) -> Result<(), ConnectError>
+    where
+        V: SnarlViewer<T>,
+    {
+        let from = OutPinId {
+            node: from_node,
+            output: out_idx,
+        };
+        let to = InPinId {
+            node: to_node,
+            input: in_idx,
+        };
+        self.try_connect(from, to, viewer, style)
+    }
+
+    /// Disconnects pins if they are connected,
+    /// otherwise connects them after validating the connection.
+    ///
+    /// Connection is validated as in [`Snarl::try_connect`].
+    /// Returns what was done to the wire.
+    pub fn toggle_wire<V>(
+        &mut self,
+        from: OutPinId,
+        to: InPinId,
+        viewer: &mut V,
+        style: &SnarlStyle,
+    ) -> WireToggle
+    where
+        V: SnarlViewer<T>,
+    {
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+
+        if self.wires.remove(&wire) {
+            return WireToggle::Disconnected;
+        }
+
+        match self.try_connect(from, to, viewer, style) {
+            Ok(()) => WireToggle::Connected,
+            Err(err) => WireToggle::Rejected(err),
+        }
+    }
+
+    /// Connects many pairs of pins after validating each connection.
+    ///
+    /// Each connection is validated as in [`Snarl::try_connect`],
+    /// including against connections made earlier in the same call.
+    /// Returns rejected connections with the reasons, in the order they were given.
+    pub fn connect_many<V>(
+        &mut self,
+        wires: impl IntoIterator<Item = (OutPinId, InPinId)>,
+        viewer: &mut V,
+        style: &SnarlStyle,
+    ) -> Vec<(OutPinId, InPinId, ConnectError)>
+    where
+        V: SnarlViewer<T>,
+    {
+        let mut accepted = Vec::new();
+        let mut batch = HashSet::<Wire>::default();
+        let mut rejected = Vec::new();
+
+        // Collected once and updated with accepted wires,
+        // so cycle checks see the whole batch without walking all wires for each connection.
+        let mut successors = if style.get_prevent_cycles() {
+            self.wires.successors()
+        } else {
+            HashMap::default()
+        };
+
+        for (from, to) in wires {
+            let wire = Wire {
+                out_pin: from,
+                in_pin: to,
+            };
+
+            let result = self.check_connect(from, to, viewer).and_then(|()| {
+                if batch.contains(&wire) {
+                    return Err(ConnectError::AlreadyConnected);
+                }
+                check_rules_with(from, to, style, |source, target| {
+                    reaches(&successors, source, target)
+                })
+            });
+
+            match result {
+                Ok(()) => {
+                    if style.get_prevent_cycles() {
+                        successors.entry(from.node).or_default().push(to.node);
+                    }
+                    batch.insert(wire);
+                    accepted.push(wire);
+                }
+                Err(err) => rejected.push((from, to, err)),
+            }
+        }
+
+        // Wire index is updated once for the whole batch.
+        self.wires.reserve(accepted.len());
+        self.wires.extend(accepted);
+        rejected
+    }
+
+    fn check_connect<V>(
+        &self,
+        from: OutPinId,
+        to: InPinId,
+        viewer: &mut V,
+    ) -> Result<(), ConnectError>
+    where
+        V: SnarlViewer<T>,
+    {
+        let Some(from_node) = self.nodes.get(from.node.0) else {
+            return Err(ConnectError::NodeNotFound(from.node));
+        };
+        let Some(to_node) = self.nodes.get(to.node.0) else {
+            return Err(ConnectError::NodeNotFound(to.node));
+        };
+
+        if from.output >= viewer.outputs(&from_node.value) {
+            return Err(ConnectError::OutputOutOfRange(from));
+        }
+        if to.input >= viewer.inputs(&to_node.value) {
+            return Err(ConnectError::InputOutOfRange(to));
+        }
+
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+        if self.wires.contains(&wire) {
+            return Err(ConnectError::AlreadyConnected);
+        }
+
+        // Same check as for wires dropped in the UI.
+        if !viewer.can_connect(&OutPin::new(self, from), &InPin::new(self, to), self) {
+            return Err(ConnectError::Rejected);
+        }
+
+        Ok(())
+    }
+
+    /// Checks connection against rules configured in the style.
+    ///
+    /// This is used for both [`Snarl::try_connect`] and wires connected in the UI.
+    pub(crate) fn check_rules(
+        &self,
+        from: OutPinId,
+        to: InPinId,
+        style: &SnarlStyle,
+    ) -> Result<(), ConnectError> {
+        check_rules_with(from, to, style, |source, target| {
+            self.is_reachable(source, target)
+        })
+    }
+
+    /// Returns `true` if connecting `from` to `to` would create a cycle in the graph.
+    ///
+    /// Connection of the node to itself is a cycle as well.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    ///
+    /// assert!(snarl.would_create_cycle(OutPinId { node: b, output: 0 }, InPinId { node: a, input: 0 }));
+    /// assert!(!snarl.would_create_cycle(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 1 }));
+    /// ```
+    #[must_use]
+    pub fn would_create_cycle(&self, from: OutPinId, to: InPinId) -> bool {
+        if from.node == to.node {
+            return true;
+        }
+
+        // New wire closes a cycle if its source is reachable from its target.
+        self.is_reachable(to.node, from.node)
+    }
+
+    /// Iterates over nodes directly connected to the node by wires.
+    ///
+    /// Node connected with multiple wires is returned multiple times.
+    /// Takes time proportional to number of the node's wires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    ///
+    /// assert_eq!(snarl.neighbors(a).collect::<Vec<_>>(), [b]);
+    /// assert_eq!(snarl.neighbors(b).collect::<Vec<_>>(), [a]);
+    /// ```
+    pub fn neighbors(&self, node: NodeId) -> impl Iterator<Item = NodeId> + '_ {
+        self.wires.node_wires(node).map(move |wire| {
+            if wire.out_pin.node == node {
+                wire.in_pin.node
+            } else {
+                wire.out_pin.node
+            }
+        })
+    }
+
+    /// Returns numbers of wires connected to inputs and outputs of the node.
+    ///
+    /// Wire from node to itself is counted in both.
+    /// Returns `(0, 0)` for node without wires.
+    /// Takes time proportional to number of the node's wires.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 1 });
+    ///
+    /// assert_eq!(snarl.node_degree(a), (0, 2));
+    /// assert_eq!(snarl.node_degree(b), (2, 0));
+    /// ```
+    #[must_use]
+    pub fn node_degree(&self, node: NodeId) -> (usize, usize) {
+        self.wires
+            .node_wires(node)
+            .fold((0, 0), |(inputs, outputs), wire| {
+                (
+                    inputs + usize::from(wire.in_pin.node == node),
+                    outputs + usize::from(wire.out_pin.node == node),
+                )
+            })
+    }
+
+    /// Returns number of wires connected to inputs of the node.
+    ///
+    /// See [`Snarl::node_degree`].
+    #[must_use]
+    pub fn in_degree(&self, node: NodeId) -> usize {
+        self.node_degree(node).0
+    }
+
+    /// Returns number of wires connected to outputs of the node.
+    ///
+    /// See [`Snarl::node_degree`].
+    #[must_use]
+    pub fn out_degree(&self, node: NodeId) -> usize {
+        self.node_degree(node).1
+    }
+
+    /// Finds cycles formed by wires.
+    ///
+    /// Returns groups of nodes that are reachable from each other,
+    /// i.e. strongly connected components with more than one node,
+    /// and nodes wired to themselves.
+    /// Nodes in each group and groups themselves are sorted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    /// assert!(snarl.find_cycles().is_empty());
+    ///
+    /// snarl.connect(OutPinId { node: b, output: 0 }, InPinId { node: a, input: 0 });
+    /// assert_eq!(snarl.find_cycles(), [[a, b]]);
+    /// ```
+    #[must_use]
+    pub fn find_cycles(&self) -> Vec<Vec<NodeId>> {
+        let successors = self.wires.successors();
+        let no_successors = Vec::new();
+        let successors_of = |node| successors.get(&node).unwrap_or(&no_successors);
+
+        // Tarjan's algorithm with explicit stack of visited path.
+        // Each node gets its visiting index and lowest index reachable from it.
+        let mut indices = HashMap::<NodeId, (usize, usize)>::default();
+        let mut on_stack = HashSet::<NodeId>::default();
+        let mut stack = Vec::new();
+        let mut cycles = Vec::new();
+
+        let mut roots = successors.keys().copied().collect::<Vec<_>>();
+        roots.sort();
+
+        for root in roots {
+            if indices.contains_key(&root) {
+                continue;
+            }
+
+            indices.insert(root, (indices.len(), indices.len()));
+            stack.push(root);
+            on_stack.insert(root);
+            let mut path = vec![(root, 0)];
+
+            while let Some(&(node, next)) = path.last() {
+                if let Some(&succ) = successors_of(node).get(next) {
+                    let top = path.len() - 1;
+                    path[top].1 += 1;
+
+                    match indices.get(&succ) {
+                        None => {
+                            indices.insert(succ, (indices.len(), indices.len()));
+                            stack.push(succ);
+                            on_stack.insert(succ);
+                            path.push((succ, 0));
+                        }
+                        Some(&(succ_index, _)) if on_stack.contains(&succ) => {
+                            if let Some((_, low)) = indices.get_mut(&node) {
+                                *low = (*low).min(succ_index);
+                            }
+                        }
+                        Some(_) => {}
+                    }
+                    continue;
+                }
+
+                path.pop();
+                let (index, low) = indices[&node];
+
+                if let Some(&(parent, _)) = path.last() {
+                    if let Some((_, parent_low)) = indices.get_mut(&parent) {
+                        *parent_low = (*parent_low).min(low);
+                    }
+                }
+
+                if index == low {
+                    let mut component = Vec::new();
+                    while let Some(member) = stack.pop() {
+                        on_stack.remove(&member);
+                        component.push(member);
+                        if member == node {
+                            break;
+                        }
+                    }
+
+                    if component.len() > 1 || successors_of(node).contains(&node) {
+                        component.sort();
+                        cycles.push(component);
+                    }
+                }
+            }
+        }
+
+        cycles.sort();
+        cycles
+    }
+
+    /// Checks if `target` node can be reached from `source` node following wires
+    /// from outputs to inputs.
+    ///
+    /// Walks only wires of nodes reachable from `source` and stops as soon as `target` is found.
+    fn is_reachable(&self, source: NodeId, target: NodeId) -> bool {
+        let mut visited = HashSet::default();
+        let mut stack = vec![source];
+
+        while let Some(node) = stack.pop() {
+            if node == target {
+                return true;
+            }
+            if !visited.insert(node) {
+                continue;
+            }
+            stack.extend(
+                self.wires
+                    .node_wires(node)
+                    .filter(|wire| wire.out_pin.node == node)
+                    .map(|wire| wire.in_pin.node),
+            );
+        }
+
+        false
+    }
+
+    /// Collects `nodes` and all nodes reachable from them following wires
+    /// from outputs to inputs, or from inputs to outputs if `upstream` is `true`.
+    pub(crate) fn reachable_nodes(
+        &self,
+        nodes: impl IntoIterator<Item = NodeId>,
+        upstream: bool,
+    ) -> HashSet<NodeId> {
+        let mut visited = HashSet::default();
+        let mut stack = nodes.into_iter().collect::<Vec<_>>();
+
+        while let Some(node) = stack.pop() {
+            if !visited.insert(node) {
+                continue;
+            }
+            stack.extend(self.wires.node_wires(node).filter_map(|wire| {
+                if upstream {
+                    (wire.in_pin.node == node).then_some(wire.out_pin.node)
+                } else {
+                    (wire.out_pin.node == node).then_some(wire.in_pin.node)
+                }
+            }));
+        }
+
+        visited
+    }
+
+    /// Disconnects two nodes.
+    /// Returns true if the connection was removed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either node does not exist.
+    #[track_caller]
+    pub fn disconnect(&mut self, from: OutPinId, to: InPinId) -> bool {
+        assert!(self.nodes.contains(from.node.0));
+        assert!(self.nodes.contains(to.node.0));
+
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+
+        self.wires.remove(&wire)
+    }
+
+    /// Removes all connections to the node's pin.
+    ///
+    /// Returns number of removed connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn drop_inputs(&mut self, pin: InPinId) -> usize {
+        assert!(self.nodes.contains(pin.node.0));
+        self.wires.drop_inputs(pin)
+    }
+
+    /// Removes all connections of the node.
+    /// Returns removed connections.
+    ///
+    /// Does nothing and returns empty vector if the node has no connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn disconnect_node(&mut self, node: NodeId) -> Vec<(OutPinId, InPinId)> {
+        assert!(self.nodes.contains(node.0));
+        self.wires
+            .drop_node(node)
+            .into_iter()
+            .map(|wire| (wire.out_pin, wire.in_pin))
+            .collect()
+    }
+
+    /// Removes all connections from the node's pin.
+    /// Returns number of removed connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node does not exist.
+    #[track_caller]
+    pub fn drop_outputs(&mut self, pin: OutPinId) -> usize {
+        assert!(self.nodes.contains(pin.node.0));
+        self.wires.drop_outputs(pin)
+    }
+
+    /// Returns reference to the node.
+    #[must_use]
+    pub fn get_node(&self, idx: NodeId) -> Option<&T> {
+        self.nodes.get(idx.0).map(|node| &node.value)
+    }
+
+    /// Returns mutable reference to the node.
+    pub fn get_node_mut(&mut self, idx: NodeId) -> Option<&mut T> {
+        match self.nodes.get_mut(idx.0) {
+            Some(node) => Some(&mut node.value),
+            None => None,
+        }
+    }
+
+    /// Returns reference to the node data.
+    #[must_use]
+    pub fn get_node_info(&self, idx: NodeId) -> Option<&Node<T>> {
+        self.nodes.get(idx.0)
+    }
+
+    /// Returns mutable reference to the node data.
+    pub fn get_node_info_mut(&mut self, idx: NodeId) -> Option<&mut Node<T>> {
+        self.nodes.get_mut(idx.0)
+    }
+
+    /// Iterates over shared references to each node.
+    pub fn nodes(&self) -> NodesIter<'_, T> {
+        NodesIter {
+            nodes: self.nodes.iter(),
+        }
+    }
+
+    /// Iterates over mutable references to each node.
+    pub fn nodes_mut(&mut self) -> NodesIterMut<'_, T> {
+        NodesIterMut {
+            nodes: self.nodes.iter_mut(),
+        }
+    }
+
+    /// Iterates over shared references to each node and its position.
+    pub fn nodes_pos(&self) -> NodesPosIter<'_, T> {
+        NodesPosIter {
+            nodes: self.nodes.iter(),
+        }
+    }
+
+    /// Iterates over mutable references to each node and its position.
+    pub fn nodes_pos_mut(&mut self) -> NodesPosIterMut<'_, T> {
+        NodesPosIterMut {
+            nodes: self.nodes.iter_mut(),
+        }
+    }
+
+    /// Iterates over shared references to each node and its identifier.
+    pub fn node_ids(&self) -> NodesIdsIter<'_, T> {
+        NodesIdsIter {
+            nodes: self.nodes.iter(),
+        }
+    }
+
+    /// Iterates over mutable references to each node and its identifier.
+    pub fn nodes_ids_mut(&mut self) -> NodesIdsIterMut<'_, T> {
+        NodesIdsIterMut {
+            nodes: self.nodes.iter_mut(),
+        }
+    }
+
+    /// Iterates over shared references to each node, its position and its identifier.
+    pub fn nodes_pos_ids(&self) -> NodesPosIdsIter<'_, T> {
+        NodesPosIdsIter {
+            nodes: self.nodes.iter(),
+        }
+    }
+
+    /// Iterates over mutable references to each node, its position and its identifier.
+    pub fn nodes_pos_ids_mut(&mut self) -> NodesPosIdsIterMut<'_, T> {
+        NodesPosIdsIterMut {
+            nodes: self.nodes.iter_mut(),
+        }
+    }
+
+    /// Iterates over shared references to each node data.
+    pub fn nodes_info(&self) -> NodeInfoIter<'_, T> {
+        NodeInfoIter {
+            nodes: self.nodes.iter(),
+        }
+    }
+
+    /// Iterates over mutable references to each node data.
+    pub fn nodes_info_mut(&mut self) -> NodeInfoIterMut<'_, T> {
+        NodeInfoIterMut {
+            nodes: self.nodes.iter_mut(),
+        }
+    }
+
+    /// Iterates over shared references to each node id and data.
+    pub fn nodes_ids_data(&self) -> NodeIdsDataIter<'_, T> {
+        NodeIdsDataIter {
+            nodes: self.nodes.iter(),
+        }
+    }
+
+    /// Iterates over mutable references to each node id and data.
+    pub fn nodes_ids_data_mut(&mut self) -> NodeIdsDataIterMut<'_, T> {
+        NodeIdsDataIterMut {
+            nodes: self.nodes.iter_mut(),
+        }
+    }
+
+    /// Returns number of nodes in the graph.
+    #[must_use]
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns number of wires in the graph.
+    #[must_use]
+    pub fn wire_count(&self) -> usize {
+        self.wires.len()
+    }
+
+    /// Returns counts of graph elements.
+    ///
+    /// Number of selected nodes is part of UI state,
+    /// use [`Snarl::get_selected_nodes`] to get it.
+    #[must_use]
+    pub fn stats(&self) -> SnarlStats {
+        SnarlStats {
+            nodes: self.node_count(),
+            wires: self.wire_count(),
+        }
+    }
+
+    /// Returns bounding rect of all nodes in graph space.
+    ///
+    /// Each node spans its frame from `measurement`, which starts before node's position by frame margin.
+    /// Nodes missing in `measurement` use their set size with [`SnarlMeasurement::node_margin`],
+    /// or contribute only their position.
+    /// Use [`Snarl::measure`] to get sizes without live [`Ui`](egui::Ui),
+    /// or [`SnarlResponse::graph_bounds`](ui::SnarlResponse::graph_bounds)
+    /// for bounds computed from the last frame's layout.
+    ///
+    /// Returns `None` if graph has no nodes.
+    #[must_use]
+    pub fn bounding_box(&self, measurement: &SnarlMeasurement) -> Option<egui::Rect> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut bounds = egui::Rect::NOTHING;
+        for (idx, node) in &self.nodes {
+            bounds = bounds.union(measured_node_rect(NodeId(idx), node, measurement));
+        }
+
+        Some(bounds)
+    }
+
+    /// Returns nodes within graph-space `rect`.
+    ///
+    /// Each node spans its frame from `measurement`.
+    /// Nodes missing in it use their set size with [`SnarlMeasurement::node_margin`],
+    /// or are treated as points.
+    /// If `contained` is `true`, only nodes fully inside `rect` are returned,
+    /// otherwise nodes intersecting it are returned as well.
+    /// Edges are inclusive in both cases, so a node touching `rect` intersects it.
+    ///
+    /// Nodes are returned in order of their ids.
+    /// Takes time proportional to number of nodes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{Snarl, SnarlMeasurement};
+    /// let mut snarl = Snarl::<()>::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), ());
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), ());
+    /// snarl.set_node_size(b, Some(egui::vec2(50.0, 50.0)));
+    ///
+    /// let rect = egui::Rect::from_min_max(egui::pos2(-10.0, -10.0), egui::pos2(120.0, 20.0));
+    /// let measurement = SnarlMeasurement::default();
+    /// assert_eq!(snarl.nodes_in_rect(rect, &measurement, false), [a, b]);
+    /// assert_eq!(snarl.nodes_in_rect(rect, &measurement, true), [a]);
+    /// ```
+    #[must_use]
+    pub fn nodes_in_rect(
+        &self,
+        rect: egui::Rect,
+        measurement: &SnarlMeasurement,
+        contained: bool,
+    ) -> Vec<NodeId> {
+        self.nodes
+            .iter()
+            .filter_map(|(idx, node)| {
+                let id = NodeId(idx);
+                let node_rect = measured_node_rect(id, node, measurement);
+                let inside = if contained {
+                    rect.contains_rect(node_rect)
+                } else {
+                    rect.intersects(node_rect)
+                };
+                inside.then_some(id)
+            })
+            .collect()
+    }
+
+    /// Iterates over wires.
+    pub fn wires(&self) -> impl Iterator<Item = (OutPinId, InPinId)> + '_ {
+        self.wires.iter().map(|wire| (wire.out_pin, wire.in_pin))
+    }
+
+    /// Iterates over wires with values of nodes they connect.
+    ///
+    /// Yields output pin, input pin, value of the output's node and value of the input's node.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use egui_snarl::{InPinId, OutPinId, Snarl};
+    /// let mut snarl = Snarl::new();
+    /// let a = snarl.insert_node(egui::pos2(0.0, 0.0), 1);
+    /// let b = snarl.insert_node(egui::pos2(100.0, 0.0), 2);
+    /// snarl.connect(OutPinId { node: a, output: 0 }, InPinId { node: b, input: 0 });
+    ///
+    /// let sum: i32 = snarl.wires_with_nodes().map(|(_, _, from, to)| from + to).sum();
+    /// assert_eq!(sum, 3);
+    /// ```
+    pub fn wires_with_nodes(&self) -> impl Iterator<Item = (OutPinId, InPinId, &T, &T)> + '_ {
+        self.wires.iter().map(|wire| {
+            (
+                wire.out_pin,
+                wire.in_pin,
+                &self.nodes[wire.out_pin.node.0].value,
+                &self.nodes[wire.in_pin.node.0].value,
+            )
+        })
+    }
+
+    /// Returns input pin of the node.
+    #[must_use]
+    pub fn in_pin(&self, pin: InPinId) -> InPin {
+        InPin::new(self, pin)
+    }
+
+    /// Returns output pin of the node.
+    #[must_use]
+    pub fn out_pin(&self, pin: OutPinId) -> OutPin {
+        OutPin::new(self, pin)
+    }
+}
+
+impl<T> Index<NodeId> for Snarl<T> {
+    type Output = T;
+
+    #[inline]
+    #[track_caller]
+    fn index(&self, idx: NodeId) -> &Self::Output {
+        &self.nodes[idx.0].value
+    }
+}
+
+impl<T> IndexMut<NodeId> for Snarl<T> {
+    #[inline]
+    #[track_caller]
+    fn index_mut(&mut self, idx: NodeId) -> &mut Self::Output {
+        &mut self.nodes[idx.0].value
+    }
+}
+
+/// Iterator over shared references to nodes.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesIter<'a, T> {
+    nodes: slab::Iter<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesIter<'a, T> {
+    type Item = &'a T;
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<&'a T> {
+        let (_, node) = self.nodes.next()?;
+        Some(&node.value)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a T> {
+        let (_, node) = self.nodes.nth(n)?;
+        Some(&node.value)
+    }
+}
+
+/// Iterator over mutable references to nodes.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesIterMut<'a, T> {
+    nodes: slab::IterMut<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesIterMut<'a, T> {
+    type Item = &'a mut T;
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<&'a mut T> {
+        let (_, node) = self.nodes.next()?;
+        Some(&mut node.value)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a mut T> {
+        let (_, node) = self.nodes.nth(n)?;
+        Some(&mut node.value)
+    }
+}
+
+/// Iterator over shared references to nodes and their positions.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesPosIter<'a, T> {
+    nodes: slab::Iter<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesPosIter<'a, T> {
+    type Item = (Pos2, &'a T);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(Pos2, &'a T)> {
+        let (_, node) = self.nodes.next()?;
+        Some((node.pos, &node.value))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(Pos2, &'a T)> {
+        let (_, node) = self.nodes.nth(n)?;
+        Some((node.pos, &node.value))
+    }
+}
+
+/// Iterator over mutable references to nodes and their positions.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesPosIterMut<'a, T> {
+    nodes: slab::IterMut<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesPosIterMut<'a, T> {
+    type Item = (Pos2, &'a mut T);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(Pos2, &'a mut T)> {
+        let (_, node) = self.nodes.next()?;
+        Some((node.pos, &mut node.value))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(Pos2, &'a mut T)> {
+        let (_, node) = self.nodes.nth(n)?;
+        Some((node.pos, &mut node.value))
+    }
+}
+
+/// Iterator over shared references to nodes and their identifiers.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesIdsIter<'a, T> {
+    nodes: slab::Iter<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesIdsIter<'a, T> {
+    type Item = (NodeId, &'a T);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(NodeId, &'a T)> {
+        let (idx, node) = self.nodes.next()?;
+        Some((NodeId(idx), &node.value))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a T)> {
+        let (idx, node) = self.nodes.nth(n)?;
+        Some((NodeId(idx), &node.value))
+    }
+}
+
+/// Iterator over mutable references to nodes and their identifiers.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesIdsIterMut<'a, T> {
+    nodes: slab::IterMut<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesIdsIterMut<'a, T> {
+    type Item = (NodeId, &'a mut T);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(NodeId, &'a mut T)> {
+        let (idx, node) = self.nodes.next()?;
+        Some((NodeId(idx), &mut node.value))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a mut T)> {
+        let (idx, node) = self.nodes.nth(n)?;
+        Some((NodeId(idx), &mut node.value))
+    }
+}
+
+/// Iterator over shared references to nodes, their positions and their identifiers.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesPosIdsIter<'a, T> {
+    nodes: slab::Iter<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesPosIdsIter<'a, T> {
+    type Item = (NodeId, Pos2, &'a T);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(NodeId, Pos2, &'a T)> {
+        let (idx, node) = self.nodes.next()?;
+        Some((NodeId(idx), node.pos, &node.value))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(NodeId, Pos2, &'a T)> {
+        let (idx, node) = self.nodes.nth(n)?;
+        Some((NodeId(idx), node.pos, &node.value))
+    }
+}
+
+/// Iterator over mutable references to nodes, their positions and their identifiers.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodesPosIdsIterMut<'a, T> {
+    nodes: slab::IterMut<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodesPosIdsIterMut<'a, T> {
+    type Item = (NodeId, Pos2, &'a mut T);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(NodeId, Pos2, &'a mut T)> {
+        let (idx, node) = self.nodes.next()?;
+        Some((NodeId(idx), node.pos, &mut node.value))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(NodeId, Pos2, &'a mut T)> {
+        let (idx, node) = self.nodes.nth(n)?;
+        Some((NodeId(idx), node.pos, &mut node.value))
+    }
+}
+
+/// Iterator over shared references to nodes.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodeInfoIter<'a, T> {
+    nodes: slab::Iter<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodeInfoIter<'a, T> {
+    type Item = &'a Node<T>;
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<&'a Node<T>> {
+        let (_, node) = self.nodes.next()?;
+        Some(node)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a Node<T>> {
+        let (_, node) = self.nodes.nth(n)?;
+        Some(node)
+    }
+}
+
+/// Iterator over mutable references to nodes.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodeInfoIterMut<'a, T> {
+    nodes: slab::IterMut<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodeInfoIterMut<'a, T> {
+    type Item = &'a mut Node<T>;
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<&'a mut Node<T>> {
+        let (_, node) = self.nodes.next()?;
+        Some(node)
+    }
+
+    fn nth(&mut self, n: usize) -> Option<&'a mut Node<T>> {
+        let (_, node) = self.nodes.nth(n)?;
+        Some(node)
+    }
+}
+
+/// Iterator over shared references to nodes.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodeIdsDataIter<'a, T> {
+    nodes: slab::Iter<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodeIdsDataIter<'a, T> {
+    type Item = (NodeId, &'a Node<T>);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(NodeId, &'a Node<T>)> {
+        let (id, node) = self.nodes.next()?;
+        Some((NodeId(id), node))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a Node<T>)> {
+        let (id, node) = self.nodes.nth(n)?;
+        Some((NodeId(id), node))
+    }
+}
+
+/// Iterator over mutable references to nodes.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+pub struct NodeIdsDataIterMut<'a, T> {
+    nodes: slab::IterMut<'a, Node<T>>,
+}
+
+impl<'a, T> Iterator for NodeIdsDataIterMut<'a, T> {
+    type Item = (NodeId, &'a mut Node<T>);
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.nodes.size_hint()
+    }
+
+    fn next(&mut self) -> Option<(NodeId, &'a mut Node<T>)> {
+        let (id, node) = self.nodes.next()?;
+        Some((NodeId(id), node))
+    }
+
+    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a mut Node<T>)> {
+        let (id, node) = self.nodes.nth(n)?;
+        Some((NodeId(id), node))
+    }
+}
+
+/// Node and its output pin.
+#[derive(Clone, Debug)]
+pub struct OutPin {
+    /// Output pin identifier.
+    pub id: OutPinId,
+
+    /// List of input pins connected to this output pin.
+    pub remotes: Vec<InPinId>,
+}
+
+/// Node and its output pin.
+#[derive(Clone, Debug)]
+pub struct InPin {
+    /// Input pin identifier.
+    pub id: InPinId,
+
+    /// List of output pins connected to this input pin.
+    pub remotes: Vec<OutPinId>,
+}
+
+impl OutPin {
+    fn new<T>(snarl: &Snarl<T>, pin: OutPinId) -> Self {
+        OutPin {
+            id: pin,
+            remotes: snarl.wires.wired_inputs(pin).collect(),
+        }
+    }
+
+    /// Returns `true` if the output is connected to at least one input.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        !self.remotes.is_empty()
+    }
+}
+
+impl InPin {
+    fn new<T>(snarl: &Snarl<T>, pin: InPinId) -> Self {
+        InPin {
+            id: pin,
+            remotes: snarl.wires.wired_outputs(pin).collect(),
+        }
+    }
+
+    /// Returns `true` if the input is connected to at least one output.
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        !self.remotes.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{pos2, Ui};
+
+    use super::*;
+    use crate::ui::PinInfo;
+
+    /// Node with given number of inputs and outputs.
+    struct Pins(usize, usize);
+
+    struct PinsViewer;
+
+    impl SnarlViewer<Pins> for PinsViewer {
+        fn title(&mut self, _node: &Pins) -> String {
+            String::new()
+        }
+
+        fn inputs(&mut self, node: &Pins) -> usize {
+            node.0
+        }
+
+        fn outputs(&mut self, node: &Pins) -> usize {
+            node.1
+        }
+
+        fn show_input(
+            &mut self,
+            _pin: &InPin,
+            _ui: &mut Ui,
+            _scale: f32,
+            _snarl: &mut Snarl<Pins>,
+        ) -> PinInfo {
+            PinInfo::default()
+        }
+
+        fn show_output(
+            &mut self,
+            _pin: &OutPin,
+            _ui: &mut Ui,
+            _scale: f32,
+            _snarl: &mut Snarl<Pins>,
+        ) -> PinInfo {
+            PinInfo::default()
+        }
+    }
+
+    const fn out_pin(node: NodeId, output: usize) -> OutPinId {
+        OutPinId { node, output }
+    }
+
+    const fn in_pin(node: NodeId, input: usize) -> InPinId {
+        InPinId { node, input }
+    }
+
+    /// Viewer of [`Pins`] nodes with hooks configured by tests.
+    ///
+    /// Records what the graph reported to it.
+    #[derive(Default)]
+    struct TestViewer {
+        /// Label shown in every input pin.
+        input_label: Option<&'static str>,
+
+        /// Label shown in every output pin.
+        output_label: Option<&'static str>,
+
+        /// Body of every node. Returns whether body content was clicked.
+        body: Option<fn(&mut Ui) -> bool>,
+
+        /// Value returned from `body_needs_update`.
+        body_needs_update: bool,
+
+        /// Value returned from `body_cache_key`.
+        body_cache_key: Option<u64>,
+
+        /// Nodes that are not interactive.
+        locked: Vec<NodeId>,
+
+        /// Connections rejected by `can_connect`.
+        reject: Vec<(OutPinId, InPinId)>,
+
+        /// Selection applied on the next frame.
+        select: Option<Vec<NodeId>>,
+
+        /// Aspect ratio of all nodes.
+        aspect_ratio: Option<f32>,
+
+        /// Attaches outputs to the bottom center of the node.
+        outputs_at_bottom: bool,
+
+        /// Whether wires have labels.
+        wire_labels: bool,
+
+        /// Screen rects of nodes from the last frame.
+        node_rects: HashMap<NodeId, egui::Rect>,
+
+        /// Nodes with bodies shown and pass numbers in which it happened.
+        bodies_shown: Vec<(NodeId, u64)>,
+
+        /// Whether body content was clicked.
+        body_clicked: bool,
+
+        /// Nodes reported as moved with their positions before the move.
+        moved: Vec<(NodeId, Pos2)>,
+
+        /// Number of requested wire labels.
+        wire_labels_requested: usize,
+
+        /// Viewport offset and scale drawn in the last frame.
+        viewport: Option<(egui::Vec2, f32)>,
+
+        /// Output anchor returned in the last frame.
+        output_anchor: Option<Pos2>,
+
+        /// Whether graph menu adds a node the first time it is shown.
+        graph_menu: bool,
+
+        /// Node added from graph menu.
+        menu_node: Option<NodeId>,
+    }
+
+    impl TestViewer {
+        /// Screen rect of the node in the last frame.
+        fn rect(&self, node: NodeId) -> egui::Rect {
+            self.node_rects[&node]
+        }
+    }
+
+    impl SnarlViewer<Pins> for TestViewer {
+        fn title(&mut self, _node: &Pins) -> String {
+            String::new()
+        }
+
+        fn inputs(&mut self, node: &Pins) -> usize {
+            node.0
+        }
+
+        fn outputs(&mut self, node: &Pins) -> usize {
+            node.1
+        }
+
+        fn show_input(
+            &mut self,
+            _pin: &InPin,
+            ui: &mut Ui,
+            _scale: f32,
+            _snarl: &mut Snarl<Pins>,
+        ) -> PinInfo {
+            if let Some(label) = self.input_label {
+                ui.label(label);
+            }
+            PinInfo::circle()
+        }
+
+        fn show_output(
+            &mut self,
+            _pin: &OutPin,
+            ui: &mut Ui,
+            _scale: f32,
+            _snarl: &mut Snarl<Pins>,
+        ) -> PinInfo {
+            if let Some(label) = self.output_label {
+                ui.label(label);
+            }
+            PinInfo::circle()
+        }
+
+        fn has_body(&mut self, _node: &Pins) -> bool {
+            self.body.is_some()
+        }
+
+        fn show_body(
+            &mut self,
+            node: NodeId,
+            _inputs: &[InPin],
+            _outputs: &[OutPin],
+            ui: &mut Ui,
+            _scale: f32,
+            _snarl: &mut Snarl<Pins>,
+        ) {
+            self.bodies_shown
+                .push((node, ui.ctx().cumulative_pass_nr()));
+            if let Some(body) = self.body {
+                self.body_clicked |= body(ui);
+            }
+        }
+
+        fn body_needs_update(&mut self, _node: NodeId, _snarl: &Snarl<Pins>) -> bool {
+            self.body_needs_update
+        }
+
+        fn body_cache_key(&mut self, _node: NodeId, _snarl: &Snarl<Pins>) -> Option<u64> {
+            self.body_cache_key
+        }
+
+        fn node_interactive(&mut self, node: NodeId, _snarl: &Snarl<Pins>) -> bool {
+            !self.locked.contains(&node)
+        }
+
+        fn can_connect(&mut self, from: &OutPin, to: &InPin, _snarl: &Snarl<Pins>) -> bool {
+            !self.reject.contains(&(from.id, to.id))
+        }
+
+        fn update_selection(&mut self, _selected_nodes: &[NodeId]) -> Option<Vec<NodeId>> {
+            self.select.take()
+        }
+
+        fn node_aspect_ratio(&mut self, _node: NodeId, _snarl: &Snarl<Pins>) -> Option<f32> {
+            self.aspect_ratio
+        }
+
+        fn pin_anchor(
+            &mut self,
+            pin: crate::ui::AnyPin,
+            node_rect: egui::Rect,
+            _snarl: &Snarl<Pins>,
+        ) -> Option<Pos2> {
+            match pin {
+                crate::ui::AnyPin::Out(_) if self.outputs_at_bottom => {
+                    let anchor = node_rect.center_bottom();
+                    self.output_anchor = Some(anchor);
+                    Some(anchor)
+                }
+                _ => None,
+            }
+        }
+
+        fn wire_label(
+            &mut self,
+            _from: &OutPinId,
+            _to: &InPinId,
+            _snarl: &Snarl<Pins>,
+        ) -> Option<egui::WidgetText> {
+            if !self.wire_labels {
+                return None;
+            }
+            self.wire_labels_requested += 1;
+            Some("label".into())
+        }
+
+        fn has_graph_menu(&mut self, _pos: Pos2, _snarl: &mut Snarl<Pins>) -> bool {
+            self.graph_menu
+        }
+
+        fn show_graph_menu(
+            &mut self,
+            pos: Pos2,
+            _ui: &mut Ui,
+            _scale: f32,
+            snarl: &mut Snarl<Pins>,
+        ) {
+            if self.menu_node.is_none() {
+                self.menu_node = Some(snarl.insert_node(pos, Pins(0, 0)));
+            }
+        }
+
+        fn on_nodes_moved(&mut self, moved: &[(NodeId, Pos2)], _snarl: &mut Snarl<Pins>) {
+            self.moved.extend_from_slice(moved);
+        }
+
+        fn draw_background(
+            &mut self,
+            background: Option<&crate::ui::BackgroundPattern>,
+            viewport: &crate::ui::Viewport,
+            snarl_style: &SnarlStyle,
+            style: &egui::Style,
+            painter: &egui::Painter,
+            _snarl: &Snarl<Pins>,
+        ) {
+            self.viewport = Some((viewport.offset, viewport.scale));
+            if let Some(background) = background {
+                background.draw(viewport, snarl_style, style, painter);
+            }
+        }
+
+        fn final_node_rect(
+            &mut self,
+            node: NodeId,
+            ui_rect: egui::Rect,
+            _graph_rect: egui::Rect,
+            _ui: &mut Ui,
+            _scale: f32,
+            _snarl: &mut Snarl<Pins>,
+        ) {
+            self.node_rects.insert(node, ui_rect);
+        }
+    }
+
+    /// Result of a single frame shown with [`run_frame`].
+    struct Frame {
+        /// Id of the ui the graph is shown in.
+        ui_id: egui::Id,
+        response: crate::ui::SnarlResponse,
+        selected: Vec<NodeId>,
+        primary: Option<NodeId>,
+    }
+
+    /// Input of a frame on 800x600 screen.
+    ///
+    /// Graph is shown at scale 1 with origin at the center of the screen.
+    fn screen_input(events: Vec<egui::Event>) -> egui::RawInput {
+        egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(
+                Pos2::ZERO,
+                egui::vec2(800.0, 600.0),
+            )),
+            events,
+            ..egui::RawInput::default()
+        }
+    }
+
+    /// Shows the graph in the central panel for a single frame with given input.
+    fn run_input(
+        cx: &egui::Context,
+        input: egui::RawInput,
+        snarl: &mut Snarl<Pins>,
+        viewer: &mut impl SnarlViewer<Pins>,
+        style: &SnarlStyle,
+    ) -> Frame {
+        let mut frame = Frame {
+            ui_id: egui::Id::NULL,
+            response: crate::ui::SnarlResponse::default(),
+            selected: Vec::new(),
+            primary: None,
+        };
+        let _ = cx.run(input, |cx| {
+            egui::CentralPanel::default().show(cx, |ui| {
+                frame.ui_id = ui.id();
+                frame.response = snarl.show(viewer, style, "snarl", ui);
+                frame.selected = Snarl::<Pins>::get_selected_nodes("snarl", ui);
+                frame.primary = Snarl::<Pins>::get_primary_selection("snarl", ui);
+            });
+        });
+        frame
+    }
+
+    /// Shows the graph for a single frame on 800x600 screen with given events.
+    ///
+    /// See [`screen_input`].
+    fn run_frame(
+        cx: &egui::Context,
+        snarl: &mut Snarl<Pins>,
+        viewer: &mut impl SnarlViewer<Pins>,
+        style: &SnarlStyle,
+        events: Vec<egui::Event>,
+    ) -> Frame {
+        run_input(cx, screen_input(events), snarl, viewer, style)
+    }
+
+    /// Primary button press or release at `pos`.
+    fn button(pos: Pos2, pressed: bool) -> egui::Event {
+        egui::Event::PointerButton {
+            pos,
+            button: egui::PointerButton::Primary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        }
+    }
+
+    /// Key press without modifiers.
+    fn press(key: egui::Key) -> egui::Event {
+        egui::Event::Key {
+            key,
+            physical_key: None,
+            pressed: true,
+            repeat: false,
+            modifiers: egui::Modifiers::NONE,
+        }
+    }
+
+    /// Drags primary button from `from` to `to` over four frames.
+    fn drag(
+        cx: &egui::Context,
+        snarl: &mut Snarl<Pins>,
+        viewer: &mut impl SnarlViewer<Pins>,
+        style: &SnarlStyle,
+        from: Pos2,
+        to: Pos2,
+    ) -> Frame {
+        run_frame(
+            cx,
+            snarl,
+            viewer,
+            style,
+            vec![egui::Event::PointerMoved(from), button(from, true)],
+        );
+        run_frame(
+            cx,
+            snarl,
+            viewer,
+            style,
+            vec![egui::Event::PointerMoved(from.lerp(to, 0.5))],
+        );
+        run_frame(
+            cx,
+            snarl,
+            viewer,
+            style,
+            vec![egui::Event::PointerMoved(to)],
+        );
+        run_frame(cx, snarl, viewer, style, vec![button(to, false)])
+    }
+
+    #[test]
+    fn layout_animation_interpolates_and_stops() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let b = snarl.insert_node(pos2(0.0, 100.0), Pins(0, 0));
+        let c = snarl.insert_node(pos2(0.0, 200.0), Pins(0, 0));
+
+        let mut targets = HashMap::default();
+        targets.insert(a, pos2(100.0, 0.0));
+        targets.insert(b, pos2(100.0, 100.0));
+        snarl.animate_to_layout(&targets, 1.0);
+
+        assert!(snarl.advance_layout_animation(0.5));
+        assert_eq!(snarl.nodes[a.0].pos, pos2(50.0, 0.0));
+        assert_eq!(snarl.nodes[c.0].pos, pos2(0.0, 200.0));
+
+        // Dragged node stays where it is.
+        snarl.stop_layout_animation(&[b]);
+
+        assert!(!snarl.advance_layout_animation(0.5));
+        assert_eq!(snarl.nodes[a.0].pos, pos2(100.0, 0.0));
+        assert_eq!(snarl.nodes[b.0].pos, pos2(50.0, 100.0));
+        assert!(!snarl.advance_layout_animation(0.5));
+    }
+
+    #[test]
+    fn resolve_overlaps_pushes_nodes_apart() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let c = snarl.insert_node(pos2(500.0, 0.0), Pins(0, 0));
+        for node in [a, b, c] {
+            snarl.nodes[node.0].size = Some(egui::vec2(100.0, 50.0));
+        }
+
+        let moved = snarl.resolve_overlaps(&SnarlMeasurement::default(), 10.0);
+        assert!(!moved.contains_key(&c));
+        assert_eq!(
+            moved,
+            snarl.resolve_overlaps(&SnarlMeasurement::default(), 10.0)
+        );
+
+        for (node, pos) in &moved {
+            snarl.nodes[node.0].pos = *pos;
+        }
+
+        let rect = |node: NodeId| {
+            egui::Rect::from_min_size(snarl.nodes[node.0].pos, egui::vec2(100.0, 50.0))
+        };
+        assert!(!rect(a).expand(4.9).intersects(rect(b).expand(4.9)));
+        assert!(snarl
+            .resolve_overlaps(&SnarlMeasurement::default(), 10.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn stats_count_nodes_and_wires() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(2, 0));
+
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 1));
+
+        assert_eq!(snarl.stats(), SnarlStats { nodes: 2, wires: 2 });
+
+        snarl.remove_node(a);
+        assert_eq!(snarl.stats(), SnarlStats { nodes: 1, wires: 0 });
+    }
+
+    #[test]
+    fn bounding_box_spans_measured_nodes() {
+        let mut snarl = Snarl::new();
+        assert_eq!(snarl.bounding_box(&SnarlMeasurement::default()), None);
+
+        snarl.insert_node(pos2(-10.0, 0.0), Pins(1, 1));
+        let b = snarl.insert_node(pos2(100.0, 50.0), Pins(0, 0));
+        snarl.set_node_size(b, Some(egui::vec2(150.0, 60.0)));
+
+        let fonts = egui::epaint::Fonts::new(1.0, 1024, egui::FontDefinitions::default());
+        let mut measurement = snarl.measure(&mut PinsViewer, &SnarlStyle::new(), &fonts);
+        let margin = egui::Frame::window(&egui::Style::default()).total_margin();
+        assert_eq!(measurement.node_margin, margin);
+
+        // Frames start before positions of nodes by the frame margin.
+        let bounds = snarl.bounding_box(&measurement).unwrap();
+        assert_eq!(bounds.min, pos2(-10.0 - margin.left, -margin.top));
+        assert_eq!(
+            bounds.max,
+            pos2(250.0 + margin.right, 110.0 + margin.bottom)
+        );
+
+        // Node that is not measured gets the same frame around its set size.
+        measurement.nodes.remove(&b);
+        assert_eq!(snarl.bounding_box(&measurement), Some(bounds));
+    }
+
+    #[test]
+    fn wire_route_avoids_node_in_the_path() {
+        use egui::Rect;
+
+        let from = pos2(0.0, 50.0);
+        let to = pos2(300.0, 50.0);
+        let node = Rect::from_min_max(pos2(100.0, 0.0), pos2(200.0, 100.0));
+        let margin = 10.0;
+
+        let route = crate::ui::route_wire(from, to, &[node], margin).unwrap();
+
+        assert_eq!(route.first(), Some(&from));
+        assert_eq!(route.last(), Some(&to));
+
+        for segment in route.windows(2) {
+            assert!(segment[0].x == segment[1].x || segment[0].y == segment[1].y);
+            assert!(
+                !Rect::from_two_pos(segment[0], segment[1]).intersects(node.expand(margin * 0.5))
+            );
+        }
+
+        // Direct path is used if nothing is in the way.
+        let route = crate::ui::route_wire(from, to, &[], margin).unwrap();
+        assert_eq!(route, [from, to]);
+    }
+
+    #[test]
+    fn try_connect_ok() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 0));
+
+        assert_eq!(
+            snarl.try_connect(
+                out_pin(a, 0),
+                in_pin(b, 0),
+                &mut PinsViewer,
+                &SnarlStyle::new()
+            ),
+            Ok(())
+        );
+        assert_eq!(snarl.wires().count(), 1);
+    }
+
+    #[test]
+    fn toggle_wire_connects_and_disconnects() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 0));
+        let style = SnarlStyle::new();
+
+        assert!(!snarl.has_wire(out_pin(a, 0), in_pin(b, 0)));
+        assert_eq!(
+            snarl.toggle_wire(out_pin(a, 0), in_pin(b, 0), &mut PinsViewer, &style),
+            WireToggle::Connected
+        );
+        assert!(snarl.has_wire(out_pin(a, 0), in_pin(b, 0)));
+        assert_eq!(
+            snarl.toggle_wire(out_pin(a, 0), in_pin(b, 0), &mut PinsViewer, &style),
+            WireToggle::Disconnected
+        );
+        assert!(!snarl.has_wire(out_pin(a, 0), in_pin(b, 0)));
+        assert_eq!(
+            snarl.toggle_wire(out_pin(a, 1), in_pin(b, 0), &mut PinsViewer, &style),
+            WireToggle::Rejected(ConnectError::OutputOutOfRange(out_pin(a, 1)))
+        );
+    }
+
+    #[test]
+    fn connect_idx_validates_indices() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 0));
+        let style = SnarlStyle::new();
+
+        assert_eq!(
+            snarl.connect_idx(a, 1, b, 0, &mut PinsViewer, &style),
+            Err(ConnectError::OutputOutOfRange(out_pin(a, 1)))
+        );
+        assert_eq!(
+            snarl.connect_idx(a, 0, b, 1, &mut PinsViewer, &style),
+            Err(ConnectError::InputOutOfRange(in_pin(b, 1)))
+        );
+        assert_eq!(
+            snarl.connect_idx(a, 0, b, 0, &mut PinsViewer, &style),
+            Ok(())
+        );
+        assert_eq!(
+            snarl.wires().collect::<Vec<_>>(),
+            vec![(out_pin(a, 0), in_pin(b, 0))]
+        );
+    }
+
+    #[test]
+    fn try_connect_missing_node() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let missing = NodeId(42);
+
+        assert_eq!(
+            snarl.try_connect(
+                out_pin(a, 0),
+                in_pin(missing, 0),
+                &mut PinsViewer,
+                &SnarlStyle::new()
+            ),
+            Err(ConnectError::NodeNotFound(missing))
+        );
+        assert_eq!(
+            snarl.try_connect(
+                out_pin(missing, 0),
+                in_pin(a, 0),
+                &mut PinsViewer,
+                &SnarlStyle::new()
+            ),
+            Err(ConnectError::NodeNotFound(missing))
+        );
+        assert_eq!(snarl.wires().count(), 0);
+    }
+
+    #[test]
+    fn try_connect_out_of_range() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 0));
+
+        assert_eq!(
+            snarl.try_connect(
+                out_pin(a, 1),
+                in_pin(b, 0),
+                &mut PinsViewer,
+                &SnarlStyle::new()
+            ),
+            Err(ConnectError::OutputOutOfRange(out_pin(a, 1)))
+        );
+        assert_eq!(
+            snarl.try_connect(
+                out_pin(a, 0),
+                in_pin(b, 1),
+                &mut PinsViewer,
+                &SnarlStyle::new()
+            ),
+            Err(ConnectError::InputOutOfRange(in_pin(b, 1)))
+        );
+        assert_eq!(snarl.wires().count(), 0);
+    }
+
+    #[test]
+    fn try_connect_duplicate() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 0));
+
+        assert!(snarl.connect(out_pin(a, 0), in_pin(b, 0)));
+        assert_eq!(
+            snarl.try_connect(
+                out_pin(a, 0),
+                in_pin(b, 0),
+                &mut PinsViewer,
+                &SnarlStyle::new()
+            ),
+            Err(ConnectError::AlreadyConnected)
+        );
+        assert_eq!(snarl.wires().count(), 1);
+    }
+
+    #[test]
+    fn try_connect_self_loop() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+
+        let style = SnarlStyle {
+            allow_self_loops: Some(false),
+            ..SnarlStyle::new()
+        };
+
+        assert_eq!(
+            snarl.try_connect(out_pin(a, 0), in_pin(a, 0), &mut PinsViewer, &style),
+            Err(ConnectError::SelfLoop)
+        );
+        assert_eq!(snarl.wires().count(), 0);
+
+        assert_eq!(
+            snarl.try_connect(
+                out_pin(a, 0),
+                in_pin(a, 0),
+                &mut PinsViewer,
+                &SnarlStyle::new()
+            ),
+            Ok(())
+        );
+        assert_eq!(snarl.wires().count(), 1);
+    }
+
+    #[test]
+    fn connect_many_reports_rejected() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(2, 0));
+        let missing = NodeId(42);
+
+        let rejected = snarl.connect_many(
+            [
+                (out_pin(a, 0), in_pin(b, 0)),
+                (out_pin(a, 0), in_pin(b, 0)),
+                (out_pin(a, 0), in_pin(missing, 0)),
+                (out_pin(a, 0), in_pin(b, 1)),
+            ],
+            &mut PinsViewer,
+            &SnarlStyle::new(),
+        );
+
+        assert_eq!(
+            rejected,
+            [
+                (out_pin(a, 0), in_pin(b, 0), ConnectError::AlreadyConnected),
+                (
+                    out_pin(a, 0),
+                    in_pin(missing, 0),
+                    ConnectError::NodeNotFound(missing)
+                ),
+            ]
+        );
+        assert_eq!(snarl.wires().count(), 2);
+    }
+
+    #[test]
+    fn connect_many_rejects_cycles_within_batch() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let c = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let style = SnarlStyle {
+            prevent_cycles: Some(true),
+            ..SnarlStyle::new()
+        };
+
+        let rejected = snarl.connect_many(
+            [
+                (out_pin(b, 0), in_pin(c, 0)),
+                (out_pin(c, 0), in_pin(a, 0)),
+                (out_pin(b, 0), in_pin(c, 0)),
+            ],
+            &mut PinsViewer,
+            &style,
+        );
+
+        assert_eq!(
+            rejected,
+            [
+                (out_pin(c, 0), in_pin(a, 0), ConnectError::Cycle),
+                (out_pin(b, 0), in_pin(c, 0), ConnectError::AlreadyConnected),
+            ]
+        );
+        assert_eq!(snarl.wires().count(), 2);
+        assert!(snarl.would_create_cycle(out_pin(c, 0), in_pin(a, 0)));
+    }
+
+    #[test]
+    fn try_connect_cycle() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let c = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(b, 0), in_pin(c, 0));
+
+        assert!(snarl.would_create_cycle(out_pin(c, 0), in_pin(a, 0)));
+        assert!(!snarl.would_create_cycle(out_pin(a, 0), in_pin(c, 0)));
+
+        let style = SnarlStyle {
+            prevent_cycles: Some(true),
+            ..SnarlStyle::new()
+        };
+
+        assert_eq!(
+            snarl.try_connect(out_pin(c, 0), in_pin(a, 0), &mut PinsViewer, &style),
+            Err(ConnectError::Cycle)
+        );
+        assert_eq!(snarl.wires().count(), 2);
+    }
+
+    #[test]
+    fn reachable_nodes_follow_wire_direction() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let c = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let d = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(b, 0), in_pin(c, 0));
+
+        let collect = |upstream: bool| {
+            let mut nodes = snarl
+                .reachable_nodes([b], upstream)
+                .into_iter()
+                .collect::<Vec<_>>();
+            nodes.sort();
+            nodes
+        };
+
+        assert_eq!(collect(false), [b, c]);
+        assert_eq!(collect(true), [a, b]);
+        assert!(!snarl.reachable_nodes([a, c], true).contains(&d));
+    }
+
+    #[test]
+    fn group_drag_places_nodes_from_origin() {
+        let mut snarl = Snarl::new();
+        let nodes = [
+            snarl.insert_node(pos2(1000.3, 20.25), Pins(0, 0)),
+            snarl.insert_node(pos2(-77.7, 7.75), Pins(0, 0)),
+            snarl.insert_node(pos2(12345.6, -50.5), Pins(0, 0)),
+        ];
+
+        let origin = nodes.map(|node| (node, snarl.nodes[node.0].pos));
+
+        // Simulate a long drag with deltas that are not exact in binary.
+        let mut total = egui::Vec2::ZERO;
+        for _ in 0..1000 {
+            total += egui::vec2(0.1, -0.3);
+            snarl.place_nodes(&origin, total);
+        }
+
+        for (node, pos) in origin {
+            assert_eq!(snarl.nodes[node.0].pos, pos + total);
+        }
+    }
+
+    #[test]
+    fn apply_layout_skips_missing_nodes() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(10.0, 0.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let layout = snarl.layout_snapshot();
+
+        snarl.nodes[a.0].pos = pos2(5.0, 5.0);
+        snarl.open_node(a, false);
+        let c = snarl.insert_node(pos2(20.0, 0.0), Pins(1, 0));
+        snarl.remove_node(b);
+
+        snarl.apply_layout(&layout);
+
+        assert_eq!(snarl.nodes[a.0].pos, pos2(0.0, 0.0));
+        assert!(snarl.nodes[a.0].open);
+        assert_eq!(snarl.nodes[c.0].pos, pos2(20.0, 0.0));
+        assert_eq!(snarl.wires().count(), 0);
+    }
+
+    #[test]
+    fn measure_reports_nodes_and_pins() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(2, 1));
+        let b = snarl.insert_node(pos2(300.0, 0.0), Pins(1, 0));
+
+        let fonts = egui::epaint::Fonts::new(1.0, 1024, egui::FontDefinitions::default());
+        let measurement = snarl.measure(&mut PinsViewer, &SnarlStyle::new(), &fonts);
+
+        // Drawing budget does not affect layout.
+        let budgeted = SnarlStyle {
+            max_nodes_per_frame: Some(1),
+            ..SnarlStyle::new()
+        };
+        assert_eq!(
+            snarl.measure(&mut PinsViewer, &budgeted, &fonts),
+            measurement
+        );
+
+        assert_eq!(measurement.nodes.len(), 2);
+
+        let a = &measurement.nodes[&a];
+        assert!(a.size.x > 0.0 && a.size.y > 0.0);
+        assert_eq!(a.inputs.len(), 2);
+        assert_eq!(a.outputs.len(), 1);
+        assert!(a.inputs[0].y < a.inputs[1].y);
+        assert!(a.inputs[0].x < a.outputs[0].x);
+
+        let b = &measurement.nodes[&b];
+        assert_eq!(b.inputs.len(), 1);
+        assert!(b.outputs.is_empty());
+    }
+
+    #[test]
+    fn node_anchor_follows_last_layout() {
+        use crate::ui::NodeAnchor;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(10.0, 20.0), Pins(2, 1));
+
+        let mut ui_id = egui::Id::NULL;
+        for _ in 0..3 {
+            ui_id = run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new()).ui_id;
+        }
+
+        let anchor_of =
+            |snarl: &Snarl<Pins>, anchor| snarl.node_anchor_at("snarl", ui_id, &cx, node, anchor);
+
+        let top_left = anchor_of(&snarl, NodeAnchor::TopLeft).unwrap();
+        let bottom_right = anchor_of(&snarl, NodeAnchor::BottomRight).unwrap();
+        assert_eq!(top_left, pos2(10.0, 20.0));
+        assert!(bottom_right.x > top_left.x && bottom_right.y > top_left.y);
+
+        let input_0 = anchor_of(&snarl, NodeAnchor::Input(0)).unwrap();
+        let input_1 = anchor_of(&snarl, NodeAnchor::Input(1)).unwrap();
+        let output_0 = anchor_of(&snarl, NodeAnchor::Output(0)).unwrap();
+        assert!(input_0.y < input_1.y);
+        assert!(input_0.x < output_0.x);
+        assert_eq!(anchor_of(&snarl, NodeAnchor::Output(1)), None);
+
+        // Anchors move with the node without new layout.
+        snarl.nodes[node.0].pos += egui::vec2(5.0, 0.0);
+        assert_eq!(
+            anchor_of(&snarl, NodeAnchor::Input(0)),
+            Some(input_0 + egui::vec2(5.0, 0.0))
+        );
+
+        // Pins removed from the node have no anchors.
+        snarl.nodes[node.0].value = Pins(1, 1);
+        run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+        assert!(anchor_of(&snarl, NodeAnchor::Input(0)).is_some());
+        assert_eq!(anchor_of(&snarl, NodeAnchor::Input(1)), None);
+
+        snarl.remove_node(node);
+        assert_eq!(anchor_of(&snarl, NodeAnchor::Center), None);
+    }
+
+    #[test]
+    fn wire_path_follows_last_frame_geometry() {
+        use crate::ui::NodeAnchor;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(200.0, 50.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let mut ui_id = egui::Id::NULL;
+        for _ in 0..3 {
+            ui_id = run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new()).ui_id;
+        }
+
+        let path = snarl
+            .wire_path_at("snarl", ui_id, &cx, out_pin(a, 0), in_pin(b, 0))
+            .unwrap();
+
+        let from = snarl
+            .node_anchor_at("snarl", ui_id, &cx, a, NodeAnchor::Output(0))
+            .unwrap();
+        let to = snarl
+            .node_anchor_at("snarl", ui_id, &cx, b, NodeAnchor::Input(0))
+            .unwrap();
+
+        let (start, start_tangent) = path.sample(0.0);
+        let (end, end_tangent) = path.sample(1.0);
+        assert!((start - from).length() < 0.01);
+        assert!((end - to).length() < 0.01);
+
+        // Bezier wire leaves output pin and enters input pin horizontally.
+        assert!(start_tangent.x > 0.99);
+        assert!(end_tangent.x > 0.99);
+
+        let (middle, _) = path.sample(0.5);
+        assert!(middle.x > from.x && middle.x < to.x);
+        assert!(path.length() >= (to - from).length());
+
+        snarl.disconnect(out_pin(a, 0), in_pin(b, 0));
+        assert_eq!(
+            snarl.wire_path_at("snarl", ui_id, &cx, out_pin(a, 0), in_pin(b, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn wire_labels_are_hidden_when_zoomed_out() {
+        let labels_requested = |min_scale: f32| {
+            let cx = egui::Context::default();
+            let mut style = SnarlStyle::new();
+            style.wire_label_min_scale = Some(min_scale);
+
+            let mut snarl = Snarl::new();
+            let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+            let b = snarl.insert_node(pos2(200.0, 50.0), Pins(1, 0));
+            snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+            let mut viewer = TestViewer {
+                wire_labels: true,
+                ..TestViewer::default()
+            };
+            for _ in 0..3 {
+                run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+            }
+            viewer.wire_labels_requested
+        };
+
+        assert!(labels_requested(0.5) > 0);
+        assert_eq!(labels_requested(2.0), 0);
+    }
+
+    #[test]
+    fn wire_style_shortcut_cycles_persisted_style() {
+        use egui::{Event, Key, KeyboardShortcut, Modifiers};
+
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.wire_style_shortcut = Some(KeyboardShortcut::new(Modifiers::NONE, Key::W));
+
+        let mut snarl = Snarl::new();
+        snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        let mut ui_id = egui::Id::NULL;
+        let mut run = |events: Vec<Event>| {
+            let mut all_events = vec![Event::PointerMoved(pos2(100.0, 100.0))];
+            all_events.extend(events);
+            ui_id = run_frame(&cx, &mut snarl, &mut PinsViewer, &style, all_events).ui_id;
+        };
+
+        run(Vec::new());
+        run(vec![press(Key::W)]);
+        run(Vec::new());
+        run(vec![press(Key::W)]);
+
+        let persisted =
+            cx.data_mut(|d| d.get_persisted::<u8>(ui_id.with("snarl").with("wire-style")));
+
+        // Bezier5 -> Bezier3 -> AxisAligned.
+        assert_eq!(persisted, Some(2));
+    }
+
+    #[test]
+    fn top_bottom_pins_are_lined_up_on_edges() {
+        use crate::ui::{NodeAnchor, PinSides};
+
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.pin_sides = Some(PinSides::TopBottom);
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(10.0, 20.0), Pins(2, 2));
+
+        let mut ui_id = egui::Id::NULL;
+        for _ in 0..3 {
+            ui_id = run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new()).ui_id;
+        }
+
+        let anchor_of = |anchor| {
+            snarl
+                .node_anchor_at("snarl", ui_id, &cx, node, anchor)
+                .unwrap()
+        };
+
+        let input_0 = anchor_of(NodeAnchor::Input(0));
+        let input_1 = anchor_of(NodeAnchor::Input(1));
+        let output_0 = anchor_of(NodeAnchor::Output(0));
+        let output_1 = anchor_of(NodeAnchor::Output(1));
+
+        assert_eq!(input_0.y, input_1.y);
+        assert_eq!(output_0.y, output_1.y);
+        assert!(input_0.x < input_1.x);
+        assert!(output_0.x < output_1.x);
+        assert!(input_0.y < anchor_of(NodeAnchor::Center).y);
+        assert!(output_0.y > anchor_of(NodeAnchor::Center).y);
+    }
+
+    #[test]
+    fn grid_snap_rounds_to_nearest_node() {
+        let grid = crate::ui::Grid::new(egui::vec2(50.0, 20.0), 0.0);
+
+        assert_eq!(grid.snap(pos2(24.0, 11.0), Pos2::ZERO), pos2(0.0, 20.0));
+        assert_eq!(grid.snap(pos2(-26.0, -9.0), Pos2::ZERO), pos2(-50.0, 0.0));
+
+        // Grid is shifted with its origin.
+        assert_eq!(grid.snap(pos2(24.0, 11.0), pos2(5.0, 3.0)), pos2(5.0, 3.0));
+        assert_eq!(grid.snap(pos2(34.0, 11.0), pos2(5.0, 3.0)), pos2(55.0, 3.0));
+    }
+
+    #[test]
+    fn grid_snap_size_rounds_to_spacing_multiples() {
+        let grid = crate::ui::Grid::new(egui::vec2(50.0, 20.0), 0.3);
+
+        assert_eq!(
+            grid.snap_size(egui::vec2(124.0, 31.0)),
+            egui::vec2(100.0, 40.0)
+        );
+        assert_eq!(grid.snap_size(egui::vec2(10.0, 9.0)), egui::vec2(0.0, 0.0));
+    }
+
+    #[test]
+    fn escape_cancels_node_drag() {
+        use egui::{Event, Key};
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        let run = |snarl: &mut Snarl<Pins>, events: Vec<Event>| {
+            run_frame(&cx, snarl, &mut PinsViewer, &style, events);
+        };
+
+        // Lay out the node. Graph origin is at the center of the screen.
+        run(&mut snarl, Vec::new());
+        run(&mut snarl, Vec::new());
+
+        let start = pos2(404.0, 304.0);
+        run(
+            &mut snarl,
+            vec![Event::PointerMoved(start), button(start, true)],
+        );
+        run(&mut snarl, vec![Event::PointerMoved(pos2(454.0, 304.0))]);
+        run(&mut snarl, vec![Event::PointerMoved(pos2(464.0, 314.0))]);
+
+        assert_ne!(snarl.nodes[node.0].pos, pos2(0.0, 0.0));
+
+        run(&mut snarl, vec![press(Key::Escape)]);
+
+        assert_eq!(snarl.nodes[node.0].pos, pos2(0.0, 0.0));
+    }
+
+    #[test]
+    fn overflow_content_does_not_drag_node() {
+        use crate::ui::show_overflow;
+        use egui::Event;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        // Shows button over the node in a floating layer.
+        // Graph origin is at the center of the screen.
+        let mut viewer = TestViewer {
+            body: Some(|ui| {
+                show_overflow(ui, "overflow", pos2(400.0, 300.0), |ui| {
+                    ui.add_sized(egui::vec2(100.0, 100.0), egui::Button::new("overflow"))
+                })
+                .inner
+                .clicked()
+            }),
+            ..TestViewer::default()
+        };
+
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+
+        let start = pos2(404.0, 304.0);
+        let end = pos2(464.0, 314.0);
+        drag(&cx, &mut snarl, &mut viewer, &style, start, end);
+
+        assert_eq!(snarl.nodes[node.0].pos, pos2(0.0, 0.0));
+
+        run_frame(
+            &cx,
+            &mut snarl,
+            &mut viewer,
+            &style,
+            vec![Event::PointerMoved(start), button(start, true)],
+        );
+        run_frame(
+            &cx,
+            &mut snarl,
+            &mut viewer,
+            &style,
+            vec![button(start, false)],
+        );
+        assert!(viewer.body_clicked);
+    }
+
+    #[test]
+    fn dropped_nodes_are_reported_to_viewer() {
+        use egui::Event;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        let mut viewer = TestViewer::default();
+        let mut run = |snarl: &mut Snarl<Pins>, events: Vec<Event>| {
+            run_frame(&cx, snarl, &mut viewer, &style, events);
+            viewer.moved.clone()
+        };
+
+        // Lay out the node. Graph origin is at the center of the screen.
+        run(&mut snarl, Vec::new());
+        run(&mut snarl, Vec::new());
+
+        let start = pos2(404.0, 304.0);
+        let end = pos2(464.0, 314.0);
+        run(
+            &mut snarl,
+            vec![Event::PointerMoved(start), button(start, true)],
+        );
+        run(&mut snarl, vec![Event::PointerMoved(pos2(454.0, 304.0))]);
+
+        // Nothing is reported while nodes are dragged.
+        assert!(run(&mut snarl, vec![Event::PointerMoved(end)]).is_empty());
+
+        let moved = run(&mut snarl, vec![button(end, false)]);
+        assert_eq!(moved, vec![(node, pos2(0.0, 0.0))]);
+        assert_ne!(snarl.nodes[node.0].pos, pos2(0.0, 0.0));
+    }
+
+    #[test]
+    fn non_interactive_node_is_not_moved() {
+        use egui::Event;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        let mut viewer = TestViewer {
+            locked: vec![node],
+            ..TestViewer::default()
+        };
+        let mut run = |snarl: &mut Snarl<Pins>, events: Vec<Event>| {
+            run_frame(&cx, snarl, &mut viewer, &style, events);
+        };
+
+        // Lay out the node. Graph origin is at the center of the screen.
+        run(&mut snarl, Vec::new());
+        run(&mut snarl, Vec::new());
+
+        let start = pos2(404.0, 304.0);
+        run(
+            &mut snarl,
+            vec![Event::PointerMoved(start), button(start, true)],
+        );
+        run(&mut snarl, vec![Event::PointerMoved(pos2(454.0, 304.0))]);
+        run(&mut snarl, vec![Event::PointerMoved(pos2(464.0, 314.0))]);
+
+        assert_eq!(snarl.nodes[node.0].pos, pos2(0.0, 0.0));
+    }
+
+    #[test]
+    fn grid_snap_does_not_move_non_interactive_nodes() {
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.snap_to_grid = Some(true);
+        style.bg_pattern = Some(crate::ui::BackgroundPattern::Grid(crate::ui::Grid {
+            spacing: egui::vec2(50.0, 50.0),
+            angle: 0.0,
+        }));
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let b = snarl.insert_node(pos2(0.0, 200.0), Pins(0, 0));
+        let c = snarl.insert_node(pos2(-200.0, 0.0), Pins(0, 0));
+
+        let mut viewer = TestViewer {
+            locked: vec![b],
+            select: Some(vec![a, b, c]),
+            ..TestViewer::default()
+        };
+
+        // Lay out the nodes. Graph origin is at the center of the screen.
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+
+        drag(
+            &cx,
+            &mut snarl,
+            &mut viewer,
+            &style,
+            pos2(404.0, 304.0),
+            pos2(441.0, 317.0),
+        );
+
+        assert_eq!(snarl.nodes[a.0].pos, pos2(50.0, 0.0));
+        assert_eq!(snarl.nodes[b.0].pos, pos2(0.0, 200.0));
+        assert_eq!(snarl.nodes[c.0].pos, pos2(-150.0, 0.0));
+    }
+
+    #[test]
+    fn grid_snap_follows_pattern_anchor() {
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.snap_to_grid = Some(true);
+        style.bg_pattern = Some(crate::ui::BackgroundPattern::Grid(crate::ui::Grid {
+            spacing: egui::vec2(50.0, 50.0),
+            angle: 0.0,
+        }));
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let anchor = snarl.insert_node(pos2(-290.0, 210.0), Pins(0, 0));
+        style.pattern_anchor = Some(anchor);
+
+        run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+        run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+
+        drag(
+            &cx,
+            &mut snarl,
+            &mut PinsViewer,
+            &style,
+            pos2(404.0, 304.0),
+            pos2(441.0, 317.0),
+        );
+
+        // Grid lines pass through the anchor, 10 units off the graph origin.
+        assert_eq!(snarl.nodes[node.0].pos, pos2(60.0, 10.0));
+    }
+
+    /// Drags new wire from the only output of node `0` to the only input of node `1`.
+    ///
+    /// Returns whether the pins got connected.
+    fn connects_by_drag(mut viewer: TestViewer) -> bool {
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(200.0, 50.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let mut ui_id = egui::Id::NULL;
+        for _ in 0..3 {
+            ui_id = run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new()).ui_id;
+        }
+
+        // Find pins on the screen by the wire between them.
+        // Graph origin is at the center of the screen.
+        let path = snarl
+            .wire_path_at("snarl", ui_id, &cx, out_pin(a, 0), in_pin(b, 0))
+            .unwrap();
+        let from = path.sample(0.0).0 + egui::vec2(400.0, 300.0);
+        let to = path.sample(1.0).0 + egui::vec2(400.0, 300.0);
+
+        snarl.disconnect(out_pin(a, 0), in_pin(b, 0));
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+
+        drag(&cx, &mut snarl, &mut viewer, &style, from, to);
+        snarl.wires().count() == 1
+    }
+
+    #[test]
+    fn non_interactive_node_rejects_connection() {
+        assert!(connects_by_drag(TestViewer::default()));
+        assert!(!connects_by_drag(TestViewer {
+            locked: vec![NodeId(1)],
+            ..TestViewer::default()
+        }));
+    }
+
+    #[test]
+    fn can_connect_rejects_connection() {
+        assert!(!connects_by_drag(TestViewer {
+            reject: vec![(out_pin(NodeId(0), 0), in_pin(NodeId(1), 0))],
+            ..TestViewer::default()
+        }));
+    }
+
+    #[test]
+    fn can_connect_rejects_checked_connection() {
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 2));
+        let b = snarl.insert_node(pos2(200.0, 0.0), Pins(1, 0));
+
+        let mut viewer = TestViewer {
+            reject: vec![(out_pin(a, 0), in_pin(b, 0))],
+            ..TestViewer::default()
+        };
+
+        assert_eq!(
+            snarl.try_connect(out_pin(a, 0), in_pin(b, 0), &mut viewer, &style),
+            Err(ConnectError::Rejected)
+        );
+        assert_eq!(
+            snarl.toggle_wire(out_pin(a, 0), in_pin(b, 0), &mut viewer, &style),
+            WireToggle::Rejected(ConnectError::Rejected)
+        );
+        assert_eq!(
+            snarl.connect_many(
+                [(out_pin(a, 0), in_pin(b, 0)), (out_pin(a, 1), in_pin(b, 0)),],
+                &mut viewer,
+                &style,
+            ),
+            vec![(out_pin(a, 0), in_pin(b, 0), ConnectError::Rejected)]
+        );
+        assert_eq!(
+            snarl.wires().collect::<Vec<_>>(),
+            vec![(out_pin(a, 1), in_pin(b, 0))]
+        );
+    }
+
+    #[test]
+    fn delete_key_removes_selected_nodes() {
+        use egui::{Event, Key};
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(-200.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(-100.0, 0.0), Pins(1, 1));
+        let c = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let d = snarl.insert_node(pos2(100.0, 0.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(b, 0), in_pin(c, 0));
+        snarl.connect(out_pin(c, 0), in_pin(d, 0));
+
+        let mut viewer = TestViewer {
+            select: Some(vec![a, b]),
+            ..TestViewer::default()
+        };
+        let mut run = |snarl: &mut Snarl<Pins>, events: Vec<Event>| {
+            run_frame(&cx, snarl, &mut viewer, &style, events);
+        };
+
+        // Pointer is over empty space of the graph.
+        run(&mut snarl, vec![Event::PointerMoved(pos2(700.0, 500.0))]);
+        run(&mut snarl, Vec::new());
+        run(&mut snarl, vec![press(Key::Delete)]);
+
+        let nodes = snarl.node_ids().map(|(id, _)| id).collect::<Vec<_>>();
+        assert_eq!(nodes, vec![c, d]);
+        assert_eq!(
+            snarl.wires().collect::<Vec<_>>(),
+            vec![(out_pin(c, 0), in_pin(d, 0))]
+        );
+    }
+
+    #[test]
+    fn delete_key_disconnects_wire_with_open_menu() {
+        use egui::{Event, Key, PointerButton};
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(-200.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(100.0, 0.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let mut viewer = TestViewer::default();
+        let mut run = |snarl: &mut Snarl<Pins>, events: Vec<Event>| {
+            run_frame(&cx, snarl, &mut viewer, &style, events).ui_id
+        };
+
+        run(&mut snarl, Vec::new());
+        let ui_id = run(&mut snarl, Vec::new());
+
+        // Graph origin is at the center of the screen.
+        let (middle, _) = snarl
+            .wire_path_at("snarl", ui_id, &cx, out_pin(a, 0), in_pin(b, 0))
+            .unwrap()
+            .sample(0.5);
+        let middle = middle + egui::vec2(400.0, 300.0);
+
+        let secondary = |pressed| Event::PointerButton {
+            pos: middle,
+            button: PointerButton::Secondary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+        run(&mut snarl, vec![Event::PointerMoved(middle)]);
+        run(&mut snarl, vec![secondary(true)]);
+        run(&mut snarl, vec![secondary(false)]);
+
+        // Pointer leaves the menu for empty space of the graph.
+        run(&mut snarl, vec![Event::PointerMoved(pos2(700.0, 500.0))]);
+        run(&mut snarl, vec![press(Key::Delete)]);
+
+        assert_eq!(snarl.wires().count(), 0);
+        assert_eq!(snarl.node_ids().count(), 2);
     }
 
-    /// Returns reference to the node.
-    #[must_use]
-    pub fn get_node(&self, idx: NodeId) -> Option<&T> {
-        self.nodes.get(idx.0).map(|node| &node.value)
+    /// Shows a text edit with `editor` id above the graph for a single frame.
+    fn run_with_editor(
+        cx: &egui::Context,
+        editor: egui::Id,
+        snarl: &mut Snarl<Pins>,
+        viewer: &mut TestViewer,
+        style: &SnarlStyle,
+        events: Vec<egui::Event>,
+    ) {
+        let _ = cx.run(screen_input(events), |cx| {
+            egui::CentralPanel::default().show(cx, |ui| {
+                egui::TextEdit::singleline(&mut String::new())
+                    .id(editor)
+                    .show(ui);
+                snarl.show(viewer, style, "snarl", ui);
+            });
+        });
     }
 
-    /// Returns mutable reference to the node.
-    pub fn get_node_mut(&mut self, idx: NodeId) -> Option<&mut T> {
-        match self.nodes.get_mut(idx.0) {
-            Some(node) => Some(&mut node.value),
-            None => None,
+    /// Right-clicks empty space of the graph until the graph menu adds a node.
+    fn spawn_from_menu(
+        cx: &egui::Context,
+        editor: egui::Id,
+        snarl: &mut Snarl<Pins>,
+        viewer: &mut TestViewer,
+        style: &SnarlStyle,
+    ) -> NodeId {
+        use egui::{Event, PointerButton};
+
+        let pos = pos2(700.0, 500.0);
+        let secondary = |pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Secondary,
+            pressed,
+            modifiers: egui::Modifiers::NONE,
+        };
+
+        let frames = [
+            vec![Event::PointerMoved(pos)],
+            vec![secondary(true)],
+            vec![secondary(false)],
+            Vec::new(),
+            Vec::new(),
+        ];
+        for events in frames {
+            run_with_editor(cx, editor, snarl, viewer, style, events);
+            if let Some(node) = viewer.menu_node {
+                return node;
+            }
         }
+        panic!("graph menu did not add a node");
     }
 
-    /// Returns reference to the node data.
-    #[must_use]
-    pub fn get_node_info(&self, idx: NodeId) -> Option<&Node<T>> {
-        self.nodes.get(idx.0)
-    }
+    #[test]
+    fn new_node_does_not_steal_focus_from_text_edit() {
+        let cx = egui::Context::default();
+        let editor = egui::Id::new("editor");
+        let style = SnarlStyle {
+            focus_new_node: Some(true),
+            ..SnarlStyle::new()
+        };
 
-    /// Returns mutable reference to the node data.
-    pub fn get_node_info_mut(&mut self, idx: NodeId) -> Option<&mut Node<T>> {
-        self.nodes.get_mut(idx.0)
-    }
+        let mut snarl = Snarl::new();
+        let mut viewer = TestViewer {
+            body: Some(|ui| {
+                ui.text_edit_singleline(&mut String::new());
+                false
+            }),
+            graph_menu: true,
+            ..TestViewer::default()
+        };
 
-    /// Iterates over shared references to each node.
-    pub fn nodes(&self) -> NodesIter<'_, T> {
-        NodesIter {
-            nodes: self.nodes.iter(),
+        run_with_editor(&cx, editor, &mut snarl, &mut viewer, &style, Vec::new());
+        spawn_from_menu(&cx, editor, &mut snarl, &mut viewer, &style);
+
+        // User focuses another text edit before the new node is drawn.
+        cx.memory_mut(|m| m.request_focus(editor));
+        for _ in 0..3 {
+            run_with_editor(&cx, editor, &mut snarl, &mut viewer, &style, Vec::new());
         }
+
+        assert!(cx.memory(|m| m.has_focus(editor)));
     }
 
-    /// Iterates over mutable references to each node.
-    pub fn nodes_mut(&mut self) -> NodesIterMut<'_, T> {
-        NodesIterMut {
-            nodes: self.nodes.iter_mut(),
+    #[test]
+    fn focus_of_removed_node_is_not_passed_to_reused_id() {
+        let cx = egui::Context::default();
+        let editor = egui::Id::new("editor");
+        let style = SnarlStyle {
+            focus_new_node: Some(true),
+            ..SnarlStyle::new()
+        };
+
+        let mut snarl = Snarl::new();
+        let mut viewer = TestViewer {
+            body: Some(|ui| {
+                ui.text_edit_singleline(&mut String::new());
+                false
+            }),
+            graph_menu: true,
+            ..TestViewer::default()
+        };
+
+        run_with_editor(&cx, editor, &mut snarl, &mut viewer, &style, Vec::new());
+        let spawned = spawn_from_menu(&cx, editor, &mut snarl, &mut viewer, &style);
+
+        // Application removes the node before it is drawn.
+        snarl.remove_node(spawned);
+        run_with_editor(&cx, editor, &mut snarl, &mut viewer, &style, Vec::new());
+
+        let reused = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        assert_eq!(reused, spawned);
+        for _ in 0..3 {
+            run_with_editor(&cx, editor, &mut snarl, &mut viewer, &style, Vec::new());
         }
+
+        assert!(cx.memory(|m| m.focused()).is_none());
     }
 
-    /// Iterates over shared references to each node and its position.
-    pub fn nodes_pos(&self) -> NodesPosIter<'_, T> {
-        NodesPosIter {
-            nodes: self.nodes.iter(),
+    #[test]
+    fn visible_nodes_over_budget_are_drawn_in_turns() {
+        let cx = egui::Context::default();
+        let style = SnarlStyle {
+            max_nodes_per_frame: Some(1),
+            ..SnarlStyle::new()
+        };
+
+        let mut snarl = Snarl::new();
+        let nodes = [
+            snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0)),
+            snarl.insert_node(pos2(100.0, 0.0), Pins(0, 0)),
+            snarl.insert_node(pos2(200.0, 0.0), Pins(0, 0)),
+        ];
+
+        let mut viewer = TestViewer::default();
+        let mut drawn = HashSet::<NodeId>::default();
+
+        for _ in 0..nodes.len() {
+            viewer.node_rects.clear();
+            run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+
+            assert_eq!(viewer.node_rects.len(), 1);
+            drawn.extend(viewer.node_rects.keys().copied());
         }
+
+        assert_eq!(drawn.len(), nodes.len());
     }
 
-    /// Iterates over mutable references to each node and its position.
-    pub fn nodes_pos_mut(&mut self) -> NodesPosIterMut<'_, T> {
-        NodesPosIterMut {
-            nodes: self.nodes.iter_mut(),
+    #[test]
+    fn culled_body_is_updated_when_needed() {
+        let cx = egui::Context::default();
+        let style = SnarlStyle {
+            max_nodes_per_frame: Some(1),
+            ..SnarlStyle::new()
+        };
+
+        let mut snarl = Snarl::new();
+        let nodes = [
+            snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0)),
+            snarl.insert_node(pos2(100.0, 0.0), Pins(0, 0)),
+            snarl.insert_node(pos2(200.0, 0.0), Pins(0, 0)),
+        ];
+
+        let mut viewer = TestViewer {
+            body: Some(|_| false),
+            body_needs_update: true,
+            ..TestViewer::default()
+        };
+        let mut passes = Vec::new();
+
+        for _ in 0..4 {
+            passes.push(cx.cumulative_pass_nr());
+            run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
         }
-    }
 
-    /// Iterates over shared references to each node and its identifier.
-    pub fn node_ids(&self) -> NodesIdsIter<'_, T> {
-        NodesIdsIter {
-            nodes: self.nodes.iter(),
+        // Only one node is drawn per pass,
+        // yet body of each node is shown exactly once per pass.
+        for node in nodes {
+            let shown = viewer
+                .bodies_shown
+                .iter()
+                .filter(|(shown, _)| *shown == node)
+                .map(|&(_, pass)| pass)
+                .collect::<Vec<_>>();
+            assert_eq!(shown, passes);
         }
     }
 
-    /// Iterates over mutable references to each node and its identifier.
-    pub fn nodes_ids_mut(&mut self) -> NodesIdsIterMut<'_, T> {
-        NodesIdsIterMut {
-            nodes: self.nodes.iter_mut(),
-        }
+    #[test]
+    fn cached_body_is_shown_once_per_key() {
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+
+        let mut viewer = TestViewer {
+            body: Some(|ui| {
+                ui.label("Plot");
+                false
+            }),
+            body_cache_key: Some(0),
+            ..TestViewer::default()
+        };
+
+        let mut run = |viewer: &mut TestViewer| {
+            for _ in 0..4 {
+                run_frame(&cx, &mut snarl, viewer, &style, Vec::new());
+            }
+            viewer.bodies_shown.len()
+        };
+
+        // Body is shown until node's layout settles, then replayed from cache.
+        let shown = run(&mut viewer);
+        assert_eq!(run(&mut viewer), shown);
+
+        // New key invalidates the cache.
+        viewer.body_cache_key = Some(1);
+        assert!(run(&mut viewer) > shown);
     }
 
-    /// Iterates over shared references to each node, its position and its identifier.
-    pub fn nodes_pos_ids(&self) -> NodesPosIdsIter<'_, T> {
-        NodesPosIdsIter {
-            nodes: self.nodes.iter(),
-        }
+    #[test]
+    fn click_keeps_selection_updates_primary() {
+        use egui::{Event, Modifiers, PointerButton, RawInput};
+
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.click_keeps_selection = Some(true);
+
+        let mut snarl = Snarl::new();
+        let first = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let second = snarl.insert_node(pos2(200.0, 0.0), Pins(0, 0));
+
+        let mut run = |events: Vec<Event>, modifiers: Modifiers| {
+            let input = RawInput {
+                modifiers,
+                ..screen_input(events)
+            };
+            let frame = run_input(&cx, input, &mut snarl, &mut PinsViewer, &style);
+            (frame.selected, frame.primary)
+        };
+
+        // Lay out the nodes. Graph origin is at the center of the screen.
+        run(Vec::new(), Modifiers::NONE);
+        run(Vec::new(), Modifiers::NONE);
+
+        let mut click = |pos: Pos2, modifiers: Modifiers| {
+            let button = |pressed| Event::PointerButton {
+                pos,
+                button: PointerButton::Primary,
+                pressed,
+                modifiers,
+            };
+            run(vec![Event::PointerMoved(pos), button(true)], modifiers);
+            run(vec![button(false)], modifiers)
+        };
+
+        click(pos2(404.0, 304.0), Modifiers::SHIFT);
+        let (selected, primary) = click(pos2(604.0, 304.0), Modifiers::SHIFT);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(primary, Some(second));
+
+        let (selected, primary) = click(pos2(404.0, 304.0), Modifiers::NONE);
+        assert_eq!(selected.len(), 2);
+        assert_eq!(primary, Some(first));
     }
 
-    /// Iterates over mutable references to each node, its position and its identifier.
-    pub fn nodes_pos_ids_mut(&mut self) -> NodesPosIdsIterMut<'_, T> {
-        NodesPosIdsIterMut {
-            nodes: self.nodes.iter_mut(),
+    #[test]
+    fn lasso_selects_enclosed_nodes() {
+        use egui::{Event, Modifiers, PointerButton, RawInput};
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let first = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        snarl.insert_node(pos2(200.0, 0.0), Pins(0, 0));
+
+        let mut run = |events: Vec<Event>| {
+            let input = RawInput {
+                modifiers: Modifiers::ALT,
+                ..screen_input(events)
+            };
+            run_input(&cx, input, &mut snarl, &mut PinsViewer, &style).selected
+        };
+
+        // Lay out the nodes. Graph origin is at the center of the screen.
+        run(Vec::new());
+        run(Vec::new());
+
+        let alt_button = |pos, pressed| Event::PointerButton {
+            pos,
+            button: PointerButton::Primary,
+            pressed,
+            modifiers: Modifiers::ALT,
+        };
+
+        // Lasso around the first node only.
+        let start = pos2(380.0, 280.0);
+        run(vec![Event::PointerMoved(start), alt_button(start, true)]);
+        for pos in [
+            pos2(430.0, 280.0),
+            pos2(480.0, 280.0),
+            pos2(480.0, 360.0),
+            pos2(380.0, 360.0),
+            pos2(380.0, 285.0),
+        ] {
+            run(vec![Event::PointerMoved(pos)]);
         }
+        let selected = run(vec![alt_button(pos2(380.0, 285.0), false)]);
+
+        assert_eq!(selected, vec![first]);
     }
 
-    /// Iterates over shared references to each node data.
-    pub fn nodes_info(&self) -> NodeInfoIter<'_, T> {
-        NodeInfoIter {
-            nodes: self.nodes.iter(),
+    #[test]
+    fn inline_pin_widget_is_not_clipped() {
+        use egui::Rect;
+
+        /// Shows inline `DragValue` on input pins.
+        struct InlineViewer {
+            hide_connected: bool,
+            value: f32,
+            widget: Option<(Rect, Rect)>,
         }
-    }
 
-    /// Iterates over mutable references to each node data.
-    pub fn nodes_info_mut(&mut self) -> NodeInfoIterMut<'_, T> {
-        NodeInfoIterMut {
-            nodes: self.nodes.iter_mut(),
+        impl SnarlViewer<Pins> for InlineViewer {
+            fn title(&mut self, _node: &Pins) -> String {
+                String::new()
+            }
+
+            fn inputs(&mut self, node: &Pins) -> usize {
+                node.0
+            }
+
+            fn outputs(&mut self, node: &Pins) -> usize {
+                node.1
+            }
+
+            fn show_input(
+                &mut self,
+                pin: &InPin,
+                ui: &mut Ui,
+                _scale: f32,
+                _snarl: &mut Snarl<Pins>,
+            ) -> PinInfo {
+                ui.label("value");
+                if !pin.is_connected() || !self.hide_connected {
+                    let r = ui.add(egui::DragValue::new(&mut self.value));
+                    self.widget = Some((r.rect, ui.clip_rect()));
+                }
+                PinInfo::default()
+            }
+
+            fn show_output(
+                &mut self,
+                _pin: &OutPin,
+                _ui: &mut Ui,
+                _scale: f32,
+                _snarl: &mut Snarl<Pins>,
+            ) -> PinInfo {
+                PinInfo::default()
+            }
         }
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(-200.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 0));
+
+        let mut viewer = InlineViewer {
+            hide_connected: true,
+            value: 0.0,
+            widget: None,
+        };
+
+        let run = |snarl: &mut Snarl<Pins>, viewer: &mut InlineViewer| {
+            viewer.widget = None;
+            run_frame(&cx, snarl, viewer, &style, Vec::new());
+        };
+
+        // Node grows to fit the widget within the very first frame.
+        run(&mut snarl, &mut viewer);
+        let (widget, clip) = viewer.widget.unwrap();
+        assert!(clip.contains_rect(widget));
+
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        run(&mut snarl, &mut viewer);
+        assert!(viewer.widget.is_none());
     }
 
-    /// Iterates over shared references to each node id and data.
-    pub fn nodes_ids_data(&self) -> NodeIdsDataIter<'_, T> {
-        NodeIdsDataIter {
-            nodes: self.nodes.iter(),
+    #[test]
+    fn node_key_keeps_widget_ids_stable() {
+        use egui::Id;
+
+        /// Uses number of outputs of `Pins` as node key and records ids of input widgets.
+        struct KeyedViewer(HashMap<usize, Id>);
+
+        impl SnarlViewer<Pins> for KeyedViewer {
+            fn title(&mut self, _node: &Pins) -> String {
+                String::new()
+            }
+
+            fn node_key(&mut self, node: &Pins) -> Option<Id> {
+                Some(Id::new(node.1))
+            }
+
+            fn inputs(&mut self, _node: &Pins) -> usize {
+                1
+            }
+
+            fn outputs(&mut self, _node: &Pins) -> usize {
+                0
+            }
+
+            fn show_input(
+                &mut self,
+                pin: &InPin,
+                ui: &mut Ui,
+                _scale: f32,
+                snarl: &mut Snarl<Pins>,
+            ) -> PinInfo {
+                self.0.insert(snarl[pin.id.node].1, ui.id());
+                PinInfo::default()
+            }
+
+            fn show_output(
+                &mut self,
+                _pin: &OutPin,
+                _ui: &mut Ui,
+                _scale: f32,
+                _snarl: &mut Snarl<Pins>,
+            ) -> PinInfo {
+                PinInfo::default()
+            }
         }
+
+        let widget_ids = |snarl: &mut Snarl<Pins>| {
+            let cx = egui::Context::default();
+            let mut viewer = KeyedViewer(HashMap::default());
+            for _ in 0..2 {
+                run_frame(&cx, snarl, &mut viewer, &SnarlStyle::new(), Vec::new());
+            }
+            viewer.0
+        };
+
+        let mut first = Snarl::new();
+        let a = first.insert_node(pos2(0.0, 0.0), Pins(1, 7));
+
+        // Same node gets different `NodeId` in another graph.
+        let mut second = Snarl::new();
+        second.insert_node(pos2(0.0, 100.0), Pins(1, 3));
+        let b = second.insert_node(pos2(0.0, 0.0), Pins(1, 7));
+        assert_ne!(a, b);
+
+        assert_eq!(widget_ids(&mut first)[&7], widget_ids(&mut second)[&7]);
     }
 
-    /// Iterates over mutable references to each node id and data.
-    pub fn nodes_ids_data_mut(&mut self) -> NodeIdsDataIterMut<'_, T> {
-        NodeIdsDataIterMut {
-            nodes: self.nodes.iter_mut(),
-        }
+    #[test]
+    fn show_reports_changed_graph() {
+        use egui::Event;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        let mut run = |events: Vec<Event>| {
+            run_frame(&cx, &mut snarl, &mut PinsViewer, &style, events)
+                .response
+                .changed
+        };
+
+        // Graph origin is at the center of the screen.
+        run(Vec::new());
+        assert!(!run(vec![Event::PointerMoved(pos2(404.0, 304.0))]));
+
+        assert!(!run(vec![button(pos2(404.0, 304.0), true)]));
+        run(vec![Event::PointerMoved(pos2(454.0, 304.0))]);
+        assert!(run(vec![Event::PointerMoved(pos2(464.0, 314.0))]));
     }
 
-    /// Iterates over wires.
-    pub fn wires(&self) -> impl Iterator<Item = (OutPinId, InPinId)> + '_ {
-        self.wires.iter().map(|wire| (wire.out_pin, wire.in_pin))
+    #[test]
+    fn show_reports_cursor_graph_pos() {
+        use egui::Event;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        let mut run = |events: Vec<Event>| {
+            run_frame(&cx, &mut snarl, &mut PinsViewer, &style, events)
+                .response
+                .cursor_graph_pos
+        };
+
+        // Graph origin is at the center of the screen.
+        assert_eq!(run(Vec::new()), None);
+        assert_eq!(
+            run(vec![Event::PointerMoved(pos2(450.0, 320.0))]),
+            Some(pos2(50.0, 20.0))
+        );
+        assert_eq!(run(vec![Event::PointerGone]), None);
     }
 
-    /// Returns input pin of the node.
-    #[must_use]
-    pub fn in_pin(&self, pin: InPinId) -> InPin {
-        InPin::new(self, pin)
+    #[test]
+    fn scroll_over_node_body_scrolls_its_widgets() {
+        use egui::{Event, Modifiers, MouseWheelUnit};
+
+        // Returns pointer position in graph space after scrolling over node body.
+        let scroll_over_body = |nodes_capture_scroll: bool| {
+            let cx = egui::Context::default();
+            let mut style = SnarlStyle::new();
+            style.capture_scroll = Some(true);
+            style.nodes_capture_scroll = Some(nodes_capture_scroll);
+
+            let mut snarl = Snarl::new();
+            snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+            // Shows tall scroll area in node body.
+            let mut viewer = TestViewer {
+                body: Some(|ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(100.0)
+                        .show(ui, |ui| {
+                            for i in 0..50 {
+                                ui.label(format!("Scrollable line {i}"));
+                            }
+                        });
+                    false
+                }),
+                ..TestViewer::default()
+            };
+
+            let mut cursor = None;
+            for frame in 0..10 {
+                let mut events = vec![Event::PointerMoved(pos2(420.0, 370.0))];
+                if frame == 3 {
+                    events.push(Event::MouseWheel {
+                        unit: MouseWheelUnit::Point,
+                        delta: egui::vec2(0.0, -50.0),
+                        modifiers: Modifiers::NONE,
+                    });
+                }
+
+                cursor = run_frame(&cx, &mut snarl, &mut viewer, &style, events)
+                    .response
+                    .cursor_graph_pos;
+            }
+            cursor
+        };
+
+        // Graph origin is at the center of the screen.
+        assert_eq!(scroll_over_body(true), Some(pos2(20.0, 70.0)));
+        assert_ne!(scroll_over_body(false), Some(pos2(20.0, 70.0)));
     }
 
-    /// Returns output pin of the node.
-    #[must_use]
-    pub fn out_pin(&self, pin: OutPinId) -> OutPin {
-        OutPin::new(self, pin)
+    #[test]
+    fn zoom_is_ignored_during_node_drag() {
+        use egui::Event;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let mut viewer = TestViewer::default();
+
+        let mut run = |snarl: &mut Snarl<Pins>, events: Vec<Event>| {
+            run_frame(&cx, snarl, &mut viewer, &style, events);
+            viewer.viewport
+        };
+
+        // Lay out the node. Graph origin is at the center of the screen.
+        run(&mut snarl, Vec::new());
+        run(&mut snarl, Vec::new());
+
+        let start = pos2(404.0, 304.0);
+        run(
+            &mut snarl,
+            vec![Event::PointerMoved(start), button(start, true)],
+        );
+        let before = run(&mut snarl, vec![Event::PointerMoved(pos2(444.0, 304.0))]);
+
+        assert_ne!(snarl.nodes[node.0].pos, pos2(0.0, 0.0));
+
+        run(
+            &mut snarl,
+            vec![Event::PointerMoved(pos2(454.0, 304.0)), Event::Zoom(2.0)],
+        );
+        let after = run(&mut snarl, vec![Event::PointerMoved(pos2(464.0, 304.0))]);
+
+        assert!(before.is_some());
+        assert_eq!(before, after);
     }
-}
 
-impl<T> Index<NodeId> for Snarl<T> {
-    type Output = T;
+    #[test]
+    fn node_visuals_combine_states() {
+        use crate::ui::{resolve_node_visuals, NodeVisualState, SelectionStyle};
+        use egui::{Color32, CornerRadius, Margin, Stroke};
 
-    #[inline]
-    #[track_caller]
-    fn index(&self, idx: NodeId) -> &Self::Output {
-        &self.nodes[idx.0].value
+        let egui_style = egui::Style::default();
+
+        let mut style = SnarlStyle::new();
+        style.node_hover_stroke = Some(Stroke::new(3.0, Color32::RED));
+        style.select_style = Some(SelectionStyle {
+            margin: Margin::same(4),
+            corner_radius: CornerRadius::same(2),
+            fill: Color32::BLUE,
+            stroke: Stroke::new(1.0, Color32::GREEN),
+        });
+        style.primary_selection_stroke = Some(Stroke::new(2.0, Color32::YELLOW));
+
+        // Everything is zoomed.
+        let scale = 2.0;
+        let select_style = SelectionStyle {
+            margin: Margin::same(8),
+            corner_radius: CornerRadius::same(4),
+            fill: Color32::BLUE,
+            stroke: Stroke::new(2.0, Color32::GREEN),
+        };
+
+        for bits in 0..32u8 {
+            let state = NodeVisualState {
+                hovered: bits & 1 != 0,
+                selected: bits & 2 != 0,
+                primary: bits & 4 != 0,
+                dimmed: bits & 8 != 0,
+                in_cycle: bits & 16 != 0,
+            };
+            let visuals = resolve_node_visuals(state, scale, &style, &egui_style);
+
+            let expected_stroke = state.hovered.then_some(Stroke::new(6.0, Color32::RED));
+            assert_eq!(visuals.hover_stroke, expected_stroke, "{state:?}");
+
+            let expected_selection = if state.primary {
+                Some(SelectionStyle {
+                    stroke: Stroke::new(4.0, Color32::YELLOW),
+                    ..select_style
+                })
+            } else if state.selected {
+                Some(select_style)
+            } else {
+                None
+            };
+            assert_eq!(visuals.selection, expected_selection, "{state:?}");
+
+            let expected_opacity = if state.dimmed { 0.3 } else { 1.0 };
+            assert!(
+                (visuals.opacity - expected_opacity).abs() < f32::EPSILON,
+                "{state:?}"
+            );
+
+            assert_eq!(visuals.cycle_tint.is_some(), state.in_cycle, "{state:?}");
+        }
+
+        // Without hover stroke in style it is taken from egui visuals.
+        let hovered = NodeVisualState {
+            hovered: true,
+            ..NodeVisualState::default()
+        };
+        let visuals = resolve_node_visuals(hovered, scale, &SnarlStyle::new(), &egui_style);
+        assert_eq!(
+            visuals.hover_stroke,
+            Some(egui_style.visuals.widgets.hovered.bg_stroke)
+        );
     }
-}
 
-impl<T> IndexMut<NodeId> for Snarl<T> {
-    #[inline]
-    #[track_caller]
-    fn index_mut(&mut self, idx: NodeId) -> &mut Self::Output {
-        &mut self.nodes[idx.0].value
+    #[test]
+    fn from_description_validates_wires() {
+        let node = |kind: &str, inputs, outputs| NodeDescription {
+            kind: kind.to_owned(),
+            pos: pos2(10.0, 20.0),
+            inputs,
+            outputs,
+        };
+        let wire = |from_node, output, to_node, input| WireDescription {
+            from_node,
+            output,
+            to_node,
+            input,
+        };
+        let make = |kind: &str| match kind {
+            "source" => Pins(0, 1),
+            _ => Pins(2, 0),
+        };
+
+        let mut desc = GraphDescription {
+            nodes: vec![
+                node("source", Some(0), Some(1)),
+                node("sink", Some(2), None),
+            ],
+            wires: vec![wire(0, 0, 1, 0), wire(0, 0, 1, 1)],
+        };
+
+        let snarl = Snarl::from_description(&desc, &mut PinsViewer, make).unwrap();
+        assert_eq!(snarl.node_count(), 2);
+        let inputs = snarl.nodes().map(|pins| pins.0).collect::<Vec<_>>();
+        assert_eq!(inputs, [0, 2]);
+        assert_eq!(snarl.wire_count(), 2);
+
+        desc.wires.push(wire(0, 1, 1, 0));
+        assert_eq!(
+            Snarl::from_description(&desc, &mut PinsViewer, make).unwrap_err(),
+            DescriptionError::OutputOutOfRange {
+                wire: 2,
+                node: 0,
+                output: 1
+            }
+        );
+
+        desc.wires[2] = wire(0, 0, 1, 2);
+        assert_eq!(
+            Snarl::from_description(&desc, &mut PinsViewer, make).unwrap_err(),
+            DescriptionError::InputOutOfRange {
+                wire: 2,
+                node: 1,
+                input: 2
+            }
+        );
+
+        // Undeclared pins are checked against the viewer.
+        desc.nodes[1].inputs = None;
+        assert_eq!(
+            Snarl::from_description(&desc, &mut PinsViewer, make).unwrap_err(),
+            DescriptionError::InputOutOfRange {
+                wire: 2,
+                node: 1,
+                input: 2
+            }
+        );
+
+        // Nodes are not created for invalid description.
+        desc.wires[2] = wire(0, 0, 5, 0);
+        let mut made = 0;
+        let result = Snarl::from_description(&desc, &mut PinsViewer, |kind| {
+            made += 1;
+            make(kind)
+        });
+        assert_eq!(
+            result.unwrap_err(),
+            DescriptionError::NodeNotFound { wire: 2, node: 5 }
+        );
+        assert_eq!(made, 0);
     }
-}
 
-/// Iterator over shared references to nodes.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesIter<'a, T> {
-    nodes: slab::Iter<'a, Node<T>>,
-}
+    #[test]
+    fn directional_box_select_depends_on_drag_direction() {
+        use crate::ui::BoxSelectMode;
 
-impl<'a, T> Iterator for NodesIter<'a, T> {
-    type Item = &'a T;
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.box_select_mode = Some(BoxSelectMode::Directional);
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+
+        // Lay out the node. Graph origin is at the center of the screen.
+        run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+        run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+
+        // Rect covers only left edge of the node.
+        let mut select = |from: Pos2, to: Pos2| {
+            drag(&cx, &mut snarl, &mut PinsViewer, &style, from, to).selected
+        };
+
+        let left_top = pos2(380.0, 280.0);
+        let right_bottom = pos2(405.0, 380.0);
+
+        assert!(select(left_top, right_bottom).is_empty());
+        assert_eq!(select(right_bottom, left_top), vec![node]);
     }
 
-    fn next(&mut self) -> Option<&'a T> {
-        let (_, node) = self.nodes.next()?;
-        Some(&node.value)
+    #[test]
+    fn pin_anchor_overrides_wire_endpoint() {
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(200.0, 50.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let mut viewer = TestViewer {
+            outputs_at_bottom: true,
+            ..TestViewer::default()
+        };
+        let mut ui_id = egui::Id::NULL;
+        for _ in 0..3 {
+            ui_id = run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new()).ui_id;
+        }
+
+        // Graph origin is at the center of the screen.
+        let anchor = viewer.output_anchor.unwrap() - egui::vec2(400.0, 300.0);
+
+        let path = snarl
+            .wire_path_at("snarl", ui_id, &cx, out_pin(a, 0), in_pin(b, 0))
+            .unwrap();
+        let (start, _) = path.sample(0.0);
+        assert!((start - anchor).length() < 0.01);
+    }
+
+    #[test]
+    fn outside_pin_labels_do_not_widen_node() {
+        use crate::ui::PinLabelPlacement;
+
+        let show = |placement: PinLabelPlacement| {
+            let cx = egui::Context::default();
+            let mut style = SnarlStyle::new();
+            style.pin_label_placement = Some(placement);
+
+            let mut snarl = Snarl::new();
+            let a = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+            let b = snarl.insert_node(pos2(300.0, 0.0), Pins(1, 1));
+            snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+            let mut viewer = TestViewer {
+                input_label: Some("rather long input label"),
+                output_label: Some("rather long output label"),
+                ..TestViewer::default()
+            };
+            let mut ui_id = egui::Id::NULL;
+            for _ in 0..3 {
+                ui_id = run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new()).ui_id;
+            }
+
+            let path = snarl
+                .wire_path_at("snarl", ui_id, &cx, out_pin(a, 0), in_pin(b, 0))
+                .unwrap();
+            let (start, _) = path.sample(0.0);
+
+            // Graph origin is at the center of the screen.
+            (viewer.rect(a), start + egui::vec2(400.0, 300.0))
+        };
+
+        let (inside, _) = show(PinLabelPlacement::Inside);
+        let (outside, start) = show(PinLabelPlacement::Outside);
+        assert!(outside.width() < inside.width());
+
+        // Wire starts at the pin, not at the end of the label outside.
+        assert!(start.x <= outside.max.x);
+        assert!(start.x >= outside.min.x);
     }
 
-    fn nth(&mut self, n: usize) -> Option<&'a T> {
-        let (_, node) = self.nodes.nth(n)?;
-        Some(&node.value)
+    #[test]
+    fn disconnect_node_returns_severed_wires() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 2));
+        let b = snarl.insert_node(pos2(100.0, 0.0), Pins(2, 1));
+        let c = snarl.insert_node(pos2(200.0, 0.0), Pins(1, 1));
+        let lonely = snarl.insert_node(pos2(300.0, 0.0), Pins(1, 1));
+
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(a, 1), in_pin(b, 1));
+        snarl.connect(out_pin(b, 0), in_pin(c, 0));
+        snarl.connect(out_pin(c, 0), in_pin(a, 0));
+
+        assert!(snarl.disconnect_node(lonely).is_empty());
+        assert_eq!(snarl.wire_count(), 4);
+
+        let mut severed = snarl.disconnect_node(b);
+        severed.sort();
+        assert_eq!(
+            severed,
+            vec![
+                (out_pin(a, 0), in_pin(b, 0)),
+                (out_pin(a, 1), in_pin(b, 1)),
+                (out_pin(b, 0), in_pin(c, 0)),
+            ]
+        );
+        assert_eq!(
+            snarl.wires().collect::<Vec<_>>(),
+            vec![(out_pin(c, 0), in_pin(a, 0))]
+        );
+        assert_eq!(snarl.neighbors(b).count(), 0);
+        assert!(snarl.disconnect_node(b).is_empty());
     }
-}
 
-/// Iterator over mutable references to nodes.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesIterMut<'a, T> {
-    nodes: slab::IterMut<'a, Node<T>>,
-}
+    #[test]
+    fn find_cycles_reports_components_and_self_loops() {
+        let mut snarl = Snarl::new();
+        let nodes = (0..6)
+            .map(|_| snarl.insert_node(pos2(0.0, 0.0), Pins(2, 2)))
+            .collect::<Vec<_>>();
+        let [a, b, c, d, e, f] = nodes[..] else {
+            unreachable!()
+        };
 
-impl<'a, T> Iterator for NodesIterMut<'a, T> {
-    type Item = &'a mut T;
+        assert!(snarl.find_cycles().is_empty());
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
-    }
+        // a -> b -> c -> a, d -> d, e -> f.
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(b, 0), in_pin(c, 0));
+        snarl.connect(out_pin(c, 0), in_pin(a, 0));
+        snarl.connect(out_pin(c, 1), in_pin(e, 0));
+        snarl.connect(out_pin(d, 0), in_pin(d, 0));
+        snarl.connect(out_pin(e, 0), in_pin(f, 0));
 
-    fn next(&mut self) -> Option<&'a mut T> {
-        let (_, node) = self.nodes.next()?;
-        Some(&mut node.value)
-    }
+        assert_eq!(snarl.find_cycles(), vec![vec![a, b, c], vec![d]]);
 
-    fn nth(&mut self, n: usize) -> Option<&'a mut T> {
-        let (_, node) = self.nodes.nth(n)?;
-        Some(&mut node.value)
+        snarl.disconnect(out_pin(b, 0), in_pin(c, 0));
+        snarl.remove_node(d);
+        assert!(snarl.find_cycles().is_empty());
     }
-}
-
-/// Iterator over shared references to nodes and their positions.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesPosIter<'a, T> {
-    nodes: slab::Iter<'a, Node<T>>,
-}
 
-impl<'a, T> Iterator for NodesPosIter<'a, T> {
-    type Item = (Pos2, &'a T);
+    #[test]
+    fn merge_topology_keeps_placement() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(10.0, 20.0), 1);
+        let b = snarl.insert_node(pos2(300.0, 20.0), 2);
+        let c = snarl.insert_node(pos2(10.0, 200.0), 3);
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(b, 0), in_pin(c, 0));
+
+        let mut topology = snarl.serialize_topology();
+        assert_eq!(topology.nodes, vec![(a, 1), (b, 2), (c, 3)]);
+
+        // Value of `a` changes, `c` is removed and `d` is wired after `b`.
+        let d = NodeId(7);
+        topology.nodes[0].1 = 10;
+        topology.nodes.retain(|&(id, _)| id != c);
+        topology.nodes.push((d, 4));
+        topology.wires = vec![(out_pin(a, 0), in_pin(b, 0)), (out_pin(b, 0), in_pin(d, 0))];
+
+        // Positions changed after the topology was taken are kept.
+        snarl.get_node_info_mut(b).unwrap().pos = pos2(400.0, 50.0);
+
+        assert_eq!(snarl.merge_topology(topology), Ok(vec![d]));
+        assert_eq!(snarl[a], 10);
+        assert!(snarl.get_node(c).is_none());
+        assert_eq!(snarl.get_node_info(a).unwrap().pos, pos2(10.0, 20.0));
+        assert_eq!(snarl.get_node_info(b).unwrap().pos, pos2(400.0, 50.0));
+        assert_eq!(snarl.get_node_info(d).unwrap().pos, pos2(600.0, 50.0));
+        let mut wires = snarl.wires().collect::<Vec<_>>();
+        wires.sort();
+        assert_eq!(
+            wires,
+            vec![(out_pin(a, 0), in_pin(b, 0)), (out_pin(b, 0), in_pin(d, 0))]
+        );
+
+        // Unwired new node goes below the graph.
+        let mut topology = snarl.serialize_topology();
+        topology.nodes.push((NodeId(8), 5));
+        snarl.merge_topology(topology).unwrap();
+        assert_eq!(
+            snarl.get_node_info(NodeId(8)).unwrap().pos,
+            pos2(10.0, 150.0)
+        );
+    }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
+    #[test]
+    fn merge_topology_rejects_duplicates_and_remaps_huge_ids() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(10.0, 20.0), 1);
+
+        let mut topology = snarl.serialize_topology();
+        topology.nodes.push((a, 2));
+        assert_eq!(
+            snarl.merge_topology(topology),
+            Err(TopologyError::DuplicateNode { node: a })
+        );
+        assert_eq!(snarl[a], 1);
+
+        // Huge id would leave the graph almost vacant and is replaced with a free one.
+        let huge = NodeId(usize::MAX / 2);
+        let mut topology = snarl.serialize_topology();
+        topology.nodes.push((huge, 2));
+        topology.wires.push((out_pin(a, 0), in_pin(huge, 0)));
+
+        let added = snarl.merge_topology(topology).unwrap();
+        assert_eq!(added, vec![NodeId(1)]);
+        assert_eq!(snarl[NodeId(1)], 2);
+        assert_eq!(
+            snarl.wires().collect::<Vec<_>>(),
+            vec![(out_pin(a, 0), in_pin(NodeId(1), 0))]
+        );
+        assert_eq!(
+            snarl.get_node_info(NodeId(1)).unwrap().pos,
+            pos2(210.0, 20.0)
+        );
     }
 
-    fn next(&mut self) -> Option<(Pos2, &'a T)> {
-        let (_, node) = self.nodes.next()?;
-        Some((node.pos, &node.value))
+    #[test]
+    fn merge_topology_skips_dangling_wires_to_remapped_ids() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(10.0, 20.0), 1);
+
+        // Node 1 is missing, and remapped huge id takes its slot.
+        let huge = NodeId(usize::MAX / 2);
+        let mut topology = snarl.serialize_topology();
+        topology.nodes.push((huge, 2));
+        topology.wires.push((out_pin(a, 0), in_pin(huge, 0)));
+        topology.wires.push((out_pin(a, 0), in_pin(NodeId(1), 1)));
+
+        let added = snarl.merge_topology(topology).unwrap();
+        assert_eq!(added, vec![NodeId(1)]);
+        assert_eq!(
+            snarl.wires().collect::<Vec<_>>(),
+            vec![(out_pin(a, 0), in_pin(NodeId(1), 0))]
+        );
     }
 
-    fn nth(&mut self, n: usize) -> Option<(Pos2, &'a T)> {
-        let (_, node) = self.nodes.nth(n)?;
-        Some((node.pos, &node.value))
+    #[cfg(feature = "serde")]
+    #[test]
+    fn topology_round_trip_ignores_positions() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(10.0, 20.0), 1);
+        let b = snarl.insert_node(pos2(300.0, 20.0), 2);
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let json = serde_json::to_string(&snarl.serialize_topology()).unwrap();
+        assert!(!json.contains("pos"));
+
+        // Moving nodes does not change serialized topology.
+        snarl.get_node_info_mut(a).unwrap().pos = pos2(-50.0, 70.0);
+        assert_eq!(
+            serde_json::to_string(&snarl.serialize_topology()).unwrap(),
+            json
+        );
+
+        let topology: SnarlTopology<i32> = serde_json::from_str(&json).unwrap();
+        assert!(snarl.merge_topology(topology).unwrap().is_empty());
+        assert_eq!(snarl.get_node_info(a).unwrap().pos, pos2(-50.0, 70.0));
+        assert_eq!(
+            snarl.serialize_topology(),
+            serde_json::from_str(&json).unwrap()
+        );
     }
-}
 
-/// Iterator over mutable references to nodes and their positions.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesPosIterMut<'a, T> {
-    nodes: slab::IterMut<'a, Node<T>>,
-}
+    #[test]
+    fn nodes_in_rect_intersects_or_contains() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        let b = snarl.insert_node(pos2(100.0, 0.0), Pins(1, 1));
+        let c = snarl.insert_node(pos2(300.0, 0.0), Pins(1, 1));
+
+        let mut measurement = SnarlMeasurement::default();
+        for id in [a, b, c] {
+            measurement.nodes.insert(
+                id,
+                NodeMeasurement {
+                    size: egui::vec2(80.0, 40.0),
+                    ..NodeMeasurement::default()
+                },
+            );
+        }
 
-impl<'a, T> Iterator for NodesPosIterMut<'a, T> {
-    type Item = (Pos2, &'a mut T);
+        let rect = egui::Rect::from_min_max(pos2(-10.0, -10.0), pos2(150.0, 50.0));
+        assert_eq!(snarl.nodes_in_rect(rect, &measurement, false), vec![a, b]);
+        assert_eq!(snarl.nodes_in_rect(rect, &measurement, true), vec![a]);
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
+        // Touching edges intersect.
+        let rect = egui::Rect::from_min_max(pos2(180.0, 0.0), pos2(300.0, 10.0));
+        assert_eq!(snarl.nodes_in_rect(rect, &measurement, false), vec![b, c]);
+        assert!(snarl.nodes_in_rect(rect, &measurement, true).is_empty());
     }
 
-    fn next(&mut self) -> Option<(Pos2, &'a mut T)> {
-        let (_, node) = self.nodes.next()?;
-        Some((node.pos, &mut node.value))
+    #[test]
+    fn node_degree_counts_wires() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(2, 2));
+        let b = snarl.insert_node(pos2(0.0, 0.0), Pins(2, 2));
+        let lonely = snarl.insert_node(pos2(0.0, 0.0), Pins(2, 2));
+
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(a, 1), in_pin(b, 0));
+        snarl.connect(out_pin(b, 0), in_pin(b, 1));
+
+        assert_eq!(snarl.node_degree(a), (0, 2));
+        assert_eq!(snarl.node_degree(b), (3, 1));
+        assert_eq!(snarl.in_degree(b), 3);
+        assert_eq!(snarl.out_degree(b), 1);
+        assert_eq!(snarl.node_degree(lonely), (0, 0));
+
+        snarl.remove_node(a);
+        assert_eq!(snarl.node_degree(b), (1, 1));
     }
 
-    fn nth(&mut self, n: usize) -> Option<(Pos2, &'a mut T)> {
-        let (_, node) = self.nodes.nth(n)?;
-        Some((node.pos, &mut node.value))
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip_keeps_node_ui_state() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node_collapsed(pos2(10.0, 20.0), ());
+        let b = snarl.insert_node(pos2(100.0, 0.0), ());
+        snarl.set_node_size(b, Some(egui::vec2(120.0, 80.0)));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+
+        let json = serde_json::to_string(&snarl).unwrap();
+        let loaded: Snarl<()> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(loaded.layout_snapshot(), snarl.layout_snapshot());
+        assert!(!loaded.get_node_info(a).unwrap().open);
+        assert_eq!(
+            loaded.get_node_info(b).unwrap().size,
+            Some(egui::vec2(120.0, 80.0))
+        );
+
+        // Wires of nodes removed from the document are dropped.
+        let mut value = serde_json::to_value(&snarl).unwrap();
+        value["wires"].as_array_mut().unwrap().push(
+            serde_json::to_value(Wire {
+                out_pin: out_pin(b, 0),
+                in_pin: in_pin(NodeId(7), 0),
+            })
+            .unwrap(),
+        );
+
+        let loaded: Snarl<()> = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            loaded.wires().collect::<Vec<_>>(),
+            vec![(out_pin(a, 0), in_pin(b, 0))]
+        );
     }
-}
 
-/// Iterator over shared references to nodes and their identifiers.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesIdsIter<'a, T> {
-    nodes: slab::Iter<'a, Node<T>>,
-}
+    #[test]
+    fn hidden_resize_handle_can_be_dragged() {
+        use crate::ui::HandleVisibility;
 
-impl<'a, T> Iterator for NodesIdsIter<'a, T> {
-    type Item = (NodeId, &'a T);
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.resizable_nodes = Some(true);
+        style.resize_handle_visibility = Some(HandleVisibility::Never);
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
-    }
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
 
-    fn next(&mut self) -> Option<(NodeId, &'a T)> {
-        let (idx, node) = self.nodes.next()?;
-        Some((NodeId(idx), &node.value))
-    }
+        let mut viewer = TestViewer::default();
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        let rect = viewer.rect(node);
 
-    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a T)> {
-        let (idx, node) = self.nodes.nth(n)?;
-        Some((NodeId(idx), &node.value))
+        let start = rect.max - egui::vec2(2.0, 2.0);
+        let end = start + egui::vec2(40.0, 30.0);
+        drag(&cx, &mut snarl, &mut viewer, &style, start, end);
+
+        let size = snarl.get_node_info(node).unwrap().size.unwrap();
+        assert!(size.x > rect.width());
+        assert_eq!(snarl.get_node_info(node).unwrap().pos, pos2(0.0, 0.0));
     }
-}
 
-/// Iterator over mutable references to nodes and their identifiers.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesIdsIterMut<'a, T> {
-    nodes: slab::IterMut<'a, Node<T>>,
-}
+    #[test]
+    fn aspect_ratio_is_kept_while_resizing() {
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.resizable_nodes = Some(true);
 
-impl<'a, T> Iterator for NodesIdsIterMut<'a, T> {
-    type Item = (NodeId, &'a mut T);
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        snarl.set_node_size(node, Some(egui::vec2(100.0, 100.0)));
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
+        // Keeps nodes twice as wide as tall.
+        let mut viewer = TestViewer {
+            aspect_ratio: Some(2.0),
+            ..TestViewer::default()
+        };
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        let rect = viewer.rect(node);
+
+        // Dragged only to the right, height follows width.
+        let start = rect.max - egui::vec2(2.0, 2.0);
+        let end = start + egui::vec2(60.0, 0.0);
+        drag(&cx, &mut snarl, &mut viewer, &style, start, end);
+
+        let size = snarl.get_node_info(node).unwrap().size.unwrap();
+        assert!(size.x > 200.0);
+        assert!((size.x - size.y * 2.0).abs() < 0.01);
     }
 
-    fn next(&mut self) -> Option<(NodeId, &'a mut T)> {
-        let (idx, node) = self.nodes.next()?;
-        Some((NodeId(idx), &mut node.value))
-    }
+    #[test]
+    fn aspect_ratio_node_shrinks_with_content() {
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        let node = snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+
+        let mut viewer = TestViewer {
+            aspect_ratio: Some(2.0),
+            body: Some(|ui| {
+                ui.allocate_space(egui::vec2(300.0, 20.0));
+                false
+            }),
+            ..TestViewer::default()
+        };
+        for _ in 0..3 {
+            run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        }
+        let wide = viewer.rect(node);
 
-    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a mut T)> {
-        let (idx, node) = self.nodes.nth(n)?;
-        Some((NodeId(idx), &mut node.value))
+        viewer.body = Some(|ui| {
+            ui.allocate_space(egui::vec2(20.0, 20.0));
+            false
+        });
+        for _ in 0..3 {
+            run_frame(&cx, &mut snarl, &mut viewer, &style, Vec::new());
+        }
+        let narrow = viewer.rect(node);
+
+        assert!(narrow.width() < wide.width() - 100.0);
+        assert!(narrow.height() < wide.height());
     }
-}
 
-/// Iterator over shared references to nodes, their positions and their identifiers.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesPosIdsIter<'a, T> {
-    nodes: slab::Iter<'a, Node<T>>,
-}
+    #[test]
+    fn view_center_follows_external_scrollbar() {
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
 
-impl<'a, T> Iterator for NodesPosIdsIter<'a, T> {
-    type Item = (NodeId, Pos2, &'a T);
+        let mut snarl = Snarl::new();
+        snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+        snarl.insert_node(pos2(1000.0, 500.0), Pins(1, 1));
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
-    }
+        let run =
+            |snarl: &mut Snarl<Pins>| run_frame(&cx, snarl, &mut PinsViewer, &style, Vec::new());
 
-    fn next(&mut self) -> Option<(NodeId, Pos2, &'a T)> {
-        let (idx, node) = self.nodes.next()?;
-        Some((NodeId(idx), node.pos, &node.value))
-    }
+        assert!(run(&mut snarl).response.viewport_changed.is_some());
+        run(&mut snarl);
+        let Frame {
+            ui_id, response, ..
+        } = run(&mut snarl);
+        assert_eq!(response.viewport_changed, None);
 
-    fn nth(&mut self, n: usize) -> Option<(NodeId, Pos2, &'a T)> {
-        let (idx, node) = self.nodes.nth(n)?;
-        Some((NodeId(idx), node.pos, &node.value))
+        let bounds = response.graph_bounds.unwrap();
+        assert!(bounds.contains(pos2(1000.0, 500.0)));
+
+        Snarl::<Pins>::set_view_center_at("snarl", ui_id, &cx, pos2(300.0, 200.0));
+        let viewport = run(&mut snarl).response.viewport_changed.unwrap();
+        assert!((viewport.center() - pos2(300.0, 200.0)).length() < 0.01);
+
+        // Center is kept within the graph.
+        Snarl::<Pins>::set_view_center_at("snarl", ui_id, &cx, pos2(5000.0, -5000.0));
+        let viewport = run(&mut snarl).response.viewport_changed.unwrap();
+        assert!((viewport.center() - pos2(bounds.max.x, bounds.min.y)).length() < 0.01);
     }
-}
 
-/// Iterator over mutable references to nodes, their positions and their identifiers.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodesPosIdsIterMut<'a, T> {
-    nodes: slab::IterMut<'a, Node<T>>,
-}
+    #[test]
+    fn wire_cache_reuses_meshes_across_frames() {
+        let cx = egui::Context::default();
+        let style = SnarlStyle {
+            wire_cache: Some(true),
+            ..SnarlStyle::new()
+        };
 
-impl<'a, T> Iterator for NodesPosIdsIterMut<'a, T> {
-    type Item = (NodeId, Pos2, &'a mut T);
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(-200.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(100.0, 0.0), Pins(1, 0));
+        snarl.connect(
+            OutPinId { node: a, output: 0 },
+            InPinId { node: b, input: 0 },
+        );
+
+        let stats = |ui_id: egui::Id| {
+            let cache = cx
+                .data_mut(|d| {
+                    d.get_temp::<crate::ui::TestWireCache>(ui_id.with("snarl").with("wire-cache"))
+                })
+                .unwrap();
+            (cache.hits, cache.misses)
+        };
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
-    }
+        let frame = run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+        assert_eq!(stats(frame.ui_id).1, 1);
 
-    fn next(&mut self) -> Option<(NodeId, Pos2, &'a mut T)> {
-        let (idx, node) = self.nodes.next()?;
-        Some((NodeId(idx), node.pos, &mut node.value))
+        // Layout settles in the first frames, then the wire is not tessellated again.
+        run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+        for _ in 0..3 {
+            let frame = run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new());
+            assert_eq!(stats(frame.ui_id), (1, 0));
+        }
     }
 
-    fn nth(&mut self, n: usize) -> Option<(NodeId, Pos2, &'a mut T)> {
-        let (idx, node) = self.nodes.nth(n)?;
-        Some((NodeId(idx), node.pos, &mut node.value))
-    }
-}
+    #[test]
+    fn wire_pin_gap_keeps_wire_away_from_pins() {
+        use crate::ui::NodeAnchor;
 
-/// Iterator over shared references to nodes.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodeInfoIter<'a, T> {
-    nodes: slab::Iter<'a, Node<T>>,
-}
+        let cx = egui::Context::default();
+        let mut style = SnarlStyle::new();
+        style.wire_pin_gap = Some(5.0);
 
-impl<'a, T> Iterator for NodeInfoIter<'a, T> {
-    type Item = &'a Node<T>;
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(200.0, 50.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
-    }
+        let mut ui_id = egui::Id::NULL;
+        for _ in 0..3 {
+            ui_id = run_frame(&cx, &mut snarl, &mut PinsViewer, &style, Vec::new()).ui_id;
+        }
 
-    fn next(&mut self) -> Option<&'a Node<T>> {
-        let (_, node) = self.nodes.next()?;
-        Some(node)
+        let path = snarl
+            .wire_path_at("snarl", ui_id, &cx, out_pin(a, 0), in_pin(b, 0))
+            .unwrap();
+        let from = snarl
+            .node_anchor_at("snarl", ui_id, &cx, a, NodeAnchor::Output(0))
+            .unwrap();
+        let to = snarl
+            .node_anchor_at("snarl", ui_id, &cx, b, NodeAnchor::Input(0))
+            .unwrap();
+
+        // Graph is shown at scale 1.
+        let (start, _) = path.sample(0.0);
+        let (end, _) = path.sample(1.0);
+        assert!((start - (from + egui::vec2(5.0, 0.0))).length() < 0.01);
+        assert!((end - (to - egui::vec2(5.0, 0.0))).length() < 0.01);
     }
 
-    fn nth(&mut self, n: usize) -> Option<&'a Node<T>> {
-        let (_, node) = self.nodes.nth(n)?;
-        Some(node)
+    #[test]
+    fn wires_with_nodes_yields_endpoint_values() {
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), "a");
+        let b = snarl.insert_node(pos2(100.0, 0.0), "b");
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
+        snarl.connect(out_pin(a, 1), in_pin(b, 1));
+        snarl.connect(out_pin(b, 0), in_pin(b, 2));
+
+        let mut wires = snarl
+            .wires_with_nodes()
+            .map(|(from, to, &from_value, &to_value)| (from, to, from_value, to_value))
+            .collect::<Vec<_>>();
+        wires.sort();
+
+        assert_eq!(
+            wires,
+            vec![
+                (out_pin(a, 0), in_pin(b, 0), "a", "b"),
+                (out_pin(a, 1), in_pin(b, 1), "a", "b"),
+                (out_pin(b, 0), in_pin(b, 2), "b", "b"),
+            ]
+        );
     }
-}
 
-/// Iterator over mutable references to nodes.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodeInfoIterMut<'a, T> {
-    nodes: slab::IterMut<'a, Node<T>>,
-}
+    #[test]
+    fn response_reports_hovered_element() {
+        use crate::ui::{HoverTarget, NodeAnchor};
+        use egui::Event;
 
-impl<'a, T> Iterator for NodeInfoIterMut<'a, T> {
-    type Item = &'a mut Node<T>;
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
-    }
+        let mut snarl = Snarl::new();
+        let a = snarl.insert_node(pos2(0.0, 0.0), Pins(0, 1));
+        let b = snarl.insert_node(pos2(200.0, 50.0), Pins(1, 0));
+        snarl.connect(out_pin(a, 0), in_pin(b, 0));
 
-    fn next(&mut self) -> Option<&'a mut Node<T>> {
-        let (_, node) = self.nodes.next()?;
-        Some(node)
-    }
+        let run = |snarl: &mut Snarl<Pins>, events: Vec<Event>| {
+            run_frame(&cx, snarl, &mut PinsViewer, &style, events)
+        };
 
-    fn nth(&mut self, n: usize) -> Option<&'a mut Node<T>> {
-        let (_, node) = self.nodes.nth(n)?;
-        Some(node)
-    }
-}
+        assert_eq!(run(&mut snarl, Vec::new()).response.hovered, None);
+        let ui_id = run(&mut snarl, Vec::new()).ui_id;
 
-/// Iterator over shared references to nodes.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodeIdsDataIter<'a, T> {
-    nodes: slab::Iter<'a, Node<T>>,
-}
+        // Graph is shown at scale 1 with origin at the center of the screen.
+        let anchor = |snarl: &Snarl<Pins>, node, anchor| {
+            snarl
+                .node_anchor_at("snarl", ui_id, &cx, node, anchor)
+                .unwrap()
+                + egui::vec2(400.0, 300.0)
+        };
+        let pin = anchor(&snarl, a, NodeAnchor::Output(0));
+        let body = anchor(&snarl, b, NodeAnchor::Center);
 
-impl<'a, T> Iterator for NodeIdsDataIter<'a, T> {
-    type Item = (NodeId, &'a Node<T>);
+        let mut hover = |pos| {
+            run(&mut snarl, vec![Event::PointerMoved(pos)]);
+            run(&mut snarl, Vec::new()).response.hovered
+        };
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
+        assert_eq!(hover(pin), Some(HoverTarget::OutPin(out_pin(a, 0))));
+        assert_eq!(hover(body), Some(HoverTarget::Node(b)));
+        assert_eq!(hover(pos2(50.0, 50.0)), Some(HoverTarget::Background));
     }
 
-    fn next(&mut self) -> Option<(NodeId, &'a Node<T>)> {
-        let (id, node) = self.nodes.next()?;
-        Some((NodeId(id), node))
-    }
+    #[test]
+    fn zoom_steps_keep_view_center() {
+        use egui::Rect;
+
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
+
+        let mut snarl = Snarl::new();
+        snarl.insert_node(pos2(0.0, 0.0), Pins(1, 1));
+
+        // Runs frames until zoom animation settles,
+        // returning last visible area and bounds of the graph.
+        let settle = |snarl: &mut Snarl<Pins>| {
+            let mut ui_id = egui::Id::NULL;
+            let mut viewport = Rect::NOTHING;
+            let mut bounds = None;
+            for _ in 0..30 {
+                let frame = run_frame(&cx, snarl, &mut PinsViewer, &style, Vec::new());
+                if let Some(changed) = frame.response.viewport_changed {
+                    viewport = changed;
+                }
+                bounds = frame.response.graph_bounds;
+                ui_id = frame.ui_id;
+            }
+            (viewport, bounds.unwrap(), ui_id)
+        };
 
-    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a Node<T>)> {
-        let (id, node) = self.nodes.nth(n)?;
-        Some((NodeId(id), node))
+        let (initial, _, ui_id) = settle(&mut snarl);
+
+        Snarl::<Pins>::zoom_in_step_at("snarl", ui_id, &cx);
+        let (zoomed, _, _) = settle(&mut snarl);
+        assert!((zoomed.width() * 1.25 - initial.width()).abs() < 0.1);
+        assert!((zoomed.center() - initial.center()).length() < 0.1);
+
+        Snarl::<Pins>::zoom_out_step_at("snarl", ui_id, &cx);
+        let (zoomed, _, _) = settle(&mut snarl);
+        assert!((zoomed.width() - initial.width()).abs() < 0.1);
+
+        // Steps requested before the next frame are added up.
+        Snarl::<Pins>::zoom_in_step_at("snarl", ui_id, &cx);
+        Snarl::<Pins>::zoom_in_step_at("snarl", ui_id, &cx);
+        let (zoomed, _, _) = settle(&mut snarl);
+        assert!((zoomed.width() * 1.25 * 1.25 - initial.width()).abs() < 0.1);
+
+        Snarl::<Pins>::zoom_out_step_at("snarl", ui_id, &cx);
+        Snarl::<Pins>::zoom_out_step_at("snarl", ui_id, &cx);
+        let (zoomed, _, _) = settle(&mut snarl);
+        assert!((zoomed.width() - initial.width()).abs() < 0.1);
+
+        // Scale is clamped to `SnarlStyle::max_scale`.
+        Snarl::<Pins>::zoom_to_at("snarl", ui_id, &cx, 100.0);
+        let (zoomed, _, _) = settle(&mut snarl);
+        assert!((zoomed.width() * 5.0 - initial.width()).abs() < 0.1);
+
+        Snarl::<Pins>::set_view_center_at("snarl", ui_id, &cx, pos2(500.0, 500.0));
+        Snarl::<Pins>::zoom_reset_at("snarl", ui_id, &cx);
+        let (reset, bounds, _) = settle(&mut snarl);
+        assert!((reset.width() - initial.width()).abs() < 0.1);
+        assert!((reset.center() - bounds.center()).length() < 0.1);
     }
-}
 
-/// Iterator over mutable references to nodes.
-#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-pub struct NodeIdsDataIterMut<'a, T> {
-    nodes: slab::IterMut<'a, Node<T>>,
-}
+    #[test]
+    fn fit_to_selection_frames_selected_nodes() {
+        use egui::Rect;
 
-impl<'a, T> Iterator for NodeIdsDataIterMut<'a, T> {
-    type Item = (NodeId, &'a mut Node<T>);
+        let cx = egui::Context::default();
+        let style = SnarlStyle::new();
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        self.nodes.size_hint()
-    }
+        let mut snarl = Snarl::new();
+        snarl.insert_node(pos2(0.0, 0.0), Pins(0, 0));
+        let far = snarl.insert_node(pos2(2000.0, 0.0), Pins(0, 0));
 
-    fn next(&mut self) -> Option<(NodeId, &'a mut Node<T>)> {
-        let (id, node) = self.nodes.next()?;
-        Some((NodeId(id), node))
-    }
+        let mut viewer = TestViewer {
+            select: Some(vec![far]),
+            ..TestViewer::default()
+        };
 
-    fn nth(&mut self, n: usize) -> Option<(NodeId, &'a mut Node<T>)> {
-        let (id, node) = self.nodes.nth(n)?;
-        Some((NodeId(id), node))
+        // Runs frames until zoom animation settles, returning last visible area.
+        let mut settle = |snarl: &mut Snarl<Pins>| {
+            let mut ui_id = egui::Id::NULL;
+            let mut viewport = Rect::NOTHING;
+            for _ in 0..30 {
+                let frame = run_frame(&cx, snarl, &mut viewer, &style, Vec::new());
+                if let Some(changed) = frame.response.viewport_changed {
+                    viewport = changed;
+                }
+                ui_id = frame.ui_id;
+            }
+            (viewport, ui_id)
+        };
+
+        let (initial, ui_id) = settle(&mut snarl);
+        assert!(!initial.contains(pos2(2000.0, 0.0)));
+
+        Snarl::<Pins>::fit_to_selection_at("snarl", ui_id, &cx, 16.0);
+        let (framed, _) = settle(&mut snarl);
+        assert!(framed.contains(pos2(2000.0, 0.0)));
+        assert!(!framed.contains(pos2(0.0, 0.0)));
     }
-}
 
-/// Node and its output pin.
-#[derive(Clone, Debug)]
-pub struct OutPin {
-    /// Output pin identifier.
-    pub id: OutPinId,
+    #[test]
+    fn viewport_is_precise_far_from_origin() {
+        use crate::ui::Viewport;
+        use egui::{Rect, Vec2};
 
-    /// List of input pins connected to this output pin.
-    pub remotes: Vec<InPinId>,
-}
+        let pos = pos2(1_000_000.25, 1_000_000.25);
+        let scale = 1.3f32;
 
-/// Node and its output pin.
-#[derive(Clone, Debug)]
-pub struct InPin {
-    /// Input pin identifier.
-    pub id: InPinId,
+        // Node is 10.3 points right and below of the viewport's center.
+        let offset = f64::from(pos.x) * f64::from(scale) - 10.3;
 
-    /// List of output pins connected to this input pin.
-    pub remotes: Vec<OutPinId>,
-}
+        let viewport = Viewport::new(
+            Rect::from_min_size(Pos2::ZERO, egui::vec2(800.0, 600.0)),
+            scale,
+            [offset; 2],
+        );
+        assert_eq!(viewport.offset, Vec2::splat(offset as f32));
 
-impl OutPin {
-    fn new<T>(snarl: &Snarl<T>, pin: OutPinId) -> Self {
-        OutPin {
-            id: pin,
-            remotes: snarl.wires.wired_inputs(pin).collect(),
-        }
-    }
-}
+        let expected = 410.3;
+        let naive = pos.x * scale - offset as f32 + 400.0;
+        let precise = viewport.graph_pos_to_screen(pos);
 
-impl InPin {
-    fn new<T>(snarl: &Snarl<T>, pin: InPinId) -> Self {
-        InPin {
-            id: pin,
-            remotes: snarl.wires.wired_outputs(pin).collect(),
-        }
+        assert!((naive - expected).abs() > 0.01);
+        assert!((precise.x - expected).abs() < 0.001);
+        assert!((precise.y - 310.3).abs() < 0.001);
+
+        let [x, y] = viewport.screen_pos_to_graph_f64(precise);
+        assert!((x - f64::from(pos.x)).abs() < 0.001);
+        assert!((y - f64::from(pos.y)).abs() < 0.001);
     }
 }
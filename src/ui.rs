@@ -1,37 +1,53 @@
 //! This module provides functionality for showing [`Snarl`] graph in [`Ui`].
 
-use std::{collections::HashMap, hash::Hash};
+use std::{
+    collections::{HashMap, HashSet},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
 
 use egui::{
     collapsing_header::paint_default_icon,
-    epaint::{CornerRadiusF32, Shadow},
+    emath::{Rot2, TSTransform},
+    epaint::{CornerRadiusF32, Fonts, Shadow},
     pos2,
     response::Flags,
-    vec2, Align, Color32, CornerRadius, Frame, Id, Layout, Margin, Modifiers, PointerButton, Pos2,
-    Rect, Sense, Shape, Stroke, StrokeKind, Style, Ui, UiBuilder, Vec2,
+    vec2, Align, Color32, Context, CornerRadius, CursorIcon, FocusDirection, Frame, Id,
+    InnerResponse, Key, KeyboardShortcut, Layout, Margin, Modifiers, Order, Painter, PointerButton,
+    Pos2, Rect, Rgba, ScrollArea, Sense, Shape, Stroke, StrokeKind, Style, TextStyle, TextWrapMode,
+    Ui, UiBuilder, Vec2, WidgetText,
 };
 
-use crate::{InPin, InPinId, Node, NodeId, OutPin, OutPinId, Snarl};
+use crate::{
+    InPin, InPinId, Node, NodeId, NodeMeasurement, OutPin, OutPinId, Snarl, SnarlMeasurement, Wire,
+};
 
 mod background_pattern;
 mod pin;
+mod route;
 mod state;
 mod viewer;
 mod wire;
 mod zoom;
 
 use self::{
-    pin::AnyPin,
-    state::{NewWires, NodeState, SnarlState},
-    wire::{draw_wire, hit_wire, pick_wire_style},
+    route::WireRoutes,
+    state::{BodyCache, NewWires, NodeState, PinOffsets, SnarlState, ZoomRequest, ZOOM_STEP},
+    wire::{
+        draw_wire, draw_wire_route, hit_wire, hit_wire_route, pick_wire_style, tessellate_mesh,
+        tessellate_wires, transpose, wire_geometry_key, WireCache, WireGeometries, WireGeometry,
+    },
     zoom::Zoom,
 };
 
+#[cfg(test)]
+pub(crate) use self::{route::route_wire, wire::WireCache as TestWireCache};
+
 pub use self::{
     background_pattern::{BackgroundPattern, Grid, Viewport},
-    pin::{AnyPins, PinInfo, PinShape},
+    pin::{AnyPin, AnyPins, PinInfo, PinShape},
     viewer::SnarlViewer,
-    wire::{WireLayer, WireStyle},
+    wire::{WireLayer, WirePath, WireStyle},
 };
 
 /// Controls how header, pins, body and footer are laid out in the node.
@@ -123,7 +139,82 @@ pub enum PinPlacement {
     },
 }
 
+/// Controls where content of pins is shown relative to the node frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+pub enum PinLabelPlacement {
+    /// Content is shown inside the node next to the pin.
+    #[default]
+    Inside,
+
+    /// Content is shown outside the node frame beyond the pin.
+    ///
+    /// It does not contribute to node width and is not clipped by the node.
+    /// Applies to pins on the left and right edges only.
+    Outside,
+}
+
+/// Controls which edges of the node pins are attached to.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+pub enum PinSides {
+    /// Input pins are stacked on the left edge and output pins on the right edge.
+    /// Wires leave and enter pins horizontally.
+    #[default]
+    LeftRight,
+
+    /// Input pins are lined up on the top edge and output pins on the bottom edge.
+    /// Wires leave and enter pins vertically.
+    ///
+    /// Nodes are laid out as [`NodeLayout::Sandwich`] regardless of [`SnarlStyle::node_layout`].
+    /// [`PinPlacement::Inside`] is treated as [`PinPlacement::Edge`].
+    TopBottom,
+}
+
+/// Controls when handle is drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+pub enum HandleVisibility {
+    /// Handle is always drawn.
+    Always,
+
+    /// Handle is drawn while pointer is over the node or handle is dragged.
+    #[default]
+    OnHover,
+
+    /// Handle is never drawn.
+    Never,
+}
+
+/// Controls which nodes are selected by selection rect.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
+pub enum BoxSelectMode {
+    /// Nodes intersecting with selection rect are selected.
+    #[default]
+    Intersect,
+
+    /// Only nodes fully contained in selection rect are selected.
+    Contain,
+
+    /// Mode depends on drag direction.
+    ///
+    /// Dragging left to right selects only fully contained nodes,
+    /// dragging right to left selects nodes intersecting with the rect.
+    /// Rect of the latter is drawn with dashed stroke.
+    Directional,
+}
+
 /// Style for rendering Snarl.
+///
+/// Style holds only plain data, so it can be serialized, probed and shared between threads.
+/// Custom rendering that needs code, like background or node frames,
+/// is provided by [`SnarlViewer`] methods, e.g. [`SnarlViewer::draw_background`]
+/// and [`SnarlViewer::node_frame`], instead of closures stored in the style.
 #[derive(Debug, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[cfg_attr(feature = "egui-probe", derive(egui_probe::EguiProbe))]
@@ -216,6 +307,22 @@ pub struct SnarlStyle {
     )]
     pub pin_placement: Option<PinPlacement>,
 
+    /// Placement of content shown with [`SnarlViewer::show_input`] and [`SnarlViewer::show_output`].
+    /// Defaults to [`PinLabelPlacement::Inside`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub pin_label_placement: Option<PinLabelPlacement>,
+
+    /// Edges of the node pins are attached to.
+    /// Defaults to [`PinSides::LeftRight`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub pin_sides: Option<PinSides>,
+
     /// Width of wires.
     #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
     #[cfg_attr(
@@ -287,6 +394,47 @@ pub struct SnarlStyle {
     )]
     pub bg_pattern_stroke: Option<Stroke>,
 
+    /// Opacity of background pattern.
+    /// Multiplied into alpha of [`SnarlStyle::bg_pattern_stroke`].
+    /// Defaults to `1.0`.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..=1.0))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub bg_pattern_opacity: Option<f32>,
+
+    /// Width of the margin along edges of the viewport
+    /// where background pattern fades out.
+    /// Defaults to `0.0` - no fading.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub bg_pattern_edge_fade: Option<f32>,
+
+    /// Whether lines of [`BackgroundPattern::Grid`] are aligned to physical pixels
+    /// to keep them sharp on displays with fractional scale factor.
+    /// Defaults to `true`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub crisp_grid: Option<bool>,
+
+    /// Node the background pattern is aligned to.
+    ///
+    /// Origin of the pattern follows position of this node as it moves.
+    /// If node does not exist, pattern is aligned to graph's origin.
+    /// Defaults to `None` - pattern is aligned to graph's origin.
+    #[cfg_attr(feature = "egui-probe", egui_probe(skip))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub pattern_anchor: Option<NodeId>,
+
     /// Minimum viewport scale that can be set.
     #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..=1.0))]
     #[cfg_attr(
@@ -335,8 +483,19 @@ pub struct SnarlStyle {
     /// Flag to control how rect selection works.
     /// If set to true, only nodes fully contained in selection rect will be selected.
     /// If set to false, nodes intersecting with selection rect will be selected.
+    /// Ignored if [`SnarlStyle::box_select_mode`] is set.
     pub select_rect_contained: Option<bool>,
 
+    /// Controls which nodes are selected by selection rect.
+    ///
+    /// Defaults to [`BoxSelectMode::Contain`] if [`SnarlStyle::select_rect_contained`] is set to true,
+    /// and to [`BoxSelectMode::Intersect`] otherwise.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub box_select_mode: Option<BoxSelectMode>,
+
     /// Style for node selection.
     #[cfg_attr(
         feature = "serde",
@@ -344,138 +503,967 @@ pub struct SnarlStyle {
     )]
     pub select_style: Option<SelectionStyle>,
 
-    #[doc(hidden)]
-    #[cfg_attr(feature = "egui-probe", egui_probe(skip))]
-    #[cfg_attr(feature = "serde", serde(skip_serializing, default))]
-    /// Do not access other than with .., here to emulate `#[non_exhaustive(pub)]`
-    pub _non_exhaustive: (),
-}
-
-impl SnarlStyle {
-    fn get_node_layout(&self) -> NodeLayout {
-        self.node_layout.unwrap_or(NodeLayout::Basic)
-    }
+    /// Whether wires may connect node's output to an input of the same node.
+    /// Any output and any input of the same node form a self-loop,
+    /// regardless of pin indices.
+    ///
+    /// When disabled, such wires are rejected when dropped in the UI
+    /// and by [`Snarl::try_connect`].
+    /// Defaults to `true`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub allow_self_loops: Option<bool>,
 
-    fn get_pin_size(&self, scale: f32, style: &Style) -> f32 {
-        self.pin_size
-            .zoomed(scale)
-            .unwrap_or(style.spacing.interact_size.y * 0.6)
-    }
+    /// Whether wires that would create a cycle in the graph are rejected.
+    ///
+    /// When enabled, such wires are rejected when dropped in the UI
+    /// and by [`Snarl::try_connect`].
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub prevent_cycles: Option<bool>,
 
-    fn get_pin_fill(&self, style: &Style) -> Color32 {
-        self.pin_fill
-            .unwrap_or(style.visuals.widgets.active.bg_fill)
-    }
+    /// Rate at which wire activity decays, per second.
+    ///
+    /// Wires flashed with [`Snarl::flash_wire`] are drawn brighter and thicker
+    /// until their activity decays to zero.
+    /// Defaults to `2.0`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_activity_decay: Option<f32>,
 
-    fn get_pin_stroke(&self, scale: f32, style: &Style) -> Stroke {
-        self.pin_stroke.zoomed(scale).unwrap_or_else(|| {
-            Stroke::new(
-                style.visuals.widgets.active.bg_stroke.width,
-                style.visuals.widgets.active.bg_stroke.color,
-            )
-        })
-    }
+    /// Width of the area near viewport edges where dragging pans the view.
+    ///
+    /// Applies to dragging nodes, wires and rect selection.
+    /// Set to `0.0` to disable edge panning.
+    /// Defaults to `20.0`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub edge_pan_margin: Option<f32>,
 
-    fn get_pin_shape(&self) -> PinShape {
-        self.pin_shape.unwrap_or(PinShape::Circle)
-    }
+    /// Speed of edge panning in points per second.
+    ///
+    /// The speed is reached when pointer is at the very edge of the viewport,
+    /// and is proportionally smaller further from it.
+    /// Defaults to `500.0`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub edge_pan_speed: Option<f32>,
 
-    fn get_pin_placement(&self) -> PinPlacement {
-        self.pin_placement.unwrap_or_default()
-    }
+    /// Whether dragged nodes snap to edges and centers of other nodes.
+    ///
+    /// Alignment guide is drawn while node is snapped.
+    /// Holding Alt temporarily disables snapping.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub smart_guides: Option<bool>,
 
-    fn get_wire_width(&self, scale: f32, style: &Style) -> f32 {
-        self.wire_width
-            .zoomed(scale)
-            .unwrap_or_else(|| self.get_pin_size(scale, style) * 0.1)
-    }
+    /// Stroke for smart guides lines.
+    ///
+    /// Defaults to selection stroke from `egui::Style`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub guide_stroke: Option<Stroke>,
 
-    fn get_wire_frame_size(&self, scale: f32, style: &Style) -> f32 {
-        self.wire_frame_size
-            .zoomed(scale)
-            .unwrap_or_else(|| self.get_pin_size(scale, style) * 3.0)
-    }
+    /// Fixed height of the node's header.
+    ///
+    /// Header content is vertically centered within this height.
+    /// If `None`, header height fits its content.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub header_height: Option<f32>,
 
-    fn get_downscale_wire_frame(&self) -> bool {
-        self.downscale_wire_frame.unwrap_or(true)
-    }
+    /// Maximum number of nodes drawn per frame.
+    ///
+    /// This is a last-resort performance valve for huge graphs.
+    /// When there are more nodes, selected, hovered and connecting nodes are always drawn,
+    /// and the rest are drawn in turns over next frames, visible nodes first.
+    /// Next frames are requested until every node was drawn since the graph or the view changed.
+    /// Wires of nodes that are not drawn are not drawn either.
+    /// Defaults to `None`, all nodes are drawn.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub max_nodes_per_frame: Option<usize>,
 
-    fn get_upscale_wire_frame(&self) -> bool {
-        self.upscale_wire_frame.unwrap_or(false)
-    }
+    /// Radius of the pin's clickable area.
+    ///
+    /// Allows keeping pins visually small while easy to grab on touch devices.
+    /// Where hit areas of adjacent pins overlap, the pin with nearest center is picked.
+    /// Defaults to half of the pin size.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub pin_hit_radius: Option<f32>,
 
-    fn get_wire_style(&self, scale: f32) -> WireStyle {
-        self.wire_style.zoomed(scale).unwrap_or(WireStyle::Bezier5)
-    }
+    /// Whether dropping a new wire on node's body connects it to the nearest compatible pin.
+    ///
+    /// Pins that would be rejected by connection rules are skipped.
+    /// If no pin is compatible, wire is drawn with error color.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub connect_to_node_body: Option<bool>,
 
-    fn get_wire_layer(&self) -> WireLayer {
-        self.wire_layer.unwrap_or(WireLayer::BehindNodes)
-    }
+    /// Whether hovering a node highlights its direct neighbors and wires, dimming the rest.
+    ///
+    /// Dimming is combined with [`SnarlViewer::node_opacity`].
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub neighborhood_highlight: Option<bool>,
 
-    fn get_header_drag_space(&self, scale: f32, style: &Style) -> Vec2 {
-        self.header_drag_space
-            .zoomed(scale)
-            .unwrap_or_else(|| vec2(style.spacing.icon_width, style.spacing.icon_width))
-    }
+    /// Size of anti-aliasing feathering of wires in physical pixels.
+    ///
+    /// When set, wires are tessellated by snarl with this feathering
+    /// instead of egui's global [`TessellationOptions`](egui::epaint::TessellationOptions),
+    /// which still apply to everything else. `0.0` disables anti-aliasing of wires.
+    /// Wires thinner than one point are drawn one point wide with reduced opacity
+    /// regardless of this setting, so they do not vanish when zoomed out.
+    /// Defaults to egui's global tessellation options.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_feathering: Option<f32>,
 
-    fn get_collapsible(&self) -> bool {
-        self.collapsible.unwrap_or(true)
-    }
+    /// Gap between pin center and the end of its wires in screen space.
+    ///
+    /// Wires start and end this far from pin centers along the direction they leave pins,
+    /// keeping pin shapes visible at any zoom.
+    /// Defaults to `0.0`.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_pin_gap: Option<f32>,
 
-    fn get_bg_frame(&self, style: &Style) -> Frame {
-        self.bg_frame.unwrap_or_else(|| Frame::canvas(style))
-    }
+    /// Whether nodes can be resized by dragging their bottom-right corner.
+    ///
+    /// Resized node keeps its size in [`Node::size`](crate::Node::size)
+    /// and its body becomes scrollable if content does not fit.
+    /// Double-click the corner to size node to fit its content again.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub resizable_nodes: Option<bool>,
 
-    fn get_bg_pattern_stroke(&self, scale: f32, style: &Style) -> Stroke {
-        self.bg_pattern_stroke
-            .zoomed(scale)
-            .unwrap_or(style.visuals.widgets.noninteractive.bg_stroke)
-    }
+    /// Controls when resize handle of resizable nodes is drawn.
+    ///
+    /// Handle can be dragged even when it is not drawn.
+    /// Defaults to [`HandleVisibility::OnHover`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub resize_handle_visibility: Option<HandleVisibility>,
 
-    fn get_min_scale(&self) -> f32 {
-        self.min_scale.unwrap_or(0.2)
-    }
+    /// Minimal size of resized nodes in graph space.
+    ///
+    /// Defaults to [`Spacing::interact_size`](egui::style::Spacing::interact_size).
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub node_min_size: Option<Vec2>,
 
-    fn get_max_scale(&self) -> f32 {
-        self.max_scale.unwrap_or(5.0)
-    }
+    /// Maximal size of resized nodes in graph space.
+    ///
+    /// Defaults to unlimited.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub node_max_size: Option<Vec2>,
 
-    fn get_scale_velocity(&self) -> f32 {
-        self.scale_velocity.unwrap_or(1.0)
-    }
+    /// Whether pins can be reordered by dragging them along their node's pins with `Alt` held.
+    ///
+    /// Snarl shows where pin will be placed and calls
+    /// [`SnarlViewer::reorder_input`] or [`SnarlViewer::reorder_output`] on drop.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub pin_reorder: Option<bool>,
 
-    fn get_node_frame(&self, scale: f32, style: &Style) -> Frame {
-        self.node_frame
+    /// Whether dropped nodes snap to the grid.
+    ///
+    /// Dragged node moves freely and [`SnarlStyle::snap_indicator_stroke`] marks where it will land.
+    /// Size of resized node snaps to multiples of grid spacing when its resize handle is released.
+    /// Grid of [`SnarlStyle::bg_pattern`] is used if it is [`BackgroundPattern::Grid`],
+    /// otherwise default [`Grid`].
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub snap_to_grid: Option<bool>,
+
+    /// Stroke of the mark drawn at grid node where dragged node will be snapped.
+    ///
+    /// Defaults to faded `ui.visuals().selection.stroke`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub snap_indicator_stroke: Option<Stroke>,
+
+    /// Distance from pointer within which new wire snaps to the nearest compatible pin.
+    ///
+    /// Snapped wire is drawn to the pin and connects to it when released.
+    /// Only pins that every new wire can connect to under style rules are considered.
+    /// Defaults to `0.0` - wire connects only when released over the pin.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub connect_snap_radius: Option<f32>,
+
+    /// Whether end of new wire eases toward the pin it snaps to
+    /// within [`SnarlStyle::connect_snap_radius`] instead of jumping to it.
+    /// End eases back to the pointer when it moves away from the pin.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub drag_wire_magnet: Option<bool>,
+
+    /// Stroke of selection rect around primary selected node.
+    ///
+    /// Primary node is the most recently selected one.
+    /// Defaults to stroke of [`SnarlStyle::select_style`] with doubled width.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub primary_selection_stroke: Option<Stroke>,
+
+    /// Stroke drawn along inner edge of node frame while pointer is over the node.
+    ///
+    /// It is drawn over the frame, so it does not change layout of the node.
+    /// Applied to selected nodes as well, with selection drawn around it.
+    /// Defaults to `ui.visuals().widgets.hovered.bg_stroke`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub node_hover_stroke: Option<Stroke>,
+
+    /// If set to true, clicking a node that is already selected
+    /// makes it primary without deselecting other nodes.
+    /// If set to false, such click leaves only the clicked node selected.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub click_keeps_selection: Option<bool>,
+
+    /// Distance kept between nodes and wires drawn with [`WireStyle::Avoid`] style.
+    ///
+    /// Defaults to pin size.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_avoidance_margin: Option<f32>,
+
+    /// If set to true, node created through graph menu or dropped wire menu
+    /// receives keyboard focus on its first focusable widget.
+    /// Focus is not moved if another widget has it.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub focus_new_node: Option<bool>,
+
+    /// If set to true, meshes of wires are cached between frames
+    /// and tessellated again only when endpoints, style or viewport of the wire change.
+    /// Changing color of the wire reuses its cached mesh.
+    ///
+    /// This saves time drawing wires of large graphs at the cost of memory
+    /// used to keep meshes of every visible wire.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_cache: Option<bool>,
+
+    /// If set to true, scrolling over the graph pans it
+    /// and scroll is not passed to parent [`ScrollArea`].
+    /// If set to false, scroll is passed to parent [`ScrollArea`],
+    /// so graph can be embedded into scrolling layout.
+    ///
+    /// Zooming with modifier and scroll is handled by the graph in both cases.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub capture_scroll: Option<bool>,
+
+    /// If set to true, scroll over nodes is given to widgets in the nodes first,
+    /// and graph is panned only by scroll they did not consume.
+    /// So scroll areas and multiline text edits in nodes can be scrolled.
+    /// If set to false, scroll over nodes pans the graph.
+    ///
+    /// Has effect only when [`SnarlStyle::capture_scroll`] is enabled.
+    /// Defaults to `true`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub nodes_capture_scroll: Option<bool>,
+
+    /// Stroke of freeform lasso selection.
+    /// Lasso selection is started by dragging over empty space with `Alt` held
+    /// and selects nodes with centers inside of the lasso.
+    /// Defaults to [`SnarlStyle::select_stoke`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub lasso_stroke: Option<Stroke>,
+
+    /// Duration in seconds of animation that scales and fades in a node
+    /// when it is laid out for the first time, e.g. after being added to the graph.
+    /// Animation only affects painting, node interacts with its full size immediately.
+    /// Defaults to `None` - nodes appear without animation.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub node_spawn_anim: Option<f32>,
+
+    /// Whether pins are highlighted while new wire is dragged
+    /// depending on whether they can accept it.
+    /// Compatible pins are enlarged and incompatible pins are dimmed.
+    /// Only pins drawn on screen are checked.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub highlight_compatible_pins: Option<bool>,
+
+    /// Whether dragging connected input pin sharply away from its node disconnects all its wires.
+    /// Slower drag from the pin starts new wire as usual.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub flick_disconnect: Option<bool>,
+
+    /// Whether context menu of a node with wires has "Disconnect all" entry.
+    /// The entry calls [`SnarlViewer::disconnect_node`].
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub node_menu_disconnect_all: Option<bool>,
+
+    /// Scale below which wire labels from [`SnarlViewer::wire_label`] are hidden,
+    /// so they do not clutter zoomed out graph.
+    /// Defaults to `0.5`.
+    #[cfg_attr(feature = "egui-probe", egui_probe(range = 0.0..))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_label_min_scale: Option<f32>,
+
+    /// Keyboard shortcut that switches default style of wires
+    /// while pointer is over the graph.
+    /// Styles are cycled in order: [`WireStyle::Bezier5`], [`WireStyle::Bezier3`],
+    /// [`WireStyle::AxisAligned`] and [`WireStyle::Avoid`].
+    ///
+    /// Chosen style overrides [`SnarlStyle::wire_style`] and is persisted with the graph's UI state.
+    /// Styles returned in [`PinInfo`] are still used for their wires.
+    /// Defaults to `None` - no shortcut.
+    #[cfg_attr(feature = "egui-probe", egui_probe(skip))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_style_shortcut: Option<KeyboardShortcut>,
+
+    /// Whether to draw debug overlay in the corner of the graph.
+    ///
+    /// Overlay shows numbers of visible and total nodes, drawn and total wires,
+    /// and time spent on laying out and painting nodes and wires in the frame.
+    /// Timings are not available on the web.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub debug_overlay: Option<bool>,
+
+    /// Whether `Ctrl+0`, `Ctrl+=` and `Ctrl+-` reset, zoom in and zoom out the view
+    /// while pointer is over the graph.
+    /// Same as [`Snarl::zoom_reset`], [`Snarl::zoom_in_step`] and [`Snarl::zoom_out_step`].
+    ///
+    /// egui zooms the whole UI with the same shortcuts unless
+    /// [`Options::zoom_with_keyboard`](egui::Options::zoom_with_keyboard) is disabled.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub zoom_shortcuts: Option<bool>,
+
+    /// Whether pressing `Delete` or `Backspace` removes selected nodes and their wires.
+    ///
+    /// Keys are handled while pointer is over the graph
+    /// and no widget, such as text edit in a node, has keyboard focus.
+    /// Nodes are removed with [`SnarlViewer::remove_node`].
+    /// Wire with open context menu is removed with [`SnarlViewer::disconnect`].
+    /// Defaults to `true`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub keyboard_delete: Option<bool>,
+
+    /// Keyboard shortcut that frames selected nodes, as [`Snarl::fit_to_selection`] does.
+    ///
+    /// Shortcut is handled while pointer is over the graph
+    /// and no widget has keyboard focus.
+    /// E.g. `KeyboardShortcut::new(Modifiers::NONE, Key::F)`.
+    /// Defaults to `None` - no shortcut.
+    #[cfg_attr(feature = "egui-probe", egui_probe(skip))]
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub fit_selection_shortcut: Option<KeyboardShortcut>,
+
+    /// Whether framing selection with no nodes selected frames the whole graph.
+    ///
+    /// When disabled, view is left unchanged.
+    /// Defaults to `true`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub fit_all_without_selection: Option<bool>,
+
+    /// Whether bodies of nodes are cached with keys from [`SnarlViewer::body_cache_key`].
+    ///
+    /// Disable to render all bodies every frame, e.g. to rule out stale caches.
+    /// Defaults to `true`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub body_cache: Option<bool>,
+
+    /// Stroke of the wire that is dragged before it is connected.
+    ///
+    /// Wire still turns to error color over targets that reject it.
+    /// Defaults to `None` - [`SnarlStyle::wire_width`] and color of the pin it is dragged from.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub drag_wire_stroke: Option<Stroke>,
+
+    /// Style of the wire that is dragged before it is connected.
+    ///
+    /// Defaults to `None` - same style as connected wires of the pin it is dragged from.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub drag_wire_style: Option<WireStyle>,
+
+    /// Outline drawn around selected wires.
+    ///
+    /// Wire is selected while its context menu is open
+    /// or when both of its nodes are selected.
+    /// Wire keeps its own color, outline is drawn under it.
+    /// Selected wires are drawn above others regardless of [`SnarlViewer::wire_z`].
+    /// Defaults to stroke of [`SnarlStyle::select_style`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_selected_stroke: Option<Stroke>,
+
+    /// Outline drawn around hovered wire.
+    ///
+    /// Wire keeps its own color, outline is drawn under it.
+    /// Defaults to faded stroke of [`SnarlStyle::select_style`].
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub wire_hovered_stroke: Option<Stroke>,
+
+    /// Whether nodes and wires forming cycles are tinted with warning color.
+    ///
+    /// Cycles are found with [`Snarl::find_cycles`] and searched again only when wires change.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub highlight_cycles: Option<bool>,
+
+    /// Whether hovering an output pin previews wire to the nearest input it can connect to.
+    ///
+    /// Faint wire is drawn only when no wire is dragged
+    /// and the nearest compatible input is not connected to the pin yet.
+    /// Defaults to `false`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(skip_serializing_if = "Option::is_none", default)
+    )]
+    pub preview_connection_on_hover: Option<bool>,
+
+    #[doc(hidden)]
+    #[cfg_attr(feature = "egui-probe", egui_probe(skip))]
+    #[cfg_attr(feature = "serde", serde(skip_serializing, default))]
+    /// Do not access other than with .., here to emulate `#[non_exhaustive(pub)]`
+    pub _non_exhaustive: (),
+}
+
+impl SnarlStyle {
+    fn get_node_layout(&self) -> NodeLayout {
+        self.node_layout.unwrap_or(NodeLayout::Basic)
+    }
+
+    fn get_pin_size(&self, scale: f32, style: &Style) -> f32 {
+        self.pin_size
+            .zoomed(scale)
+            .unwrap_or(style.spacing.interact_size.y * 0.6)
+    }
+
+    fn get_pin_fill(&self, style: &Style) -> Color32 {
+        self.pin_fill
+            .unwrap_or(style.visuals.widgets.active.bg_fill)
+    }
+
+    fn get_pin_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.pin_stroke.zoomed(scale).unwrap_or_else(|| {
+            Stroke::new(
+                style.visuals.widgets.active.bg_stroke.width,
+                style.visuals.widgets.active.bg_stroke.color,
+            )
+        })
+    }
+
+    fn get_pin_shape(&self) -> PinShape {
+        self.pin_shape.unwrap_or(PinShape::Circle)
+    }
+
+    fn get_pin_placement(&self) -> PinPlacement {
+        self.pin_placement.unwrap_or_default()
+    }
+
+    fn get_pin_label_placement(&self) -> PinLabelPlacement {
+        self.pin_label_placement.unwrap_or_default()
+    }
+
+    fn get_pin_sides(&self) -> PinSides {
+        self.pin_sides.unwrap_or_default()
+    }
+
+    fn get_wire_width(&self, scale: f32, style: &Style) -> f32 {
+        self.wire_width
+            .zoomed(scale)
+            .unwrap_or_else(|| self.get_pin_size(scale, style) * 0.1)
+    }
+
+    fn get_wire_frame_size(&self, scale: f32, style: &Style) -> f32 {
+        self.wire_frame_size
+            .zoomed(scale)
+            .unwrap_or_else(|| self.get_pin_size(scale, style) * 3.0)
+    }
+
+    fn get_downscale_wire_frame(&self) -> bool {
+        self.downscale_wire_frame.unwrap_or(true)
+    }
+
+    fn get_upscale_wire_frame(&self) -> bool {
+        self.upscale_wire_frame.unwrap_or(false)
+    }
+
+    fn get_wire_style(&self, scale: f32) -> WireStyle {
+        self.wire_style.zoomed(scale).unwrap_or(WireStyle::Bezier5)
+    }
+
+    fn get_wire_layer(&self) -> WireLayer {
+        self.wire_layer.unwrap_or(WireLayer::BehindNodes)
+    }
+
+    fn get_header_drag_space(&self, scale: f32, style: &Style) -> Vec2 {
+        self.header_drag_space
+            .zoomed(scale)
+            .unwrap_or_else(|| vec2(style.spacing.icon_width, style.spacing.icon_width))
+    }
+
+    fn get_collapsible(&self) -> bool {
+        self.collapsible.unwrap_or(true)
+    }
+
+    fn get_bg_frame(&self, style: &Style) -> Frame {
+        self.bg_frame.unwrap_or_else(|| Frame::canvas(style))
+    }
+
+    fn get_bg_pattern_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        let stroke = self
+            .bg_pattern_stroke
+            .zoomed(scale)
+            .unwrap_or(style.visuals.widgets.noninteractive.bg_stroke);
+
+        Stroke::new(
+            stroke.width,
+            stroke.color.gamma_multiply(self.get_bg_pattern_opacity()),
+        )
+    }
+
+    fn get_bg_pattern_opacity(&self) -> f32 {
+        self.bg_pattern_opacity.unwrap_or(1.0).clamp(0.0, 1.0)
+    }
+
+    fn get_bg_pattern_edge_fade(&self) -> f32 {
+        self.bg_pattern_edge_fade.unwrap_or(0.0).max(0.0)
+    }
+
+    fn get_crisp_grid(&self) -> bool {
+        self.crisp_grid.unwrap_or(true)
+    }
+
+    fn get_pattern_origin<T>(&self, snarl: &Snarl<T>) -> Pos2 {
+        self.pattern_anchor
+            .and_then(|node| snarl.nodes.get(node.0))
+            .map_or(Pos2::ZERO, |node| node.pos)
+    }
+
+    fn get_min_scale(&self) -> f32 {
+        self.min_scale.unwrap_or(0.2)
+    }
+
+    fn get_max_scale(&self) -> f32 {
+        self.max_scale.unwrap_or(5.0)
+    }
+
+    fn get_scale_velocity(&self) -> f32 {
+        self.scale_velocity.unwrap_or(1.0)
+    }
+
+    fn get_node_frame(&self, scale: f32, style: &Style) -> Frame {
+        self.node_frame
             .zoomed(scale)
             .unwrap_or_else(|| Frame::window(style))
     }
 
-    fn get_header_frame(&self, scale: f32, style: &Style) -> Frame {
-        self.header_frame
-            .zoomed(scale)
-            .unwrap_or_else(|| self.get_node_frame(scale, style).shadow(Shadow::NONE))
+    fn get_header_frame(&self, scale: f32, style: &Style) -> Frame {
+        self.header_frame
+            .zoomed(scale)
+            .unwrap_or_else(|| self.get_node_frame(scale, style).shadow(Shadow::NONE))
+    }
+
+    fn get_centering(&self) -> bool {
+        self.centering.unwrap_or(true)
+    }
+
+    fn get_select_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.select_stoke.zoomed(scale).unwrap_or_else(|| {
+            Stroke::new(
+                style.visuals.selection.stroke.width,
+                style.visuals.selection.stroke.color.gamma_multiply(0.5),
+            )
+        })
+    }
+
+    fn get_select_fill(&self, style: &Style) -> Color32 {
+        self.select_fill
+            .unwrap_or_else(|| style.visuals.selection.bg_fill.gamma_multiply(0.3))
+    }
+
+    fn get_select_rect_contained(&self) -> bool {
+        self.select_rect_contained.unwrap_or(false)
+    }
+
+    fn get_box_select_mode(&self) -> BoxSelectMode {
+        self.box_select_mode
+            .unwrap_or(if self.get_select_rect_contained() {
+                BoxSelectMode::Contain
+            } else {
+                BoxSelectMode::Intersect
+            })
+    }
+
+    pub(crate) fn get_allow_self_loops(&self) -> bool {
+        self.allow_self_loops.unwrap_or(true)
+    }
+
+    pub(crate) fn get_prevent_cycles(&self) -> bool {
+        self.prevent_cycles.unwrap_or(false)
+    }
+
+    fn get_wire_activity_decay(&self) -> f32 {
+        self.wire_activity_decay.unwrap_or(2.0)
+    }
+
+    fn get_edge_pan_margin(&self) -> f32 {
+        self.edge_pan_margin.unwrap_or(20.0)
+    }
+
+    fn get_edge_pan_speed(&self) -> f32 {
+        self.edge_pan_speed.unwrap_or(500.0)
+    }
+
+    fn get_header_height(&self, scale: f32) -> Option<f32> {
+        self.header_height.zoomed(scale)
+    }
+
+    fn get_pin_hit_radius(&self, scale: f32, pin_size: f32) -> f32 {
+        self.pin_hit_radius.zoomed(scale).unwrap_or(pin_size * 0.5)
+    }
+
+    fn get_connect_to_node_body(&self) -> bool {
+        self.connect_to_node_body.unwrap_or(false)
+    }
+
+    fn get_neighborhood_highlight(&self) -> bool {
+        self.neighborhood_highlight.unwrap_or(false)
+    }
+
+    fn get_wire_feathering(&self) -> Option<f32> {
+        self.wire_feathering.map(|feathering| feathering.max(0.0))
+    }
+
+    fn get_pin_reorder(&self) -> bool {
+        self.pin_reorder.unwrap_or(false)
+    }
+
+    fn get_snap_grid(&self) -> Option<Grid> {
+        if !self.snap_to_grid.unwrap_or(false) {
+            return None;
+        }
+
+        match self.bg_pattern {
+            Some(BackgroundPattern::Grid(grid)) => Some(grid),
+            _ => Some(Grid::default()),
+        }
+    }
+
+    fn get_snap_indicator_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.snap_indicator_stroke.zoomed(scale).unwrap_or_else(|| {
+            let stroke = style.visuals.selection.stroke;
+            Stroke::new(stroke.width, stroke.color.gamma_multiply(0.5))
+        })
+    }
+
+    fn get_connect_snap_radius(&self, scale: f32) -> f32 {
+        self.connect_snap_radius
+            .zoomed(scale)
+            .unwrap_or(0.0)
+            .max(0.0)
+    }
+
+    fn get_drag_wire_magnet(&self) -> bool {
+        self.drag_wire_magnet.unwrap_or(false)
+    }
+
+    fn get_primary_selection_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.primary_selection_stroke
+            .zoomed(scale)
+            .unwrap_or_else(|| {
+                let stroke = self.get_select_style(scale, style).stroke;
+                Stroke::new(stroke.width * 2.0, stroke.color)
+            })
+    }
+
+    fn get_node_hover_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.node_hover_stroke
+            .zoomed(scale)
+            .unwrap_or(style.visuals.widgets.hovered.bg_stroke)
+    }
+
+    fn get_click_keeps_selection(&self) -> bool {
+        self.click_keeps_selection.unwrap_or(false)
+    }
+
+    fn get_wire_avoidance_margin(&self, scale: f32, style: &Style) -> f32 {
+        self.wire_avoidance_margin
+            .zoomed(scale)
+            .unwrap_or_else(|| self.get_pin_size(scale, style))
+            .max(0.0)
+    }
+
+    fn get_focus_new_node(&self) -> bool {
+        self.focus_new_node.unwrap_or(false)
+    }
+
+    fn get_wire_cache(&self) -> bool {
+        self.wire_cache.unwrap_or(false)
+    }
+
+    fn get_capture_scroll(&self) -> bool {
+        self.capture_scroll.unwrap_or(false)
+    }
+
+    fn get_nodes_capture_scroll(&self) -> bool {
+        self.nodes_capture_scroll.unwrap_or(true)
+    }
+
+    fn get_lasso_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.lasso_stroke
+            .zoomed(scale)
+            .unwrap_or_else(|| self.get_select_stroke(scale, style))
+    }
+
+    fn get_node_spawn_anim(&self) -> Option<f32> {
+        self.node_spawn_anim.filter(|duration| *duration > 0.0)
+    }
+
+    fn get_highlight_compatible_pins(&self) -> bool {
+        self.highlight_compatible_pins.unwrap_or(false)
+    }
+
+    fn get_flick_disconnect(&self) -> bool {
+        self.flick_disconnect.unwrap_or(false)
+    }
+
+    fn get_node_menu_disconnect_all(&self) -> bool {
+        self.node_menu_disconnect_all.unwrap_or(false)
+    }
+
+    fn get_wire_label_min_scale(&self) -> f32 {
+        self.wire_label_min_scale.unwrap_or(0.5)
+    }
+
+    const fn get_wire_style_shortcut(&self) -> Option<KeyboardShortcut> {
+        self.wire_style_shortcut
+    }
+
+    fn get_wire_pin_gap(&self) -> f32 {
+        self.wire_pin_gap.unwrap_or(0.0).max(0.0)
+    }
+
+    fn get_debug_overlay(&self) -> bool {
+        self.debug_overlay.unwrap_or(false)
+    }
+
+    fn get_zoom_shortcuts(&self) -> bool {
+        self.zoom_shortcuts.unwrap_or(false)
+    }
+
+    fn get_keyboard_delete(&self) -> bool {
+        self.keyboard_delete.unwrap_or(true)
+    }
+
+    const fn get_fit_selection_shortcut(&self) -> Option<KeyboardShortcut> {
+        self.fit_selection_shortcut
+    }
+
+    fn get_fit_all_without_selection(&self) -> bool {
+        self.fit_all_without_selection.unwrap_or(true)
+    }
+
+    fn get_body_cache(&self) -> bool {
+        self.body_cache.unwrap_or(true)
+    }
+
+    fn get_drag_wire_stroke(&self, scale: f32) -> Option<Stroke> {
+        self.drag_wire_stroke.zoomed(scale)
+    }
+
+    fn get_drag_wire_style(&self, scale: f32) -> Option<WireStyle> {
+        self.drag_wire_style.zoomed(scale)
+    }
+
+    fn get_wire_selected_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.wire_selected_stroke
+            .zoomed(scale)
+            .unwrap_or_else(|| self.get_select_style(scale, style).stroke)
+    }
+
+    fn get_highlight_cycles(&self) -> bool {
+        self.highlight_cycles.unwrap_or(false)
+    }
+
+    fn get_preview_connection_on_hover(&self) -> bool {
+        self.preview_connection_on_hover.unwrap_or(false)
+    }
+
+    fn get_wire_hovered_stroke(&self, scale: f32, style: &Style) -> Stroke {
+        self.wire_hovered_stroke.zoomed(scale).unwrap_or_else(|| {
+            let stroke = self.get_select_style(scale, style).stroke;
+            Stroke::new(stroke.width, stroke.color.gamma_multiply(0.5))
+        })
+    }
+
+    fn get_resizable_nodes(&self) -> bool {
+        self.resizable_nodes.unwrap_or(false)
+    }
+
+    fn get_resize_handle_visibility(&self) -> HandleVisibility {
+        self.resize_handle_visibility.unwrap_or_default()
     }
 
-    fn get_centering(&self) -> bool {
-        self.centering.unwrap_or(true)
+    fn get_node_min_size(&self, style: &Style) -> Vec2 {
+        self.node_min_size
+            .unwrap_or(style.spacing.interact_size)
+            .max(Vec2::ZERO)
     }
 
-    fn get_select_stroke(&self, scale: f32, style: &Style) -> Stroke {
-        self.select_stoke.zoomed(scale).unwrap_or_else(|| {
-            Stroke::new(
-                style.visuals.selection.stroke.width,
-                style.visuals.selection.stroke.color.gamma_multiply(0.5),
-            )
-        })
+    fn get_node_max_size(&self, style: &Style) -> Vec2 {
+        self.node_max_size
+            .unwrap_or(Vec2::INFINITY)
+            .max(self.get_node_min_size(style))
     }
 
-    fn get_select_fill(&self, style: &Style) -> Color32 {
-        self.select_fill
-            .unwrap_or_else(|| style.visuals.selection.bg_fill.gamma_multiply(0.3))
+    const fn get_max_nodes_per_frame(&self) -> Option<usize> {
+        self.max_nodes_per_frame
     }
 
-    fn get_select_rect_contained(&self) -> bool {
-        self.select_rect_contained.unwrap_or(false)
+    fn get_smart_guides(&self) -> bool {
+        self.smart_guides.unwrap_or(false)
+    }
+
+    fn get_guide_stroke(&self, style: &Style) -> Stroke {
+        self.guide_stroke.unwrap_or(style.visuals.selection.stroke)
     }
 
     fn get_select_style(&self, scale: f32, style: &Style) -> SelectionStyle {
@@ -550,6 +1538,8 @@ impl SnarlStyle {
             pin_stroke: None,
             pin_shape: None,
             pin_placement: None,
+            pin_label_placement: None,
+            pin_sides: None,
             wire_width: None,
             wire_frame_size: None,
             downscale_wire_frame: None,
@@ -562,6 +1552,10 @@ impl SnarlStyle {
             bg_frame: None,
             bg_pattern: None,
             bg_pattern_stroke: None,
+            bg_pattern_opacity: None,
+            bg_pattern_edge_fade: None,
+            crisp_grid: None,
+            pattern_anchor: None,
 
             min_scale: None,
             max_scale: None,
@@ -572,7 +1566,58 @@ impl SnarlStyle {
             select_stoke: None,
             select_fill: None,
             select_rect_contained: None,
+            box_select_mode: None,
             select_style: None,
+            allow_self_loops: None,
+            prevent_cycles: None,
+            wire_activity_decay: None,
+            edge_pan_margin: None,
+            edge_pan_speed: None,
+            smart_guides: None,
+            guide_stroke: None,
+            header_height: None,
+            max_nodes_per_frame: None,
+            pin_hit_radius: None,
+            connect_to_node_body: None,
+            neighborhood_highlight: None,
+            wire_feathering: None,
+            wire_pin_gap: None,
+            resizable_nodes: None,
+            resize_handle_visibility: None,
+            node_min_size: None,
+            node_max_size: None,
+            pin_reorder: None,
+            snap_to_grid: None,
+            snap_indicator_stroke: None,
+            connect_snap_radius: None,
+            drag_wire_magnet: None,
+            primary_selection_stroke: None,
+            node_hover_stroke: None,
+            click_keeps_selection: None,
+            wire_avoidance_margin: None,
+            focus_new_node: None,
+            wire_cache: None,
+            capture_scroll: None,
+            nodes_capture_scroll: None,
+            lasso_stroke: None,
+            node_spawn_anim: None,
+            highlight_compatible_pins: None,
+            flick_disconnect: None,
+            node_menu_disconnect_all: None,
+            wire_label_min_scale: None,
+            wire_style_shortcut: None,
+            debug_overlay: None,
+            zoom_shortcuts: None,
+            keyboard_delete: None,
+            fit_selection_shortcut: None,
+            fit_all_without_selection: None,
+            body_cache: None,
+            drag_wire_stroke: None,
+            drag_wire_style: None,
+            wire_selected_stroke: None,
+            wire_hovered_stroke: None,
+            highlight_cycles: None,
+            preview_connection_on_hover: None,
 
             _non_exhaustive: (),
         }
@@ -586,6 +1631,226 @@ impl Default for SnarlStyle {
     }
 }
 
+/// Combination of states that affect how node is drawn.
+///
+/// See [`resolve_node_visuals`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct NodeVisualState {
+    /// Pointer is over the node.
+    pub hovered: bool,
+
+    /// Node is selected.
+    pub selected: bool,
+
+    /// Node is the primary selection.
+    ///
+    /// Implies `selected`.
+    pub primary: bool,
+
+    /// Node is outside of highlighted neighborhood.
+    pub dimmed: bool,
+
+    /// Node is part of a cycle highlighted with [`SnarlStyle::highlight_cycles`].
+    pub in_cycle: bool,
+}
+
+/// Visuals of the node resolved from its [`NodeVisualState`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NodeVisuals {
+    /// Stroke drawn along inner edge of the node frame.
+    ///
+    /// `None` if node is not hovered.
+    pub hover_stroke: Option<Stroke>,
+
+    /// Selection rect drawn behind the node.
+    ///
+    /// `None` if node is not selected.
+    pub selection: Option<SelectionStyle>,
+
+    /// Opacity multiplier of the node.
+    pub opacity: f32,
+
+    /// Tint drawn over the node.
+    ///
+    /// `None` if node is not in a highlighted cycle.
+    pub cycle_tint: Option<Color32>,
+}
+
+/// Resolves visuals of the node in given state.
+///
+/// Each state contributes independently,
+/// so hovered selected node gets both hover stroke and selection rect.
+/// Values are zoomed by `scale`, same as when node is drawn.
+#[must_use]
+pub fn resolve_node_visuals(
+    state: NodeVisualState,
+    scale: f32,
+    style: &SnarlStyle,
+    egui_style: &Style,
+) -> NodeVisuals {
+    let selection = (state.selected || state.primary).then(|| {
+        let mut selection = style.get_select_style(scale, egui_style);
+        if state.primary {
+            selection.stroke = style.get_primary_selection_stroke(scale, egui_style);
+        }
+        selection
+    });
+
+    let hover_stroke = state
+        .hovered
+        .then(|| style.get_node_hover_stroke(scale, egui_style));
+
+    let opacity = if state.dimmed { DIMMED_OPACITY } else { 1.0 };
+
+    let cycle_tint = state.in_cycle.then(|| {
+        egui_style
+            .visuals
+            .warn_fg_color
+            .gamma_multiply(CYCLE_TINT_OPACITY)
+    });
+
+    NodeVisuals {
+        hover_stroke,
+        selection,
+        opacity,
+        cycle_tint,
+    }
+}
+
+/// Shows content in a floating layer above the graph, so it is not clipped by the node.
+///
+/// Use it from node content, e.g. [`SnarlViewer::show_body`],
+/// for widgets that overflow the node, like custom dropdowns or inspectors.
+/// `pos` is top-left corner of the content in screen space,
+/// take it from a widget of the node to keep content next to it while the node moves.
+/// Content uses style and opacity of `ui`, so it is zoomed with the graph.
+/// Interacting with the content does not drag the node underneath.
+///
+/// Content is shown only while the node is drawn,
+/// so node with overflow content should not be cached with [`SnarlViewer::body_cache_key`].
+pub fn show_overflow<R>(
+    ui: &Ui,
+    id_salt: impl std::hash::Hash,
+    pos: Pos2,
+    add_contents: impl FnOnce(&mut Ui) -> R,
+) -> InnerResponse<R> {
+    egui::Area::new(ui.id().with(("snarl-overflow", id_salt)))
+        .order(Order::Foreground)
+        .fixed_pos(pos)
+        .show(ui.ctx(), |area_ui| {
+            area_ui.set_style(ui.style().clone());
+            area_ui.multiply_opacity(ui.opacity());
+            add_contents(area_ui)
+        })
+}
+
+/// Wire being dragged from pins.
+///
+/// See [`Snarl::current_wire_drag`].
+#[derive(Clone, Debug)]
+pub struct WireDrag {
+    pins: NewWires,
+    pos: Pos2,
+}
+
+impl WireDrag {
+    /// Returns pins the wire is dragged from.
+    #[must_use]
+    pub fn pins(&self) -> AnyPins<'_> {
+        match &self.pins {
+            NewWires::In(pins) => AnyPins::In(pins),
+            NewWires::Out(pins) => AnyPins::Out(pins),
+        }
+    }
+
+    /// Returns pointer position in graph space.
+    #[must_use]
+    pub const fn pos(&self) -> Pos2 {
+        self.pos
+    }
+}
+
+/// Response of [`Snarl::show`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SnarlResponse {
+    /// Whether nodes or wires of the graph were added, removed or moved during the frame.
+    ///
+    /// Panning, zooming, hovering and selection do not change the graph.
+    /// Changes of node values made by the viewer are not tracked.
+    pub changed: bool,
+
+    /// Position of the pointer in graph space if it is over the graph.
+    ///
+    /// Computed with viewport at the end of the frame,
+    /// after panning and zooming applied in it.
+    pub cursor_graph_pos: Option<Pos2>,
+
+    /// Visible area of the graph in graph space if it changed since the previous frame.
+    ///
+    /// Set on the first frame and whenever view is panned, zoomed or resized.
+    /// Together with [`SnarlResponse::graph_bounds`] it allows sizing external scrollbars.
+    /// Use [`Snarl::set_view_center`] to pan the view from them.
+    pub viewport_changed: Option<Rect>,
+
+    /// Bounding rect of all nodes in graph space.
+    ///
+    /// `None` if graph has no nodes.
+    pub graph_bounds: Option<Rect>,
+
+    /// Element of the graph under the pointer.
+    ///
+    /// `None` if pointer is outside of the graph.
+    pub hovered: Option<HoverTarget>,
+}
+
+/// Element of the graph under the pointer.
+///
+/// See [`SnarlResponse::hovered`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum HoverTarget {
+    /// Node, excluding its pins.
+    Node(NodeId),
+
+    /// Input pin.
+    InPin(InPinId),
+
+    /// Output pin.
+    OutPin(OutPinId),
+
+    /// Wire between output and input pins.
+    Wire(OutPinId, InPinId),
+
+    /// Empty space of the graph.
+    Background,
+}
+
+/// Point of a node used to attach external overlays.
+///
+/// See [`Snarl::node_anchor`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum NodeAnchor {
+    /// Top-left corner of the node.
+    TopLeft,
+
+    /// Top-right corner of the node.
+    TopRight,
+
+    /// Bottom-left corner of the node.
+    BottomLeft,
+
+    /// Bottom-right corner of the node.
+    BottomRight,
+
+    /// Center of the node.
+    Center,
+
+    /// Center of input pin with given index.
+    Input(usize),
+
+    /// Center of output pin with given index.
+    Output(usize),
+}
+
 struct Input {
     hover_pos: Option<Pos2>,
     interact_pos: Option<Pos2>,
@@ -593,14 +1858,52 @@ struct Input {
     // primary_pressed: bool,
     secondary_pressed: bool,
     modifiers: Modifiers,
+    stable_dt: f32,
+    escape_pressed: bool,
+    pointer_velocity: Vec2,
+}
+
+/// Cycles found in the graph with revision of wires they were found for.
+#[derive(Clone)]
+struct CycleCache {
+    revision: u64,
+    nodes: Arc<HashMap<NodeId, usize>>,
+}
+
+/// Bookkeeping of nodes drawn under [`SnarlStyle::max_nodes_per_frame`] budget.
+#[derive(Clone, Default)]
+struct DrawBudget {
+    /// Hash of the graph and the viewport.
+    key: u64,
+
+    /// Pass in which the graph or the viewport changed last time.
+    changed_pass: u64,
+
+    /// Pass in which each node was drawn last time.
+    drawn: HashMap<NodeId, u64>,
+}
+
+/// Nodes that would close a cycle if their pins were connected to new wires.
+///
+/// Collected once per frame, so that checking each pin does not walk the graph.
+#[derive(Default)]
+struct NewWiresCycles {
+    /// Nodes upstream of dragged outputs or downstream of dragged inputs.
+    /// Empty if the style allows cycles.
+    nodes: egui::ahash::HashSet<NodeId>,
+
+    /// Whether new wires are dragged from outputs.
+    from_outputs: bool,
 }
 
 struct DrawNodeResponse {
     node_moved: Option<(NodeId, Vec2)>,
+    node_dropped: Option<NodeId>,
     node_to_top: Option<NodeId>,
     drag_released: bool,
     pin_hovered: Option<AnyPin>,
     final_rect: Rect,
+    hovered: bool,
 }
 
 struct DrawPinsResponse {
@@ -617,6 +1920,9 @@ struct PinResponse {
     pos: Pos2,
     pin_color: Color32,
     wire_style: Option<WireStyle>,
+
+    /// Whether node of the pin is interactive.
+    interactive: bool,
 }
 
 impl<T> Snarl<T> {
@@ -630,24 +1936,531 @@ impl<T> Snarl<T> {
     ) where
         V: SnarlViewer<T>,
     {
-        let viewport = Viewport {
-            rect: *viewport,
-            scale: snarl_state.scale(),
-            offset: snarl_state.offset(),
+        let mut viewport = Viewport::new(*viewport, snarl_state.scale(), snarl_state.offset_f64());
+        viewport.pattern_origin = style.get_pattern_origin(self);
+
+        viewer.draw_background(
+            style.bg_pattern.as_ref(),
+            &viewport,
+            style,
+            ui.style(),
+            ui.painter(),
+            self,
+        );
+    }
+
+    /// Returns index of cycle of each node found in cycles.
+    ///
+    /// Cycles are stored in context and searched again only when wires change.
+    fn cached_cycles(&self, snarl_id: Id, cx: &Context) -> Arc<HashMap<NodeId, usize>> {
+        let cache_id = snarl_id.with("cycles");
+        let revision = self.wires.revision;
+
+        if let Some(cache) = cx.data(|d| d.get_temp::<CycleCache>(cache_id)) {
+            if cache.revision == revision {
+                return cache.nodes;
+            }
+        }
+
+        let nodes = self
+            .find_cycles()
+            .into_iter()
+            .enumerate()
+            .flat_map(|(idx, cycle)| cycle.into_iter().map(move |node| (node, idx)))
+            .collect::<HashMap<_, _>>();
+        let nodes = Arc::new(nodes);
+
+        cx.data_mut(|d| {
+            d.insert_temp(
+                cache_id,
+                CycleCache {
+                    revision,
+                    nodes: nodes.clone(),
+                },
+            );
+        });
+        nodes
+    }
+
+    /// Picks nodes to draw in this frame within `max` budget.
+    ///
+    /// Interaction targets are always picked.
+    /// Then nodes not drawn since the graph or viewport changed, visible ones first,
+    /// and then visible nodes that were drawn least recently,
+    /// so that all of them are drawn in turns.
+    /// Returned nodes keep their drawing order.
+    ///
+    /// Also returns whether some of skipped nodes were not drawn since the last change,
+    /// and next frame is needed to draw them.
+    #[allow(clippy::too_many_arguments)]
+    fn budget_draw_order(
+        &self,
+        draw_order: &[NodeId],
+        max: usize,
+        fingerprint: u64,
+        snarl_state: &SnarlState,
+        snarl_id: Id,
+        viewport: Rect,
+        hover_pos: Option<Pos2>,
+        cx: &Context,
+    ) -> (Vec<NodeId>, bool) {
+        let graph_viewport = snarl_state.screen_rect_to_graph(viewport, viewport);
+        let hover_pos = hover_pos.map(|pos| snarl_state.screen_pos_to_graph(pos, viewport));
+
+        let wired_nodes = match snarl_state.new_wires() {
+            Some(NewWires::In(pins)) => pins.iter().map(|pin| pin.node).collect(),
+            Some(NewWires::Out(pins)) => pins.iter().map(|pin| pin.node).collect(),
+            None => Vec::new(),
+        };
+
+        let pass = cx.cumulative_pass_nr();
+        let budget_id = snarl_id.with("draw-budget");
+        let mut budget = cx
+            .data(|d| d.get_temp::<DrawBudget>(budget_id))
+            .unwrap_or_default();
+
+        let mut hasher = DefaultHasher::new();
+        fingerprint.hash(&mut hasher);
+        for corner in [graph_viewport.min, graph_viewport.max] {
+            corner.x.to_bits().hash(&mut hasher);
+            corner.y.to_bits().hash(&mut hasher);
+        }
+        let key = hasher.finish();
+
+        if budget.key != key {
+            budget.key = key;
+            budget.changed_pass = pass;
+        }
+
+        let mut targets = Vec::new();
+        let mut candidates = Vec::new();
+
+        // Nodes on top were interacted with recently, so they go first.
+        for (top, (idx, &node)) in draw_order.iter().enumerate().rev().enumerate() {
+            let rect = NodeState::stored_graph_size(cx, snarl_id.with(("snarl-node", node)))
+                .map(|size| Rect::from_min_size(self.nodes[node.0].pos, size));
+
+            let hovered =
+                matches!((rect, hover_pos), (Some(rect), Some(pos)) if rect.contains(pos));
+
+            if hovered
+                || snarl_state.selected_nodes().contains(&node)
+                || wired_nodes.contains(&node)
+            {
+                targets.push(idx);
+            } else {
+                // Nodes with unknown size are considered visible.
+                let visible = !matches!(rect, Some(rect) if !rect.intersects(graph_viewport));
+                let last_drawn = budget.drawn.get(&node).copied();
+                let drawn_since_change = last_drawn.is_some_and(|p| p >= budget.changed_pass);
+
+                candidates.push(((drawn_since_change, !visible, last_drawn, top), idx));
+            }
+        }
+
+        candidates.sort_unstable_by_key(|&(key, _)| key);
+
+        let count = max.saturating_sub(targets.len());
+        let pending = candidates
+            .iter()
+            .skip(count)
+            .any(|&((drawn_since_change, ..), _)| !drawn_since_change);
+
+        let mut picked = targets;
+        picked.extend(candidates.iter().take(count).map(|&(_, idx)| idx));
+        picked.sort_unstable();
+
+        let picked = picked
+            .into_iter()
+            .map(|idx| draw_order[idx])
+            .collect::<Vec<_>>();
+
+        // Forget removed nodes.
+        budget.drawn.retain(|node, _| self.nodes.contains(node.0));
+        for &node in &picked {
+            budget.drawn.insert(node, pass);
+        }
+        cx.data_mut(|d| d.insert_temp(budget_id, budget));
+
+        (picked, pending)
+    }
+
+    /// Collects nodes that would close a cycle if their pins were connected to new wires.
+    fn new_wires_cycles(&self, new_wires: &NewWires, style: &SnarlStyle) -> NewWiresCycles {
+        let (nodes, from_outputs) = match new_wires {
+            _ if !style.get_prevent_cycles() => (egui::ahash::HashSet::default(), false),
+            // Wire from output to a node upstream of it closes a cycle.
+            NewWires::Out(out_pins) => (
+                self.reachable_nodes(out_pins.iter().map(|pin| pin.node), true),
+                true,
+            ),
+            // Wire to input from a node downstream of it closes a cycle.
+            NewWires::In(in_pins) => (
+                self.reachable_nodes(in_pins.iter().map(|pin| pin.node), false),
+                false,
+            ),
+        };
+
+        NewWiresCycles {
+            nodes,
+            from_outputs,
+        }
+    }
+
+    /// Checks if new wire can connect `out_pin` to `in_pin`.
+    fn accepts_new_wire<V>(
+        &self,
+        viewer: &mut V,
+        cycles: &NewWiresCycles,
+        out_pin: OutPinId,
+        in_pin: InPinId,
+        style: &SnarlStyle,
+    ) -> bool
+    where
+        V: SnarlViewer<T>,
+    {
+        let target = if cycles.from_outputs {
+            in_pin.node
+        } else {
+            out_pin.node
+        };
+
+        crate::check_rules_with(out_pin, in_pin, style, |_, _| {
+            cycles.nodes.contains(&target)
+        })
+        .is_ok()
+            && viewer.can_connect(&OutPin::new(self, out_pin), &InPin::new(self, in_pin), self)
+    }
+
+    /// Checks if `pin` can be connected to every new wire.
+    ///
+    /// Returns `None` if `pin` is on the same side as pins new wires are dragged from.
+    fn accepts_new_wires<V>(
+        &self,
+        viewer: &mut V,
+        new_wires: &NewWires,
+        cycles: &NewWiresCycles,
+        pin: AnyPin,
+        style: &SnarlStyle,
+    ) -> Option<bool>
+    where
+        V: SnarlViewer<T>,
+    {
+        match (new_wires, pin) {
+            (NewWires::Out(out_pins), AnyPin::In(in_pin)) => Some(
+                out_pins
+                    .iter()
+                    .all(|out_pin| self.accepts_new_wire(viewer, cycles, *out_pin, in_pin, style)),
+            ),
+            (NewWires::In(in_pins), AnyPin::Out(out_pin)) => Some(
+                in_pins
+                    .iter()
+                    .all(|in_pin| self.accepts_new_wire(viewer, cycles, out_pin, *in_pin, style)),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Finds pin nearest to `pos` that every new wire can connect to.
+    ///
+    /// Only pins accepted by `filter` with their node and screen position are considered.
+    fn nearest_compatible_pin<V>(
+        &self,
+        viewer: &mut V,
+        new_wires: &NewWires,
+        cycles: &NewWiresCycles,
+        input_info: &HashMap<InPinId, PinResponse>,
+        output_info: &HashMap<OutPinId, PinResponse>,
+        style: &SnarlStyle,
+        pos: Pos2,
+        filter: impl Fn(NodeId, Pos2) -> bool,
+    ) -> Option<(AnyPin, Pos2)>
+    where
+        V: SnarlViewer<T>,
+    {
+        let distance = |r: &PinResponse| r.pos.distance_sq(pos);
+        let mut accepts = |pin: AnyPin| {
+            self.accepts_new_wires(viewer, new_wires, cycles, pin, style) == Some(true)
+        };
+
+        match new_wires {
+            NewWires::Out(_) => input_info
+                .iter()
+                .filter(|(in_pin, r)| {
+                    r.interactive && filter(in_pin.node, r.pos) && accepts(AnyPin::In(**in_pin))
+                })
+                .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+                .map(|(in_pin, r)| (AnyPin::In(*in_pin), r.pos)),
+            NewWires::In(_) => output_info
+                .iter()
+                .filter(|(out_pin, r)| {
+                    r.interactive && filter(out_pin.node, r.pos) && accepts(AnyPin::Out(**out_pin))
+                })
+                .min_by(|(_, a), (_, b)| distance(a).total_cmp(&distance(b)))
+                .map(|(out_pin, r)| (AnyPin::Out(*out_pin), r.pos)),
+        }
+    }
+
+    /// Shows body of the node that is not drawn in this frame
+    /// in invisible [`Ui`] of zero size.
+    ///
+    /// See [`SnarlViewer::body_needs_update`].
+    fn update_culled_body<V>(
+        &mut self,
+        viewer: &mut V,
+        node: NodeId,
+        ui: &mut Ui,
+        snarl_state: &SnarlState,
+        viewport: Rect,
+    ) where
+        V: SnarlViewer<T>,
+    {
+        let Node { pos, ref value, .. } = self.nodes[node.0];
+
+        if !viewer.has_body(value) {
+            return;
+        }
+
+        let inputs = (0..viewer.inputs(value))
+            .map(|idx| InPin::new(self, InPinId { node, input: idx }))
+            .collect::<Vec<_>>();
+
+        let outputs = (0..viewer.outputs(value))
+            .map(|idx| OutPin::new(self, OutPinId { node, output: idx }))
+            .collect::<Vec<_>>();
+
+        let pos = snarl_state.graph_pos_to_screen(pos, viewport);
+
+        let mut body_ui = ui.new_child(
+            UiBuilder::new()
+                .max_rect(Rect::from_min_size(pos, Vec2::ZERO))
+                .layout(Layout::left_to_right(Align::Min))
+                .id_salt(("culled-body", node))
+                .invisible(),
+        );
+        body_ui.set_clip_rect(Rect::NOTHING);
+
+        viewer.show_body(
+            node,
+            &inputs,
+            &outputs,
+            &mut body_ui,
+            snarl_state.scale(),
+            self,
+        );
+    }
+
+    /// Measures layout of nodes without live [`Ui`].
+    ///
+    /// Nodes are laid out by the same rules as in [`Snarl::show`] at scale `1.0`
+    /// with default egui style.
+    /// Header is measured as node's title shown by default [`SnarlViewer::show_header`],
+    /// laid out with provided `fonts`, so results depend on the fonts.
+    /// Pass fonts used by the application to match its rendering.
+    ///
+    /// Content of pins, body and footer is shown by the viewer in [`Ui`],
+    /// so it is not measured and only space reserved for pins is counted.
+    /// Pins on top and bottom edges with [`PinSides::TopBottom`] are centered over their content,
+    /// so their offsets are approximate for pins that show content.
+    /// Resized nodes have size set with [`Snarl::set_node_size`].
+    pub fn measure<V>(&self, viewer: &mut V, style: &SnarlStyle, fonts: &Fonts) -> SnarlMeasurement
+    where
+        V: SnarlViewer<T>,
+    {
+        let ui_style = Style::default();
+        let spacing = ui_style.spacing.item_spacing;
+        let pin_size = style.get_pin_size(1.0, &ui_style).max(0.0);
+        let font_id = TextStyle::Body.resolve(&ui_style);
+
+        // Pins' content is not measured, so each pin row takes only space reserved for the pin.
+        let pins_size = |count: usize, spacing_x: Option<f32>, row: bool| {
+            let item = match (spacing_x, row) {
+                (Some(spacing_x), false) => vec2(spacing_x, pin_size),
+                _ => Vec2::ZERO,
+            };
+            let gaps = count.saturating_sub(1) as f32;
+
+            #[allow(clippy::cast_precision_loss)]
+            let count = count as f32;
+
+            if row {
+                vec2(item.x.mul_add(count, spacing.x * gaps), item.y)
+            } else {
+                vec2(item.x, item.y.mul_add(count, spacing.y * gaps))
+            }
+        };
+
+        let mut measurement = SnarlMeasurement {
+            node_margin: style.get_node_frame(1.0, &ui_style).total_margin(),
+            ..SnarlMeasurement::default()
         };
 
-        viewer.draw_background(
-            style.bg_pattern.as_ref(),
-            &viewport,
-            style,
-            ui.style(),
-            ui.painter(),
-            self,
-        );
+        for (idx, node) in &self.nodes {
+            let id = NodeId(idx);
+
+            let inputs = (0..viewer.inputs(&node.value))
+                .map(|input| InPin::new(self, InPinId { node: id, input }))
+                .collect::<Vec<_>>();
+            let outputs = (0..viewer.outputs(&node.value))
+                .map(|output| OutPin::new(self, OutPinId { node: id, output }))
+                .collect::<Vec<_>>();
+
+            let node_frame = viewer.node_frame(
+                style.get_node_frame(1.0, &ui_style),
+                id,
+                &inputs,
+                &outputs,
+                self,
+            );
+            let header_frame = viewer.header_frame(
+                style.get_header_frame(1.0, &ui_style),
+                id,
+                &inputs,
+                &outputs,
+                self,
+            );
+
+            let node_layout = match style.get_pin_sides() {
+                PinSides::LeftRight => {
+                    viewer.node_layout(style.get_node_layout(), id, &inputs, &outputs, self)
+                }
+                PinSides::TopBottom => NodeLayout::Sandwich,
+            };
+
+            // Header items are laid out from left to right.
+            let title = fonts.layout_no_wrap(
+                viewer.title(&node.value),
+                font_id.clone(),
+                Color32::PLACEHOLDER,
+            );
+            let drag_space = style.get_header_drag_space(1.0, &ui_style).max(Vec2::ZERO);
+            let mut header = vec2(
+                drag_space.x + spacing.x + title.size().x,
+                f32::max(drag_space.y, title.size().y),
+            );
+            if style.get_collapsible() {
+                header.x += ui_style.spacing.icon_width + spacing.x;
+                header.y = header.y.max(ui_style.spacing.icon_width);
+            }
+            if let Some(header_height) = style.get_header_height(1.0) {
+                header.y = header.y.max(header_height);
+            }
+
+            let rows = style.get_pin_sides() == PinSides::TopBottom;
+            let columns = pin_columns(style, Rect::ZERO, node_frame.inner_margin, pin_size, 1.0);
+
+            let parts = NodeParts {
+                header,
+                inputs: pins_size(inputs.len(), columns.input_spacing, rows),
+                outputs: pins_size(outputs.len(), columns.output_spacing, rows),
+                body: viewer.has_body(&node.value).then_some(Vec2::ZERO),
+                footer: viewer.has_footer(&node.value).then_some(Vec2::ZERO),
+            };
+
+            let header_margin = header_frame.total_margin().bottom;
+            let content_size = node_content_size(node_layout, &parts, header_margin, spacing);
+            let aspect_ratio = viewer.node_aspect_ratio(id, self);
+            let mut size = node_size(content_size, node.size, aspect_ratio);
+
+            // Collapsed node is as tall as its header.
+            if !node.open {
+                size.y = header.y;
+            }
+
+            let node_rect = Rect::from_min_size(node.pos, size);
+            let frame_rect = node_rect + node_frame.total_margin();
+            let columns = pin_columns(style, frame_rect, node_frame.inner_margin, pin_size, 1.0);
+
+            // Pins are placed under the header, in order of node layout.
+            let payload_top = node_rect.top() + header.y + header_margin + spacing.y;
+            let body_height = parts.body.map_or(0.0, |body| body.y + spacing.y);
+            let (inputs_top, outputs_top) = match node_layout {
+                NodeLayout::Basic => (payload_top, payload_top),
+                NodeLayout::Sandwich => (
+                    payload_top,
+                    payload_top + parts.inputs.y + spacing.y + body_height,
+                ),
+                NodeLayout::FlippedSandwich => (
+                    payload_top + parts.outputs.y + spacing.y + body_height,
+                    payload_top,
+                ),
+            };
+
+            let min_pin_y = header.y.mul_add(0.5, node_rect.top());
+            let pin_pos = |idx: usize, x: f32, top: f32, height: f32, row_y: Option<f32>| {
+                #[allow(clippy::cast_precision_loss)]
+                let idx = idx as f32;
+                match row_y {
+                    // Pins in a row are centered over their content, which is not measured,
+                    // so each pin takes only item spacing.
+                    Some(row_y) => pos2(idx.mul_add(spacing.x, node_rect.left()), row_y),
+                    None => pos2(
+                        x,
+                        min_pin_y.max(idx.mul_add(height + spacing.y, top) + height * 0.5),
+                    ),
+                }
+            };
+
+            let mut anchor = |pin: AnyPin, pos: Pos2| {
+                viewer.pin_anchor(pin, frame_rect, self).unwrap_or(pos) - node.pos
+            };
+
+            let inputs = inputs
+                .iter()
+                .enumerate()
+                .map(|(idx, pin)| {
+                    let height = columns.input_spacing.map_or(0.0, |_| pin_size);
+                    let pos = pin_pos(
+                        idx,
+                        columns.input_x,
+                        inputs_top,
+                        height,
+                        columns.input_row_y,
+                    );
+                    anchor(AnyPin::In(pin.id), pos)
+                })
+                .collect();
+            let outputs = outputs
+                .iter()
+                .enumerate()
+                .map(|(idx, pin)| {
+                    let height = columns.output_spacing.map_or(0.0, |_| pin_size);
+                    let pos = pin_pos(
+                        idx,
+                        columns.output_x,
+                        outputs_top,
+                        height,
+                        columns.output_row_y,
+                    );
+                    anchor(AnyPin::Out(pin.id), pos)
+                })
+                .collect();
+
+            measurement.nodes.insert(
+                id,
+                NodeMeasurement {
+                    offset: frame_rect.min - node.pos,
+                    size: frame_rect.size(),
+                    inputs,
+                    outputs,
+                },
+            );
+        }
+
+        measurement
     }
 
     /// Render [`Snarl`] using given viewer and style into the [`Ui`].
-    pub fn show<V>(&mut self, viewer: &mut V, style: &SnarlStyle, id_salt: impl Hash, ui: &mut Ui)
+    ///
+    /// Returned [`SnarlResponse`] tells whether the graph was changed during the frame
+    /// and where the pointer is in graph space.
+    pub fn show<V>(
+        &mut self,
+        viewer: &mut V,
+        style: &SnarlStyle,
+        id_salt: impl Hash,
+        ui: &mut Ui,
+    ) -> SnarlResponse
     where
         V: SnarlViewer<T>,
     {
@@ -655,6 +2468,11 @@ impl<T> Snarl<T> {
 
         let snarl_id = ui.make_persistent_id(id_salt);
 
+        let fingerprint = self.fingerprint();
+        let mut cursor_graph_pos = None;
+        let mut viewport_changed = None;
+        let mut hover_target = None;
+
         // Draw background pattern.
         let bg_frame = style.get_bg_frame(ui.style());
 
@@ -665,25 +2483,63 @@ impl<T> Snarl<T> {
             modifiers: i.modifiers,
             // primary_pressed: i.pointer.primary_pressed(),
             secondary_pressed: i.pointer.secondary_pressed(),
+            stable_dt: i.stable_dt,
+            escape_pressed: i.key_pressed(Key::Escape),
+            pointer_velocity: i.pointer.velocity(),
         });
 
         bg_frame.show(ui, |ui| {
             let mut node_moved = None;
+            let mut node_dropped = None;
             let mut node_to_top = None;
 
             let mut bg_r = ui.allocate_rect(ui.max_rect(), Sense::click_and_drag());
             let viewport = bg_r.rect;
-            ui.set_clip_rect(viewport);
+            // Intersect with current clip rect so that nested snarls do not draw outside of parent.
+            ui.set_clip_rect(viewport.intersect(ui.clip_rect()));
 
             let pivot = input.hover_pos.unwrap_or_else(|| viewport.center());
 
             let mut snarl_state =
                 SnarlState::load(ui.ctx(), snarl_id, pivot, viewport, self, style);
 
+            // Node waiting for focus may be removed outside of `show`
+            // and its id reused by an unrelated node.
+            if snarl_state
+                .focus_node()
+                .is_some_and(|(node, _)| !self.nodes.contains(node.0))
+            {
+                snarl_state.set_focus_node(None);
+            }
+
+            let view_center = ui
+                .ctx()
+                .data_mut(|d| d.remove_temp::<Pos2>(snarl_id.with("view-center")));
+            if let Some(center) = view_center {
+                let center = match self.graph_bounds(ui.ctx(), snarl_id) {
+                    Some(bounds) => center.clamp(bounds.min, bounds.max),
+                    None => center,
+                };
+                snarl_state.set_offset(center.to_vec2() * snarl_state.scale());
+            }
+
+            let mut zoom_request = ui
+                .ctx()
+                .data_mut(|d| d.remove_temp::<ZoomRequest>(snarl_id.with("zoom-request")));
+
+            self.wire_drag = snarl_state.new_wires().map(|pins| WireDrag {
+                pins: pins.clone(),
+                pos: snarl_state.screen_pos_to_graph(pivot, viewport),
+            });
+
             if let Some(selection) = viewer.update_selection(snarl_state.selected_nodes()) {
                 snarl_state.select_many_nodes(true, selection.iter().cloned());
             }
 
+            if self.advance_layout_animation(input.stable_dt) {
+                ui.ctx().request_repaint();
+            }
+
             ui.style_mut().zoom(snarl_state.scale());
 
             // let mut node_style: Style = (**ui.style()).clone();
@@ -693,6 +2549,9 @@ impl<T> Snarl<T> {
             self.draw_background(viewer, style, &snarl_state, &viewport, ui);
 
             let wire_frame_size = style.get_wire_frame_size(snarl_state.scale(), ui.style());
+
+            // Wires leave pins on top and bottom edges vertically.
+            let vertical_wires = style.get_pin_sides() == PinSides::TopBottom;
             let wire_width = style.get_wire_width(snarl_state.scale(), ui.style());
 
             let wire_shape_idx = match style.get_wire_layer() {
@@ -701,39 +2560,247 @@ impl<T> Snarl<T> {
             };
 
             // Zooming
-            match input.hover_pos {
-                Some(hover_pos)
-                    if viewport.contains(hover_pos) && ui.rect_contains_pointer(viewport) =>
+            let hovered = input
+                .hover_pos
+                .is_some_and(|hover_pos| viewport.contains(hover_pos))
+                && ui.rect_contains_pointer(viewport);
+
+            // Only innermost snarl is zoomed when snarls are nested.
+            let innermost = snarl_state.update_hovered(ui.ctx(), hovered);
+
+            if let Some(shortcut) = style.get_wire_style_shortcut() {
+                if hovered && innermost && ui.input_mut(|i| i.consume_shortcut(&shortcut)) {
+                    snarl_state.cycle_wire_style(style);
+                }
+            }
+
+            if style.get_zoom_shortcuts() && hovered && innermost {
+                let shortcut = |key| KeyboardShortcut::new(Modifiers::COMMAND, key);
+
+                let request = ui.input_mut(|i| {
+                    if i.consume_shortcut(&shortcut(Key::Num0)) {
+                        return Some(ZoomRequest::Reset);
+                    }
+
+                    // Each key press in the frame is one step.
+                    let mut steps = 0;
+                    while i.consume_shortcut(&shortcut(Key::Equals))
+                        || i.consume_shortcut(&shortcut(Key::Plus))
+                    {
+                        steps += 1;
+                    }
+                    while i.consume_shortcut(&shortcut(Key::Minus)) {
+                        steps -= 1;
+                    }
+                    (steps != 0).then_some(ZoomRequest::Steps(steps))
+                });
+
+                if let Some(request) = request {
+                    zoom_request =
+                        Some(zoom_request.map_or(request, |prev: ZoomRequest| prev.then(request)));
+                }
+            }
+
+            if let Some(shortcut) = style.get_fit_selection_shortcut() {
+                if hovered
+                    && innermost
+                    && !ui.ctx().wants_keyboard_input()
+                    && ui.input_mut(|i| i.consume_shortcut(&shortcut))
                 {
-                    if input.zoom_delta != 1.0 {
-                        let new_scale = (snarl_state.scale()
-                            * input.zoom_delta.powf(style.get_scale_velocity()))
-                        .clamp(style.get_min_scale(), style.get_max_scale());
+                    zoom_request = Some(ZoomRequest::FitSelection(FIT_SELECTION_MARGIN));
+                }
+            }
+
+            if style.get_keyboard_delete()
+                && hovered
+                && innermost
+                && (!snarl_state.selected_nodes().is_empty() || snarl_state.wire_menu().is_some())
+                && !ui.ctx().wants_keyboard_input()
+                && ui.input_mut(|i| {
+                    i.consume_key(Modifiers::NONE, Key::Delete)
+                        || i.consume_key(Modifiers::NONE, Key::Backspace)
+                })
+            {
+                // Wire with open context menu is selected as well.
+                if let Some((from, to)) = snarl_state.wire_menu() {
+                    snarl_state.close_wire_menu();
 
-                        snarl_state.set_scale(new_scale);
+                    let wire = Wire {
+                        out_pin: from,
+                        in_pin: to,
+                    };
+                    if self.wires.contains(&wire) {
+                        let out_pin = OutPin::new(self, from);
+                        let in_pin = InPin::new(self, to);
+                        viewer.disconnect(&out_pin, &in_pin, self);
+                    }
+                }
+
+                let nodes = snarl_state.selected_nodes().to_vec();
+                snarl_state.deselect_all_nodes();
+
+                for node in nodes {
+                    if self.nodes.contains(node.0) {
+                        viewer.remove_node(node, self);
+                        NodeState::forget(ui.ctx(), snarl_id.with(("snarl-node", node)));
                     }
                 }
-                _ => {}
+            }
+
+            if let Some(request) = zoom_request {
+                let scale = match request {
+                    ZoomRequest::To(scale) => scale,
+                    ZoomRequest::Reset => {
+                        if let Some(bounds) = self.graph_bounds(ui.ctx(), snarl_id) {
+                            snarl_state.set_offset(bounds.center().to_vec2() * snarl_state.scale());
+                        }
+                        1.0
+                    }
+                    ZoomRequest::Steps(steps) => snarl_state.target_scale() * ZOOM_STEP.powi(steps),
+                    ZoomRequest::FitSelection(margin) => {
+                        let selected = snarl_state.selected_nodes();
+                        let bounds = if !selected.is_empty() {
+                            self.nodes_bounds(ui.ctx(), snarl_id, selected.iter().copied())
+                        } else if style.get_fit_all_without_selection() {
+                            self.graph_bounds(ui.ctx(), snarl_id)
+                        } else {
+                            None
+                        };
+
+                        match bounds {
+                            Some(bounds) => {
+                                snarl_state
+                                    .set_offset(bounds.center().to_vec2() * snarl_state.scale());
+
+                                let size = (viewport.size() - Vec2::splat(margin * 2.0))
+                                    .max(Vec2::splat(1.0));
+                                let bounds_size = bounds.size().max(Vec2::splat(1.0));
+                                f32::min(size.x / bounds_size.x, size.y / bounds_size.y)
+                            }
+                            None => snarl_state.target_scale(),
+                        }
+                    }
+                };
+
+                snarl_state
+                    .set_scale_centered(scale.clamp(style.get_min_scale(), style.get_max_scale()));
+            }
+
+            // Viewport is frozen while dragging, so stray scroll does not move things under the pointer.
+            // Edge panning is applied separately.
+            let dragging = snarl_state.is_dragging();
+
+            if hovered && innermost && !dragging && input.zoom_delta != 1.0 {
+                let new_scale = (snarl_state.scale()
+                    * input.zoom_delta.powf(style.get_scale_velocity()))
+                .clamp(style.get_min_scale(), style.get_max_scale());
+
+                snarl_state.set_scale(new_scale);
+            }
+
+            let scroll_pans = hovered && innermost && !dragging && style.get_capture_scroll();
+
+            // Widgets in nodes consume scroll they use,
+            // so graph is panned after nodes are shown.
+            let pan_after_nodes = style.get_nodes_capture_scroll();
+
+            if scroll_pans && !pan_after_nodes {
+                pan_by_scroll(ui, &mut snarl_state);
             }
             let mut input_info = HashMap::new();
             let mut output_info = HashMap::new();
 
             let mut pin_hovered = None;
 
-            let draw_order = snarl_state.update_draw_order(self);
+            let new_wires_cycles = snarl_state
+                .new_wires()
+                .map(|new_wires| self.new_wires_cycles(new_wires, style))
+                .unwrap_or_default();
+
+            let mut draw_order = snarl_state.update_draw_order(self);
+
+            // Nodes skipped in this frame.
+            let mut culled = Vec::new();
+
+            if let Some(max) = style.get_max_nodes_per_frame() {
+                if draw_order.len() > max {
+                    let (picked, pending) = self.budget_draw_order(
+                        &draw_order,
+                        max,
+                        fingerprint,
+                        &snarl_state,
+                        snarl_id,
+                        viewport,
+                        input.hover_pos,
+                        ui.ctx(),
+                    );
+
+                    let picked_set = picked.iter().copied().collect::<HashSet<_>>();
+                    culled = draw_order
+                        .into_iter()
+                        .filter(|node| !picked_set.contains(node))
+                        .collect();
+                    draw_order = picked;
+
+                    // Draw deferred nodes in next frames.
+                    if pending {
+                        ui.ctx().request_repaint();
+                    }
+                }
+            }
+
             let mut drag_released = false;
 
+            // Nodes around the node hovered in previous frame.
+            let neighborhood = match snarl_state.hovered_node() {
+                Some(node) if style.get_neighborhood_highlight() && self.nodes.contains(node.0) => {
+                    let mut nodes = self.neighbors(node).collect::<Vec<_>>();
+                    nodes.push(node);
+                    Some((node, nodes))
+                }
+                _ => None,
+            };
+
+            let node_dimmed =
+                |node: NodeId| matches!(&neighborhood, Some((_, nodes)) if !nodes.contains(&node));
+
+            let node_opacity = |viewer: &mut V, snarl: &Snarl<T>, node: NodeId| {
+                let opacity = viewer.node_opacity(node, snarl).clamp(0.0, 1.0);
+                if node_dimmed(node) {
+                    opacity * DIMMED_OPACITY
+                } else {
+                    opacity
+                }
+            };
+
+            // Cycles are searched again only when wires change.
+            let cycles = style
+                .get_highlight_cycles()
+                .then(|| self.cached_cycles(snarl_id, ui.ctx()));
+            let in_cycle = |node: NodeId| {
+                cycles
+                    .as_ref()
+                    .is_some_and(|cycles| cycles.contains_key(&node))
+            };
+
+            let mut hovered_node = None;
+
             let mut centers_sum = vec2(0.0, 0.0);
             let mut centers_weight = 0;
 
             let mut node_rects = Vec::new();
 
+            let nodes_timer = style.get_debug_overlay().then(DebugTimer::start);
+
             for node_idx in draw_order {
                 if !self.nodes.contains(node_idx.0) {
                     continue;
                 }
 
                 // show_node(node_idx);
+                let opacity = viewer.node_opacity(node_idx, self).clamp(0.0, 1.0);
+                let dimmed = node_dimmed(node_idx);
+
                 let response = self.draw_node(
                     ui,
                     node_idx,
@@ -744,15 +2811,25 @@ impl<T> Snarl<T> {
                     &mut input_info,
                     &input,
                     &mut output_info,
+                    opacity,
+                    dimmed,
+                    in_cycle(node_idx),
+                    &new_wires_cycles,
                 );
 
                 if let Some(response) = response {
+                    if response.hovered {
+                        hovered_node = Some(node_idx);
+                    }
                     if let Some(v) = response.node_to_top {
                         node_to_top = Some(v);
                     }
                     if let Some(v) = response.node_moved {
                         node_moved = Some(v);
                     }
+                    if let Some(v) = response.node_dropped {
+                        node_dropped = Some(v);
+                    }
                     if let Some(v) = response.pin_hovered {
                         pin_hovered = Some(v);
                     }
@@ -761,18 +2838,196 @@ impl<T> Snarl<T> {
                     centers_sum += response.final_rect.center().to_vec2();
                     centers_weight += 1;
 
-                    if snarl_state.is_rect_selection() {
-                        node_rects.push((node_idx, response.final_rect));
+                    node_rects.push((node_idx, response.final_rect));
+                }
+            }
+
+            for node in culled {
+                if self.nodes.contains(node.0) && viewer.body_needs_update(node, self) {
+                    self.update_culled_body(viewer, node, ui, &snarl_state, viewport);
+                }
+            }
+
+            let nodes_time = nodes_timer.and_then(|timer| timer.elapsed_ms());
+
+            // Pin offsets are kept for `Snarl::node_anchor`,
+            // including nodes that were not drawn in this frame.
+            // Pins that no longer exist are dropped even if their node was not drawn.
+            let pin_offsets_id = snarl_id.with("pin-offsets");
+            let mut pin_offsets = ui
+                .ctx()
+                .data_mut(|d| d.remove_temp::<PinOffsets>(pin_offsets_id))
+                .unwrap_or_default();
+
+            pin_offsets.inputs.retain(|pin, _| {
+                self.nodes
+                    .get(pin.node.0)
+                    .is_some_and(|node| pin.input < viewer.inputs(&node.value))
+            });
+            pin_offsets.outputs.retain(|pin, _| {
+                self.nodes
+                    .get(pin.node.0)
+                    .is_some_and(|node| pin.output < viewer.outputs(&node.value))
+            });
+
+            for (pin, r) in &input_info {
+                if let Some(node) = self.nodes.get(pin.node.0) {
+                    let offset = snarl_state.screen_pos_to_graph(r.pos, viewport) - node.pos;
+                    pin_offsets.inputs.insert(*pin, offset);
+                }
+            }
+
+            for (pin, r) in &output_info {
+                if let Some(node) = self.nodes.get(pin.node.0) {
+                    let offset = snarl_state.screen_pos_to_graph(r.pos, viewport) - node.pos;
+                    pin_offsets.outputs.insert(*pin, offset);
+                }
+            }
+
+            ui.ctx()
+                .data_mut(|d| d.insert_temp(pin_offsets_id, pin_offsets));
+
+            // Pin under the pointer, before new wire is snapped to other pins.
+            let pointer_pin = pin_hovered;
+
+            // New wire over node's body targets the nearest compatible pin.
+            let mut body_rejected = false;
+
+            if pin_hovered.is_none() && style.get_connect_to_node_body() {
+                if let (Some(new_wires), Some(hover_pos)) =
+                    (snarl_state.new_wires(), input.hover_pos)
+                {
+                    let graph_pos = snarl_state.screen_pos_to_graph(hover_pos, viewport);
+
+                    // Nodes are drawn in order, so the last one is on top.
+                    let node = node_rects
+                        .iter()
+                        .rev()
+                        .find(|(_, rect)| rect.contains(graph_pos))
+                        .map(|&(node, _)| node);
+
+                    if let Some(node) = node {
+                        let target = self.nearest_compatible_pin(
+                            viewer,
+                            new_wires,
+                            &new_wires_cycles,
+                            &input_info,
+                            &output_info,
+                            style,
+                            hover_pos,
+                            |pin_node, _| pin_node == node,
+                        );
+
+                        body_rejected = target.is_none();
+                        pin_hovered = target.map(|(pin, _)| pin);
+                    }
+                }
+            }
+
+            // New wire released near a pin snaps to it.
+            let mut snapped_pos = None;
+            let snap_radius = style.get_connect_snap_radius(snarl_state.scale());
+
+            if pin_hovered.is_none() && !body_rejected && snap_radius > 0.0 {
+                if let (Some(new_wires), Some(hover_pos)) =
+                    (snarl_state.new_wires(), input.hover_pos)
+                {
+                    let target = self.nearest_compatible_pin(
+                        viewer,
+                        new_wires,
+                        &new_wires_cycles,
+                        &input_info,
+                        &output_info,
+                        style,
+                        hover_pos,
+                        |_, pos| {
+                            viewport.contains(pos)
+                                && pos.distance_sq(hover_pos) <= snap_radius * snap_radius
+                        },
+                    );
+
+                    if let Some((pin, pos)) = target {
+                        pin_hovered = Some(pin);
+                        snapped_pos = Some(pos);
                     }
                 }
             }
 
+            // Pins of non-interactive nodes reject new wires.
+            let pin_locked = match pin_hovered {
+                Some(AnyPin::In(pin)) => input_info.get(&pin).is_some_and(|r| !r.interactive),
+                Some(AnyPin::Out(pin)) => output_info.get(&pin).is_some_and(|r| !r.interactive),
+                None => false,
+            };
+
             let mut hovered_wire = None;
             let mut hovered_wire_disconnect = false;
+            let mut wire_double_click = None;
             let mut wire_shapes = Vec::new();
+
+            // Highlighted wires are drawn after the rest to not be hidden under them.
+            let mut highlighted_wire_shapes = Vec::new();
+            let mut selected_wire_shapes = Vec::new();
+            let mut hovered_wire_shapes = Vec::new();
+
+            let wire_selected_stroke =
+                style.get_wire_selected_stroke(snarl_state.scale(), ui.style());
+            let wire_hovered_stroke =
+                style.get_wire_hovered_stroke(snarl_state.scale(), ui.style());
+
             let mut wire_hit = false;
 
-            for wire in self.wires.iter() {
+            // Response used to open wire context menu.
+            // Separate id keeps it from conflicting with graph menu.
+            let mut wire_menu_r = bg_r.clone();
+            wire_menu_r.id = snarl_id.with("wire-menu");
+            let mut wire_menu_clicked = false;
+
+            // Routes of wires that avoid nodes are cached in graph space,
+            // so panning does not invalidate them.
+            let routes_id = snarl_id.with("wire-routes");
+            let mut routes = ui
+                .ctx()
+                .data_mut(|d| d.remove_temp::<WireRoutes>(routes_id))
+                .unwrap_or_default();
+
+            let wire_cache_id = snarl_id.with("wire-cache");
+            let mut wire_cache = style.get_wire_cache().then(|| {
+                let mut wire_cache: WireCache = ui
+                    .ctx()
+                    .data_mut(|d| d.remove_temp(wire_cache_id))
+                    .unwrap_or_default();
+                wire_cache.start_frame();
+                wire_cache
+            });
+
+            let avoidance_margin = style.get_wire_avoidance_margin(snarl_state.scale(), ui.style())
+                / snarl_state.scale();
+
+            let wires_timer = style.get_debug_overlay().then(DebugTimer::start);
+
+            // Geometry of drawn wires for `Snarl::wire_path`.
+            let mut wire_geometries = WireGeometries::default();
+
+            let show_wire_labels = snarl_state.scale() >= style.get_wire_label_min_scale();
+            let mut wire_label_shapes = Vec::new();
+
+            // Wires leave outputs and enter inputs along the main axis.
+            let pin_gap = if vertical_wires {
+                vec2(0.0, style.get_wire_pin_gap())
+            } else {
+                vec2(style.get_wire_pin_gap(), 0.0)
+            };
+
+            // Wires are drawn in order of `SnarlViewer::wire_z`.
+            let mut wires = self
+                .wires
+                .iter()
+                .map(|wire| (viewer.wire_z(&wire.out_pin, &wire.in_pin, self), wire))
+                .collect::<Vec<_>>();
+            wires.sort_by_key(|&(z, _)| z);
+
+            for (_, wire) in wires {
                 let Some(from_r) = output_info.get(&wire.out_pin) else {
                     continue;
                 };
@@ -780,33 +3035,125 @@ impl<T> Snarl<T> {
                     continue;
                 };
 
+                let from_pos = from_r.pos + pin_gap;
+                let to_pos = to_r.pos - pin_gap;
+
+                // Self-loops always get a full-size frame,
+                // otherwise the wire is squeezed over the node it starts from.
+                let (upscale, downscale) = if wire.out_pin.node == wire.in_pin.node {
+                    (true, false)
+                } else {
+                    (
+                        style.get_upscale_wire_frame(),
+                        style.get_downscale_wire_frame(),
+                    )
+                };
+
+                let wire_style = pick_wire_style(
+                    snarl_state.wire_style(style),
+                    from_r.wire_style.zoomed(snarl_state.scale()),
+                    to_r.wire_style.zoomed(snarl_state.scale()),
+                );
+
+                // Vertical wires are routed as horizontal ones in transposed space.
+                let axes = |pos: Pos2| if vertical_wires { transpose(pos) } else { pos };
+
+                // Route in graph space.
+                let graph_route = match wire_style {
+                    WireStyle::Avoid { .. } => routes
+                        .route(
+                            wire,
+                            axes(snarl_state.screen_pos_to_graph(from_pos, viewport)),
+                            axes(snarl_state.screen_pos_to_graph(to_pos, viewport)),
+                            node_rects
+                                .iter()
+                                .filter(|(id, _)| {
+                                    *id != wire.out_pin.node && *id != wire.in_pin.node
+                                })
+                                .map(|&(_, rect)| {
+                                    let rect = snarl_state.screen_rect_to_graph(rect, viewport);
+                                    Rect::from_two_pos(axes(rect.min), axes(rect.max))
+                                }),
+                            avoidance_margin,
+                        )
+                        .map(|route| route.iter().map(|&pos| axes(pos)).collect::<Vec<_>>()),
+                    _ => None,
+                };
+
+                let route = graph_route.as_ref().map(|route| {
+                    route
+                        .iter()
+                        .map(|&pos| snarl_state.graph_pos_to_screen(pos, viewport))
+                        .collect::<Vec<_>>()
+                });
+
+                let geometry = WireGeometry {
+                    from: snarl_state.screen_pos_to_graph(from_pos, viewport),
+                    to: snarl_state.screen_pos_to_graph(to_pos, viewport),
+                    frame_size: wire_frame_size / snarl_state.scale(),
+                    upscale,
+                    downscale,
+                    vertical: vertical_wires,
+                    style: wire_style.zoomed(1.0 / snarl_state.scale()),
+                    route: graph_route,
+                };
+
+                // Label is placed at the middle of the wire path.
+                let label = if show_wire_labels {
+                    viewer
+                        .wire_label(&wire.out_pin, &wire.in_pin, self)
+                        .map(|text| {
+                            let (pos, _) = geometry.path().sample(0.5);
+                            (text, snarl_state.graph_pos_to_screen(pos, viewport))
+                        })
+                } else {
+                    None
+                };
+
+                wire_geometries.insert(wire, geometry);
+
                 if !wire_hit && !snarl_state.has_new_wires() && bg_r.hovered() && !bg_r.dragged() {
                     // Try to find hovered wire
                     // If not draggin new wire
                     // And not hovering over item above.
 
                     if let Some(interact_pos) = input.interact_pos {
-                        wire_hit = hit_wire(
-                            interact_pos,
-                            wire_frame_size,
-                            style.get_upscale_wire_frame(),
-                            style.get_downscale_wire_frame(),
-                            from_r.pos,
-                            to_r.pos,
-                            wire_width.max(1.5),
-                            pick_wire_style(
-                                style.get_wire_style(snarl_state.scale()),
-                                from_r.wire_style,
-                                to_r.wire_style,
-                            )
-                            .zoomed(snarl_state.scale()),
-                        );
+                        wire_hit = match &route {
+                            Some(route) => hit_wire_route(interact_pos, route, wire_width.max(1.5)),
+                            None => hit_wire(
+                                interact_pos,
+                                wire_frame_size,
+                                upscale,
+                                downscale,
+                                vertical_wires,
+                                from_pos,
+                                to_pos,
+                                wire_width.max(1.5),
+                                pick_wire_style(
+                                    snarl_state.wire_style(style),
+                                    from_r.wire_style,
+                                    to_r.wire_style,
+                                )
+                                .zoomed(snarl_state.scale()),
+                            ),
+                        };
 
                         if wire_hit {
                             hovered_wire = Some(wire);
 
-                            //Remove hovered wire by second click
-                            hovered_wire_disconnect |= bg_r.clicked_by(PointerButton::Secondary);
+                            if bg_r.clicked_by(PointerButton::Secondary) {
+                                if viewer.has_wire_menu(&wire.out_pin, &wire.in_pin, self) {
+                                    snarl_state.open_wire_menu(wire.out_pin, wire.in_pin);
+                                    wire_menu_clicked = true;
+                                } else {
+                                    //Remove hovered wire by second click
+                                    hovered_wire_disconnect = true;
+                                }
+                            }
+
+                            if bg_r.double_clicked_by(PointerButton::Primary) {
+                                wire_double_click = Some(interact_pos);
+                            }
 
                             // Background is not hovered then.
                             bg_r.flags &= Flags::HOVERED;
@@ -815,28 +3162,183 @@ impl<T> Snarl<T> {
                     }
                 }
 
-                let color = mix_colors(from_r.pin_color, to_r.pin_color);
+                let mut color = mix_colors(from_r.pin_color, to_r.pin_color);
+
+                // Wire between nodes of the same cycle is part of it.
+                if let Some(cycles) = &cycles {
+                    if let (Some(a), Some(b)) = (
+                        cycles.get(&wire.out_pin.node),
+                        cycles.get(&wire.in_pin.node),
+                    ) {
+                        if a == b {
+                            color = lerp_colors(color, ui.visuals().warn_fg_color, 0.75);
+                        }
+                    }
+                }
+
+                let in_neighborhood = match &neighborhood {
+                    Some((node, _)) => wire.out_pin.node == *node || wire.in_pin.node == *node,
+                    None => false,
+                };
+
+                // Wire is dimmed by average opacity of its endpoints.
+                let mut opacity = (node_opacity(viewer, self, wire.out_pin.node)
+                    + node_opacity(viewer, self, wire.in_pin.node))
+                    * 0.5;
+                if neighborhood.is_some() && !in_neighborhood {
+                    opacity *= DIMMED_OPACITY;
+                }
+                if opacity < 1.0 {
+                    color = color.gamma_multiply(opacity);
+                }
 
                 let mut draw_width = wire_width;
                 if hovered_wire == Some(wire) {
                     draw_width *= 1.5;
                 }
 
-                draw_wire(
-                    ui,
-                    &mut wire_shapes,
-                    wire_frame_size,
-                    style.get_upscale_wire_frame(),
-                    style.get_downscale_wire_frame(),
-                    from_r.pos,
-                    to_r.pos,
-                    Stroke::new(draw_width, color),
-                    pick_wire_style(
-                        style.get_wire_style(snarl_state.scale()),
-                        from_r.wire_style.zoomed(snarl_state.scale()),
-                        to_r.wire_style.zoomed(snarl_state.scale()),
+                let activity = snarl_state.wire_activity(wire.out_pin, wire.in_pin);
+                if activity > 0.0 {
+                    draw_width *= 1.0 + activity;
+                    color = lerp_colors(color, Color32::WHITE, activity * 0.5);
+                }
+
+                let selected = snarl_state.wire_menu() == Some((wire.out_pin, wire.in_pin))
+                    || (snarl_state.selected_nodes().contains(&wire.out_pin.node)
+                        && snarl_state.selected_nodes().contains(&wire.in_pin.node));
+
+                // Outline is drawn under the wire, so wire keeps its color.
+                let outline = if hovered_wire == Some(wire) {
+                    Some(wire_hovered_stroke)
+                } else if selected {
+                    Some(wire_selected_stroke)
+                } else {
+                    None
+                }
+                .filter(|outline| !outline.is_empty())
+                .map(|outline| {
+                    Stroke::new(
+                        draw_width + outline.width * 2.0,
+                        outline.color.gamma_multiply(opacity),
+                    )
+                });
+
+                let shapes = if hovered_wire == Some(wire) {
+                    &mut hovered_wire_shapes
+                } else if selected {
+                    &mut selected_wire_shapes
+                } else if activity > 0.0
+                    || in_neighborhood
+                    || snarl_state.selected_nodes().contains(&wire.out_pin.node)
+                    || snarl_state.selected_nodes().contains(&wire.in_pin.node)
+                {
+                    &mut highlighted_wire_shapes
+                } else {
+                    &mut wire_shapes
+                };
+
+                let stroke = Stroke::new(draw_width, color);
+
+                if let Some((text, pos)) = label {
+                    draw_wire_label(ui, &mut wire_label_shapes, text, pos, opacity);
+                }
+
+                let draw = |shapes: &mut Vec<Shape>, stroke: Stroke| match (&route, wire_style) {
+                    (Some(route), WireStyle::Avoid { corner_radius }) => {
+                        draw_wire_route(ui, shapes, route, corner_radius, stroke);
+                    }
+                    _ => draw_wire(
+                        ui,
+                        shapes,
+                        wire_frame_size,
+                        upscale,
+                        downscale,
+                        vertical_wires,
+                        from_pos,
+                        to_pos,
+                        stroke,
+                        wire_style,
                     ),
-                );
+                };
+
+                // Outlines are drawn only for a few wires and are not cached.
+                if let Some(outline) = outline {
+                    draw(shapes, outline);
+                }
+
+                match &mut wire_cache {
+                    Some(wire_cache) => {
+                        let clip = ui.clip_rect();
+                        let floats = [
+                            from_pos.x,
+                            from_pos.y,
+                            to_pos.x,
+                            to_pos.y,
+                            wire_frame_size,
+                            stroke.width,
+                            clip.min.x,
+                            clip.min.y,
+                            clip.max.x,
+                            clip.max.y,
+                            ui.ctx().pixels_per_point(),
+                            style.get_wire_feathering().unwrap_or(-1.0),
+                        ];
+                        // Route depends on nodes around the wire, not only on its endpoints.
+                        let route_floats = route.iter().flatten().flat_map(|pos| [pos.x, pos.y]);
+
+                        let key = wire_geometry_key(
+                            floats.into_iter().chain(route_floats),
+                            wire_style,
+                            &[upscale, downscale, vertical_wires],
+                        );
+                        wire_cache.draw(
+                            (wire.out_pin, wire.in_pin),
+                            key,
+                            stroke.color,
+                            shapes,
+                            || {
+                                let mut wire_shapes = Vec::new();
+                                draw(&mut wire_shapes, Stroke::new(stroke.width, Color32::WHITE));
+                                tessellate_mesh(ui, wire_shapes, style.get_wire_feathering())
+                            },
+                        );
+                    }
+                    None => draw(shapes, stroke),
+                }
+            }
+
+            routes.retain_used();
+            ui.ctx().data_mut(|d| d.insert_temp(routes_id, routes));
+
+            let wires_time = wires_timer.and_then(|timer| timer.elapsed_ms());
+            let drawn_wires = wire_geometries.len();
+
+            // Shared, so `Snarl::wire_path` does not clone geometry of all wires.
+            ui.ctx().data_mut(|d| {
+                d.insert_temp(snarl_id.with("wire-geometries"), Arc::new(wire_geometries));
+            });
+
+            let wire_cache_stats = wire_cache
+                .as_ref()
+                .map(|wire_cache| (wire_cache.hits, wire_cache.misses));
+
+            if let Some(mut wire_cache) = wire_cache {
+                wire_cache.retain_used();
+                ui.ctx()
+                    .data_mut(|d| d.insert_temp(wire_cache_id, wire_cache));
+            }
+
+            wire_shapes.append(&mut highlighted_wire_shapes);
+            wire_shapes.append(&mut selected_wire_shapes);
+            wire_shapes.append(&mut hovered_wire_shapes);
+
+            if let Some(wire) = hovered_wire {
+                if let Some(text) = viewer.wire_tooltip(&wire.out_pin, &wire.in_pin, self) {
+                    // Id is unique per wire, so tooltip is reset when another wire is hovered.
+                    let mut tooltip_r = wire_menu_r.clone();
+                    tooltip_r.id = snarl_id.with(("wire-tooltip", wire));
+                    tooltip_r.on_hover_text(text);
+                }
             }
 
             //Remove hovered wire by second click
@@ -848,6 +3350,18 @@ impl<T> Snarl<T> {
                 }
             }
 
+            if let (Some(wire), Some(pos)) = (hovered_wire, wire_double_click) {
+                if self.wires.contains(&wire) {
+                    viewer.on_wire_double_click(
+                        wire.out_pin,
+                        wire.in_pin,
+                        snarl_state.screen_pos_to_graph(pos, viewport),
+                        ui,
+                        self,
+                    );
+                }
+            }
+
             //<<<<<<< HEAD
             if bg_r.drag_started_by(PointerButton::Primary) {
                 let screen_pos = input.interact_pos.unwrap_or(viewport.center());
@@ -856,7 +3370,11 @@ impl<T> Snarl<T> {
                 //                let screen_pos = input.interact_pos.unwrap_or_else(|| viewport.center());
                 //>>>>>>> b17ce7513987729f1ebe62ed03ca60bd74dcbac2
                 let graph_pos = snarl_state.screen_pos_to_graph(screen_pos, viewport);
-                snarl_state.start_rect_selection(graph_pos);
+                if input.modifiers.alt {
+                    snarl_state.start_lasso_selection(graph_pos);
+                } else {
+                    snarl_state.start_rect_selection(graph_pos);
+                }
             }
 
             if bg_r.dragged_by(PointerButton::Primary) {
@@ -865,6 +3383,14 @@ impl<T> Snarl<T> {
                     let graph_pos = snarl_state.screen_pos_to_graph(screen_pos, viewport);
                     snarl_state.update_rect_selection(graph_pos);
                 }
+
+                if let Some(screen_pos) = input.hover_pos {
+                    if snarl_state.is_lasso_selection() {
+                        let graph_pos = snarl_state.screen_pos_to_graph(screen_pos, viewport);
+                        let min_distance = LASSO_MIN_DISTANCE / snarl_state.scale();
+                        snarl_state.update_lasso_selection(graph_pos, min_distance);
+                    }
+                }
             }
 
             if bg_r.dragged_by(PointerButton::Secondary) {
@@ -873,8 +3399,9 @@ impl<T> Snarl<T> {
 
             if bg_r.drag_stopped_by(PointerButton::Primary) {
                 if let Some(select_rect) = snarl_state.rect_selection() {
-                    let select_nodes = node_rects.into_iter().filter_map(|(id, rect)| {
-                        let select = if style.get_select_rect_contained() {
+                    let contained = rect_selection_contains(style, &snarl_state);
+                    let select_nodes = node_rects.iter().filter_map(|&(id, rect)| {
+                        let select = if contained {
                             select_rect.contains_rect(rect)
                         } else {
                             select_rect.intersects(rect)
@@ -895,16 +3422,69 @@ impl<T> Snarl<T> {
 
                     snarl_state.stop_rect_selection();
                 }
+
+                if let Some(lasso) = snarl_state.lasso_selection() {
+                    let select_nodes = node_rects
+                        .iter()
+                        .filter(|(_, rect)| lasso_contains(lasso, rect.center()))
+                        .map(|&(id, _)| id)
+                        .collect::<Vec<_>>();
+
+                    if input.modifiers.command {
+                        snarl_state.deselect_many_nodes(select_nodes.into_iter());
+                    } else {
+                        snarl_state
+                            .select_many_nodes(!input.modifiers.shift, select_nodes.into_iter());
+                    }
+
+                    snarl_state.stop_lasso_selection();
+                }
+            }
+
+            if let Some(lasso) = snarl_state.lasso_selection() {
+                let points = lasso
+                    .iter()
+                    .map(|&pos| snarl_state.graph_pos_to_screen(pos, viewport))
+                    .collect::<Vec<_>>();
+
+                ui.painter().add(Shape::closed_line(
+                    points,
+                    style.get_lasso_stroke(snarl_state.scale(), ui.style()),
+                ));
             }
 
             if let Some(select_rect) = snarl_state.rect_selection() {
-                ui.painter().rect(
-                    snarl_state.graph_rect_to_screen(select_rect, viewport),
-                    0.0,
-                    style.get_select_fill(ui.style()),
-                    style.get_select_stroke(snarl_state.scale(), ui.style()),
-                    StrokeKind::Middle,
-                );
+                let rect = snarl_state.graph_rect_to_screen(select_rect, viewport);
+                let stroke = style.get_select_stroke(snarl_state.scale(), ui.style());
+
+                // Crossing selection of directional mode is hinted with dashed stroke.
+                let dashed = style.get_box_select_mode() == BoxSelectMode::Directional
+                    && !rect_selection_contains(style, &snarl_state);
+
+                if dashed {
+                    ui.painter()
+                        .rect_filled(rect, 0.0, style.get_select_fill(ui.style()));
+                    ui.painter().extend(Shape::dashed_line(
+                        &[
+                            rect.left_top(),
+                            rect.right_top(),
+                            rect.right_bottom(),
+                            rect.left_bottom(),
+                            rect.left_top(),
+                        ],
+                        stroke,
+                        SELECT_RECT_DASH,
+                        SELECT_RECT_DASH,
+                    ));
+                } else {
+                    ui.painter().rect(
+                        rect,
+                        0.0,
+                        style.get_select_fill(ui.style()),
+                        stroke,
+                        StrokeKind::Middle,
+                    );
+                }
             }
 
             // If right button is clicked while new wire is being dragged, cancel it.
@@ -920,6 +3500,52 @@ impl<T> Snarl<T> {
                 bg_r.flags &= !Flags::CLICKED;
             }
 
+            // Cancel any drag in progress with Escape, rolling back changes made by it.
+            if input.escape_pressed {
+                let origin = snarl_state.take_drag_origin();
+                let mut canceled = false;
+
+                if !snarl_state.is_link_menu_open() && snarl_state.take_wires().is_some() {
+                    for (out_pin, in_pin) in origin.wires {
+                        if self.nodes.contains(out_pin.node.0) && self.nodes.contains(in_pin.node.0)
+                        {
+                            self.connect(out_pin, in_pin);
+                        }
+                    }
+                    canceled = true;
+                }
+
+                if snarl_state.is_rect_selection() {
+                    snarl_state.stop_rect_selection();
+                    canceled = true;
+                }
+
+                if snarl_state.is_lasso_selection() {
+                    snarl_state.stop_lasso_selection();
+                    canceled = true;
+                }
+
+                if snarl_state.pin_reorder().is_some() {
+                    snarl_state.stop_pin_reorder();
+                    canceled = true;
+                }
+
+                if node_moved.take().is_some() {
+                    for (node, pos) in origin.nodes {
+                        if let Some(node) = self.nodes.get_mut(node.0) {
+                            node.pos = pos;
+                        }
+                    }
+                    canceled = true;
+                }
+
+                if canceled {
+                    // Stop dragging so that drag does not continue until pointer is released.
+                    ui.ctx().stop_dragging();
+                    ui.ctx().request_repaint();
+                }
+            }
+
             // Do centering unless no nodes are present.
             if style.get_centering() && bg_r.double_clicked() && centers_weight > 0 {
                 #[allow(clippy::cast_precision_loss)]
@@ -934,7 +3560,12 @@ impl<T> Snarl<T> {
             }
 
             // Wire end position will be overridden when link graph menu is opened.
-            let mut wire_end_pos = input.hover_pos.unwrap_or_default();
+            let mut wire_end_pos = if style.get_drag_wire_magnet() {
+                magnet_wire_end(ui.ctx(), snarl_id, snapped_pos, input.hover_pos)
+            } else {
+                snapped_pos.or(input.hover_pos)
+            }
+            .unwrap_or_default();
 
             if drag_released {
                 let new_wires = snarl_state.take_wires();
@@ -942,22 +3573,29 @@ impl<T> Snarl<T> {
                     ui.ctx().request_repaint();
                 }
                 match (new_wires, pin_hovered) {
+                    (Some(_), Some(_)) if pin_locked => {}
                     (Some(NewWires::In(in_pins)), Some(AnyPin::Out(out_pin))) => {
                         for in_pin in in_pins {
-                            viewer.connect(
-                                &OutPin::new(self, out_pin),
-                                &InPin::new(self, in_pin),
-                                self,
-                            );
+                            let from = OutPin::new(self, out_pin);
+                            let to = InPin::new(self, in_pin);
+                            if self.check_rules(out_pin, in_pin, style).is_err()
+                                || !viewer.can_connect(&from, &to, self)
+                            {
+                                continue;
+                            }
+                            viewer.connect(&from, &to, self);
                         }
                     }
                     (Some(NewWires::Out(out_pins)), Some(AnyPin::In(in_pin))) => {
                         for out_pin in out_pins {
-                            viewer.connect(
-                                &OutPin::new(self, out_pin),
-                                &InPin::new(self, in_pin),
-                                self,
-                            );
+                            let from = OutPin::new(self, out_pin);
+                            let to = InPin::new(self, in_pin);
+                            if self.check_rules(out_pin, in_pin, style).is_err()
+                                || !viewer.can_connect(&from, &to, self)
+                            {
+                                continue;
+                            }
+                            viewer.connect(&from, &to, self);
                         }
                     }
                     (Some(new_wires), None) if bg_r.hovered() => {
@@ -974,6 +3612,21 @@ impl<T> Snarl<T> {
                 }
             }
 
+            // Payload of egui's drag-and-drop, e.g. from a node palette, dropped over the graph.
+            if hovered
+                && innermost
+                && egui::DragAndDrop::has_any_payload(ui.ctx())
+                && ui.input(|i| i.pointer.any_released())
+            {
+                if let Some(hover_pos) = input.hover_pos {
+                    viewer.on_drop_payload(
+                        snarl_state.screen_pos_to_graph(hover_pos, viewport),
+                        ui,
+                        self,
+                    );
+                }
+            }
+
             // Open graph menu when right-clicking on empty space.
             let mut is_menu_visible = false;
 
@@ -999,6 +3652,8 @@ impl<T> Snarl<T> {
 
                             wire_end_pos = ui.cursor().min;
 
+                            let vacant = self.nodes.vacant_key();
+
                             // The context menu is opened as *link* graph menu.
                             viewer.show_dropped_wire_menu(
                                 snarl_state.screen_pos_to_graph(ui.cursor().min, viewport),
@@ -1007,6 +3662,10 @@ impl<T> Snarl<T> {
                                 pins,
                                 self,
                             );
+
+                            if style.get_focus_new_node() && self.nodes.contains(vacant) {
+                                snarl_state.set_focus_node(Some((NodeId(vacant), false)));
+                            }
                         });
                     }
                 } else if snarl_state.is_link_menu_open()
@@ -1019,14 +3678,42 @@ impl<T> Snarl<T> {
                             snarl_state.open_link_menu();
                         }
 
+                        let vacant = self.nodes.vacant_key();
+
                         viewer.show_graph_menu(
                             snarl_state.screen_pos_to_graph(ui.cursor().min, viewport),
                             ui,
                             snarl_state.scale(),
                             self,
                         );
+
+                        if style.get_focus_new_node() && self.nodes.contains(vacant) {
+                            snarl_state.set_focus_node(Some((NodeId(vacant), false)));
+                        }
+                    });
+                }
+            }
+
+            if !wire_menu_clicked {
+                wire_menu_r.flags &= !Flags::CLICKED;
+            }
+
+            if let Some((from, to)) = snarl_state.wire_menu() {
+                let mut is_wire_menu_visible = false;
+
+                if self.wires.contains(&Wire {
+                    out_pin: from,
+                    in_pin: to,
+                }) {
+                    wire_menu_r.context_menu(|ui| {
+                        is_wire_menu_visible = true;
+                        viewer.show_wire_menu(from, to, ui, snarl_state.scale(), self);
                     });
                 }
+
+                if !is_wire_menu_visible && !wire_menu_clicked {
+                    snarl_state.close_wire_menu();
+                }
             }
 
             if !is_menu_visible && snarl_state.is_link_menu_open() {
@@ -1034,25 +3721,100 @@ impl<T> Snarl<T> {
                 snarl_state.close_link_menu();
             }
 
+            // Wires that would be rejected if dropped on hovered pin
+            // are drawn with error color.
+            let rejected_color = ui.visuals().error_fg_color;
+
+            // Dragged wires may be styled differently from connected ones.
+            let drag_stroke = style.get_drag_wire_stroke(snarl_state.scale());
+            let drag_wire_width = drag_stroke.map_or(wire_width, |stroke| stroke.width);
+            let drag_wire_style = style.get_drag_wire_style(snarl_state.scale());
+
             match snarl_state.new_wires() {
-                None => {}
+                None => {
+                    // Hovered output previews wire to the nearest input it can connect to.
+                    let preview_from = match pointer_pin {
+                        Some(AnyPin::Out(out_pin)) if style.get_preview_connection_on_hover() => {
+                            output_info
+                                .get(&out_pin)
+                                .filter(|r| r.interactive)
+                                .map(|r| (out_pin, r))
+                        }
+                        _ => None,
+                    };
+
+                    if let Some((out_pin, from_r)) = preview_from {
+                        let preview_wires = NewWires::Out(vec![out_pin]);
+                        let cycles = self.new_wires_cycles(&preview_wires, style);
+                        let target = self.nearest_compatible_pin(
+                            viewer,
+                            &preview_wires,
+                            &cycles,
+                            &input_info,
+                            &output_info,
+                            style,
+                            from_r.pos,
+                            |node, pos| node != out_pin.node && viewport.contains(pos),
+                        );
+
+                        if let Some((AnyPin::In(in_pin), to_pos)) = target {
+                            if !self.has_wire(out_pin, in_pin) {
+                                draw_wire(
+                                    ui,
+                                    &mut wire_shapes,
+                                    wire_frame_size,
+                                    style.get_upscale_wire_frame(),
+                                    style.get_downscale_wire_frame(),
+                                    vertical_wires,
+                                    from_r.pos + pin_gap,
+                                    to_pos - pin_gap,
+                                    Stroke::new(
+                                        drag_wire_width * 0.5,
+                                        from_r.pin_color.gamma_multiply(0.3),
+                                    ),
+                                    from_r
+                                        .wire_style
+                                        .zoomed(snarl_state.scale())
+                                        .unwrap_or_else(|| snarl_state.wire_style(style)),
+                                );
+                            }
+                        }
+                    }
+                }
                 Some(NewWires::In(pins)) => {
                     for pin in pins {
                         let from_pos = wire_end_pos;
                         let to_r = &input_info[pin];
 
+                        let color = match pin_hovered {
+                            Some(AnyPin::Out(out_pin))
+                                if !self.accepts_new_wire(
+                                    viewer,
+                                    &new_wires_cycles,
+                                    out_pin,
+                                    *pin,
+                                    style,
+                                ) =>
+                            {
+                                rejected_color
+                            }
+                            _ if body_rejected || pin_locked => rejected_color,
+                            _ => drag_stroke.map_or(to_r.pin_color, |stroke| stroke.color),
+                        };
+
                         draw_wire(
                             ui,
                             &mut wire_shapes,
                             wire_frame_size,
                             style.get_upscale_wire_frame(),
                             style.get_downscale_wire_frame(),
+                            vertical_wires,
                             from_pos,
-                            to_r.pos,
-                            Stroke::new(wire_width, to_r.pin_color),
-                            to_r.wire_style
-                                .zoomed(snarl_state.scale())
-                                .unwrap_or_else(|| style.get_wire_style(snarl_state.scale())),
+                            to_r.pos - pin_gap,
+                            Stroke::new(drag_wire_width, color),
+                            drag_wire_style
+                                .or_else(|| to_r.wire_style.zoomed(snarl_state.scale()))
+                                .unwrap_or_else(|| snarl_state.wire_style(style)),
                         );
                     }
                 }
@@ -1061,30 +3823,54 @@ impl<T> Snarl<T> {
                         let from_r = &output_info[pin];
                         let to_pos = wire_end_pos;
 
+                        let color = match pin_hovered {
+                            Some(AnyPin::In(in_pin))
+                                if !self.accepts_new_wire(
+                                    viewer,
+                                    &new_wires_cycles,
+                                    *pin,
+                                    in_pin,
+                                    style,
+                                ) =>
+                            {
+                                rejected_color
+                            }
+                            _ if body_rejected || pin_locked => rejected_color,
+                            _ => drag_stroke.map_or(from_r.pin_color, |stroke| stroke.color),
+                        };
+
                         draw_wire(
                             ui,
                             &mut wire_shapes,
                             wire_frame_size,
                             style.get_upscale_wire_frame(),
                             style.get_downscale_wire_frame(),
-                            from_r.pos,
+                            vertical_wires,
+                            from_r.pos + pin_gap,
                             to_pos,
-                            Stroke::new(wire_width, from_r.pin_color),
-                            from_r
-                                .wire_style
-                                .zoomed(snarl_state.scale())
-                                .unwrap_or_else(|| style.get_wire_style(snarl_state.scale())),
+                            Stroke::new(drag_wire_width, color),
+                            drag_wire_style
+                                .or_else(|| from_r.wire_style.zoomed(snarl_state.scale()))
+                                .unwrap_or_else(|| snarl_state.wire_style(style)),
                         );
                     }
                 }
             }
 
+            let wire_shapes = match style.get_wire_feathering() {
+                Some(feathering) => tessellate_wires(ui, wire_shapes, feathering),
+                None => Shape::Vec(wire_shapes),
+            };
+
+            // Labels are drawn right above wires.
+            let wire_shapes = Shape::Vec(vec![wire_shapes, Shape::Vec(wire_label_shapes)]);
+
             match wire_shape_idx {
                 None => {
-                    ui.painter().add(Shape::Vec(wire_shapes));
+                    ui.painter().add(wire_shapes);
                 }
                 Some(idx) => {
-                    ui.painter().set(idx, Shape::Vec(wire_shapes));
+                    ui.painter().set(idx, wire_shapes);
                 }
             }
 
@@ -1097,23 +3883,304 @@ impl<T> Snarl<T> {
                 }
             }
 
+            // Pan the view when dragging close to the viewport's edge.
+            let dragging = ui.input(|i| i.pointer.primary_down())
+                && (node_moved.is_some()
+                    || snarl_state.has_new_wires()
+                    || snarl_state.is_rect_selection()
+                    || snarl_state.is_lasso_selection());
+
+            if dragging {
+                if let Some(hover_pos) = input.hover_pos {
+                    let pan = edge_pan(hover_pos, viewport, style.get_edge_pan_margin())
+                        * style.get_edge_pan_speed()
+                        * input.stable_dt;
+
+                    if pan != Vec2::ZERO {
+                        snarl_state.pan(pan);
+
+                        // Keep dragged nodes under the pointer.
+                        if let Some((_, delta)) = &mut node_moved {
+                            *delta += snarl_state.screen_vec_to_graph(pan);
+                        }
+
+                        ui.ctx().request_repaint();
+                    }
+                }
+            }
+
+            // Snap dragged node to other nodes.
+            let mut guide_snap = Vec2::ZERO;
+
+            if let Some((node, delta)) = &mut node_moved {
+                if style.get_smart_guides() && !input.modifiers.alt {
+                    let moving = if snarl_state.selected_nodes().contains(node) {
+                        snarl_state.selected_nodes()
+                    } else {
+                        std::slice::from_ref(node)
+                    };
+
+                    let graph_viewport = snarl_state.screen_rect_to_graph(viewport, viewport);
+
+                    let others = node_rects
+                        .iter()
+                        .filter(|(id, rect)| {
+                            !moving.contains(id) && graph_viewport.intersects(*rect)
+                        })
+                        .map(|&(_, rect)| rect)
+                        .collect::<Vec<_>>();
+
+                    if let Some(&(_, rect)) = node_rects.iter().find(|(id, _)| *id == *node) {
+                        // Position of the node as if it was not snapped.
+                        let free_delta = *delta - snarl_state.guide_snap();
+                        let threshold = GUIDE_SNAP_DISTANCE / snarl_state.scale();
+
+                        let (snap, guides) =
+                            snap_to_guides(rect.translate(free_delta), &others, threshold);
+
+                        *delta = free_delta + snap;
+                        guide_snap = snap;
+
+                        let stroke = style.get_guide_stroke(ui.style());
+                        for [a, b] in guides.into_iter().flatten() {
+                            ui.painter().line_segment(
+                                [
+                                    snarl_state.graph_pos_to_screen(a, viewport),
+                                    snarl_state.graph_pos_to_screen(b, viewport),
+                                ],
+                                stroke,
+                            );
+                        }
+                    }
+                }
+            }
+
+            snarl_state.set_guide_snap(guide_snap);
+            snarl_state.set_hovered_node(hovered_node);
+
+            // Mark grid node where dragged node will land.
+            if let (Some(grid), Some((node, delta))) = (style.get_snap_grid(), node_moved) {
+                if let Some(node) = self.nodes.get(node.0) {
+                    let target = grid.snap(node.pos + delta, style.get_pattern_origin(self));
+                    let arm = grid.spacing.min_elem().max(1.0) * 0.25;
+                    let rot = Rot2::from_angle(grid.angle);
+                    let stroke = style.get_snap_indicator_stroke(snarl_state.scale(), ui.style());
+
+                    for dir in [vec2(arm, 0.0), vec2(0.0, arm)] {
+                        let dir = rot * dir;
+                        ui.painter().line_segment(
+                            [
+                                snarl_state.graph_pos_to_screen(target - dir, viewport),
+                                snarl_state.graph_pos_to_screen(target + dir, viewport),
+                            ],
+                            stroke,
+                        );
+                    }
+                }
+            }
+
+            // Snap dropped node to the grid, moving the rest of dragged nodes with it.
+            // Non-interactive nodes are not in the drag and stay in place.
+            if let (Some(grid), Some(node)) = (style.get_snap_grid(), node_dropped) {
+                let moving = snarl_state
+                    .drag_origin_nodes()
+                    .iter()
+                    .map(|&(id, _)| id)
+                    .collect::<Vec<_>>();
+
+                if let Some(pos) = self
+                    .nodes
+                    .get(node.0)
+                    .filter(|_| moving.contains(&node))
+                    .map(|node| node.pos)
+                {
+                    let delta = grid.snap(pos, style.get_pattern_origin(self)) - pos;
+
+                    snarl_state.advance_node_drag(delta);
+                    self.move_nodes(&moving, delta);
+                    ui.ctx().request_repaint();
+                }
+            }
+
+            if node_dropped.is_some() {
+                let moved = snarl_state
+                    .drag_origin_nodes()
+                    .iter()
+                    .copied()
+                    .filter(|(id, from)| self.nodes.get(id.0).is_some_and(|node| node.pos != *from))
+                    .collect::<Vec<_>>();
+
+                if !moved.is_empty() {
+                    viewer.on_nodes_moved(&moved, self);
+                }
+            }
+
+            // Pin reorder drag ends when pointer is released, even if not dropped on its node.
+            if !ui.input(|i| i.pointer.primary_down()) {
+                snarl_state.stop_pin_reorder();
+            }
+
+            self.wire_drag = None;
+
             if let Some((node, delta)) = node_moved {
                 if self.nodes.contains(node.0) {
                     ui.ctx().request_repaint();
-                    if snarl_state.selected_nodes().contains(&node) {
-                        for node in snarl_state.selected_nodes() {
-                            let node = &mut self.nodes[node.0];
-                            node.pos += delta;
-                        }
+
+                    // Non-interactive nodes stay in place when moved with selection.
+                    let moving = if snarl_state.selected_nodes().contains(&node) {
+                        snarl_state
+                            .selected_nodes()
+                            .iter()
+                            .copied()
+                            .filter(|&id| viewer.node_interactive(id, self))
+                            .collect()
                     } else {
-                        let node = &mut self.nodes[node.0];
-                        node.pos += delta;
-                    }
+                        vec![node]
+                    };
+
+                    snarl_state.record_drag_origin(
+                        moving
+                            .iter()
+                            .filter_map(|id| Some((*id, self.nodes.get(id.0)?.pos))),
+                    );
+
+                    // Nodes are placed relative to their positions at drag start
+                    // so that per-frame rounding does not accumulate.
+                    let total = snarl_state.advance_node_drag(delta);
+                    self.stop_layout_animation(&moving);
+                    self.place_nodes(snarl_state.drag_origin_nodes(), total);
+                }
+            } else {
+                snarl_state.clear_drag_origin_nodes();
+            }
+
+            let decay = input.stable_dt * style.get_wire_activity_decay();
+            if snarl_state.decay_wire_activity(decay) {
+                ui.ctx().request_repaint();
+            }
+
+            if scroll_pans && pan_after_nodes && pan_by_scroll(ui, &mut snarl_state) {
+                // Nodes were shown with previous offset.
+                ui.ctx().request_repaint();
+            }
+
+            if ui.rect_contains_pointer(viewport) {
+                cursor_graph_pos = input
+                    .hover_pos
+                    .map(|pos| snarl_state.screen_pos_to_graph(pos, viewport));
+
+                hover_target = Some(match (pointer_pin, hovered_node, hovered_wire) {
+                    (Some(AnyPin::In(pin)), _, _) => HoverTarget::InPin(pin),
+                    (Some(AnyPin::Out(pin)), _, _) => HoverTarget::OutPin(pin),
+                    (None, Some(node), _) => HoverTarget::Node(node),
+                    (None, None, Some(wire)) => HoverTarget::Wire(wire.out_pin, wire.in_pin),
+                    (None, None, None) => HoverTarget::Background,
+                });
+            }
+
+            if style.get_debug_overlay() {
+                let visible_nodes = node_rects
+                    .iter()
+                    .filter(|(_, rect)| rect.intersects(viewport))
+                    .count();
+                let time = |ms: Option<f32>| {
+                    ms.map_or_else(|| "n/a".to_owned(), |ms| format!("{ms:.2} ms"))
+                };
+
+                let mut text = [
+                    format!("nodes: {visible_nodes} / {}", self.nodes.len()),
+                    format!("wires: {drawn_wires} / {}", self.wires.len()),
+                    format!("nodes time: {}", time(nodes_time)),
+                    format!("wires time: {}", time(wires_time)),
+                ]
+                .join("\n");
+                if let Some((hits, misses)) = wire_cache_stats {
+                    text.push_str(&format!("\nwire cache: {hits} hits, {misses} misses"));
                 }
+                draw_debug_overlay(ui, viewport, text);
+            }
+
+            let graph_viewport = snarl_state.screen_rect_to_graph(viewport, viewport);
+            let prev_viewport = ui.ctx().data_mut(|d| {
+                let id = snarl_id.with("graph-viewport");
+                let prev = d.get_temp::<Rect>(id);
+                d.insert_temp(id, graph_viewport);
+                prev
+            });
+            if prev_viewport != Some(graph_viewport) {
+                viewport_changed = Some(graph_viewport);
             }
 
             snarl_state.store(self, ui.ctx());
         });
+
+        SnarlResponse {
+            changed: self.fingerprint() != fingerprint,
+            cursor_graph_pos,
+            viewport_changed,
+            graph_bounds: self.graph_bounds(ui.ctx(), snarl_id),
+            hovered: hover_target,
+        }
+    }
+
+    /// Returns bounding rect of all nodes in graph space,
+    /// using sizes from their last layout.
+    fn graph_bounds(&self, cx: &Context, snarl_id: Id) -> Option<Rect> {
+        self.nodes_bounds(cx, snarl_id, self.nodes.iter().map(|(idx, _)| NodeId(idx)))
+    }
+
+    /// Returns bounding rect of given nodes in graph space,
+    /// using sizes from their last layout.
+    ///
+    /// Removed nodes are skipped.
+    fn nodes_bounds(
+        &self,
+        cx: &Context,
+        snarl_id: Id,
+        nodes: impl Iterator<Item = NodeId>,
+    ) -> Option<Rect> {
+        let mut bounds = None;
+        for id in nodes {
+            let Some(node) = self.nodes.get(id.0) else {
+                continue;
+            };
+            let rect = NodeState::stored_graph_rect(
+                cx,
+                snarl_id.with(("snarl-node", id)),
+                node.pos,
+                node.open,
+            )
+            .unwrap_or_else(|| Rect::from_min_size(node.pos, Vec2::ZERO));
+            bounds = Some(bounds.map_or(rect, |bounds: Rect| bounds.union(rect)));
+        }
+        bounds
+    }
+
+    /// Hashes positions and sizes of nodes and set of wires.
+    fn fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+
+        self.nodes.len().hash(&mut hasher);
+        for (idx, node) in &self.nodes {
+            idx.hash(&mut hasher);
+            node.pos.x.to_bits().hash(&mut hasher);
+            node.pos.y.to_bits().hash(&mut hasher);
+            node.open.hash(&mut hasher);
+            node.size
+                .map(|size| (size.x.to_bits(), size.y.to_bits()))
+                .hash(&mut hasher);
+        }
+
+        // Wires are not ordered, so their hashes are combined commutatively.
+        self.wires.len().hash(&mut hasher);
+        let wires = self.wires.iter().fold(0u64, |acc, wire| {
+            let mut hasher = DefaultHasher::new();
+            wire.hash(&mut hasher);
+            acc.wrapping_add(hasher.finish())
+        });
+        wires.hash(&mut hasher);
+
+        hasher.finish()
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -1121,6 +4188,7 @@ impl<T> Snarl<T> {
         &mut self,
         viewer: &mut V,
         node: NodeId,
+        interactive: bool,
         inputs: &[InPin],
         pin_size: f32,
         style: &SnarlStyle,
@@ -1128,12 +4196,15 @@ impl<T> Snarl<T> {
         inputs_rect: Rect,
         clip_rect: Rect,
         viewport: Rect,
+        node_rect: Rect,
         input_x: f32,
         min_pin_y: f32,
         input_spacing: Option<f32>,
+        pins_row_y: Option<f32>,
         snarl_state: &mut SnarlState,
         input: &Input,
         input_positions: &mut HashMap<InPinId, PinResponse>,
+        new_wires_cycles: &NewWiresCycles,
     ) -> DrawPinsResponse
     where
         V: SnarlViewer<T>,
@@ -1141,49 +4212,108 @@ impl<T> Snarl<T> {
         let mut drag_released = false;
         let mut pin_hovered = None;
 
-        // Input pins on the left.
+        // Input pins on the left, or in a row on the top edge.
+        let inputs_layout = match pins_row_y {
+            None => Layout::top_down(Align::Min),
+            Some(_) => Layout::left_to_right(Align::Min),
+        };
+
         let inputs_ui = &mut ui.new_child(
             UiBuilder::new()
                 .max_rect(inputs_rect)
-                .layout(Layout::top_down(Align::Min))
+                .layout(inputs_layout)
                 .id_salt("inputs"),
         );
 
         inputs_ui.set_clip_rect(clip_rect.intersect(viewport));
 
+        // Content shown outside is aligned to the edge beyond both the pins and the node frame.
+        let label_x = match (style.get_pin_label_placement(), pins_row_y) {
+            (PinLabelPlacement::Outside, None) => Some(
+                f32::min(pin_size.mul_add(-0.5, input_x), node_rect.left())
+                    - ui.spacing().item_spacing.x,
+            ),
+            _ => None,
+        };
+
+        let hit_radius = style.get_pin_hit_radius(snarl_state.scale(), pin_size);
+        let mut prev_pin_y = None;
+
+        // Pins' positions for reordering.
+        let mut pin_ys = Vec::new();
+        let mut reorder_released = false;
+
         for in_pin in inputs {
             // Show input pin.
             inputs_ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
-                if let Some(input_spacing) = input_spacing {
+                if let (Some(input_spacing), None) = (input_spacing, pins_row_y) {
                     ui.allocate_space(vec2(input_spacing, pin_size));
                 }
 
+                let x0 = ui.cursor().min.x;
                 let y0 = ui.cursor().min.y;
 
                 // Show input content
-                let pin_info = viewer.show_input(in_pin, ui, snarl_state.scale(), self);
+                let pin_info = match label_x {
+                    Some(label_x) => {
+                        let mut label_ui = ui.new_child(
+                            UiBuilder::new()
+                                .max_rect(
+                                    Rect::everything_left_of(label_x)
+                                        .intersect(Rect::everything_below(y0)),
+                                )
+                                .layout(Layout::right_to_left(Align::Min))
+                                .id_salt(("outside-label", in_pin.id)),
+                        );
+                        label_ui.set_clip_rect(viewport);
+
+                        let pin_info =
+                            viewer.show_input(in_pin, &mut label_ui, snarl_state.scale(), self);
+
+                        // Row keeps height of the content, but not its width.
+                        ui.allocate_space(vec2(0.0, label_ui.min_rect().height()));
+                        pin_info
+                    }
+                    None => viewer.show_input(in_pin, ui, snarl_state.scale(), self),
+                };
                 if !self.nodes.contains(node.0) {
                     // If removed
                     return;
                 }
 
+                let x1 = ui.min_rect().max.x;
                 let y1 = ui.min_rect().max.y;
 
                 // ui.end_row();
 
-                // Centered vertically.
-                let y = min_pin_y.max((y0 + y1) * 0.5);
-
-                let pin_pos = pos2(input_x, y);
+                let pin_pos = match pins_row_y {
+                    // Centered horizontally above the content.
+                    Some(row_y) => pos2((x0 + x1) * 0.5, row_y),
+                    // Centered vertically.
+                    None => pos2(input_x, min_pin_y.max((y0 + y1) * 0.5)),
+                };
 
                 // Interact with pin shape.
                 ui.set_clip_rect(viewport);
 
-                let r = ui.interact(
-                    Rect::from_center_size(pin_pos, vec2(pin_size, pin_size)),
-                    ui.next_auto_id(),
-                    Sense::click_and_drag(),
-                );
+                // Pin placed by the viewer does not limit hit rect of the next one.
+                let hit_rect = match viewer.pin_anchor(AnyPin::In(in_pin.id), node_rect, self) {
+                    Some(anchor) => pin_hit_rect(anchor, hit_radius, None),
+                    None => pin_hit_rect(pin_pos, hit_radius, prev_pin_y),
+                };
+
+                // Pins of non-interactive node only track hover to reject dropped wires.
+                let sense = if interactive {
+                    Sense::click_and_drag()
+                } else {
+                    Sense::hover()
+                };
+                let r = ui.interact(hit_rect, ui.next_auto_id(), sense);
+
+                if pins_row_y.is_none() {
+                    prev_pin_y = Some(pin_pos.y);
+                    pin_ys.push(pin_pos.y);
+                }
 
                 ui.skip_ahead_auto_ids(1);
 
@@ -1199,9 +4329,14 @@ impl<T> Snarl<T> {
                     }
                 }
                 if r.drag_started_by(PointerButton::Primary) {
-                    if input.modifiers.command {
+                    if style.get_pin_reorder() && pins_row_y.is_none() && input.modifiers.alt {
+                        snarl_state.start_pin_reorder(AnyPin::In(in_pin.id));
+                    } else if input.modifiers.command {
                         snarl_state.start_new_wires_out(&in_pin.remotes);
                         if !input.modifiers.shift {
+                            snarl_state.set_detached_wires(
+                                in_pin.remotes.iter().map(|&out| (out, in_pin.id)).collect(),
+                            );
                             self.drop_inputs(in_pin.id);
                             if !self.nodes.contains(node.0) {
                                 // If removed
@@ -1212,15 +4347,46 @@ impl<T> Snarl<T> {
                         snarl_state.start_new_wire_in(in_pin.id);
                     }
                 }
+
+                // Fast drag away from the node severs wires of the pin
+                // instead of dragging new wire from it.
+                let velocity = match pins_row_y {
+                    None => input.pointer_velocity,
+                    Some(_) => vec2(input.pointer_velocity.y, input.pointer_velocity.x),
+                };
+                let flicked = style.get_flick_disconnect()
+                    && !in_pin.remotes.is_empty()
+                    && r.dragged_by(PointerButton::Primary)
+                    && is_flick(velocity, -1.0)
+                    && matches!(
+                        snarl_state.new_wires(),
+                        Some(NewWires::In(pins)) if pins[..] == [in_pin.id]
+                    );
+
+                if flicked {
+                    let _ = snarl_state.take_wires();
+                    for &out_pin in &in_pin.remotes {
+                        let out_pin = OutPin::new(self, out_pin);
+                        viewer.disconnect(&out_pin, in_pin, self);
+                    }
+                    ui.ctx().request_repaint();
+
+                    if !self.nodes.contains(node.0) {
+                        // If removed
+                        return;
+                    }
+                }
+
                 if r.drag_stopped() {
                     drag_released = true;
+                    reorder_released |= snarl_state.pin_reorder() == Some(AnyPin::In(in_pin.id));
                 }
 
                 let mut visual_pin_size = pin_size;
 
                 match input.hover_pos {
                     Some(hover_pos) if r.rect.contains(hover_pos) => {
-                        if input.modifiers.shift {
+                        if interactive && input.modifiers.shift {
                             snarl_state.add_new_wire_in(in_pin.id);
                         } else if input.secondary_pressed {
                             snarl_state.remove_new_wire_in(in_pin.id);
@@ -1234,6 +4400,20 @@ impl<T> Snarl<T> {
                 let mut pin_painter = ui.painter().clone();
                 pin_painter.set_clip_rect(viewport);
 
+                if style.get_highlight_compatible_pins() && viewport.contains(r.rect.center()) {
+                    let compatible = snarl_state.new_wires().and_then(|new_wires| {
+                        self.accepts_new_wires(
+                            viewer,
+                            new_wires,
+                            new_wires_cycles,
+                            AnyPin::In(in_pin.id),
+                            style,
+                        )
+                        .map(|ok| ok && interactive)
+                    });
+                    highlight_compatible_pin(compatible, &mut visual_pin_size, &mut pin_painter);
+                }
+
                 let pin_color = viewer.draw_input_pin(
                     in_pin,
                     &pin_info,
@@ -1252,6 +4432,7 @@ impl<T> Snarl<T> {
                         pos: r.rect.center(),
                         pin_color,
                         wire_style: pin_info.wire_style,
+                        interactive,
                     },
                 );
             });
@@ -1260,6 +4441,29 @@ impl<T> Snarl<T> {
         let final_rect = inputs_ui.min_rect();
         ui.expand_to_include_rect(final_rect.intersect(clip_rect));
 
+        if let Some(AnyPin::In(pin)) = snarl_state.pin_reorder() {
+            if pin.node == node {
+                let target = input
+                    .hover_pos
+                    .and_then(|pos| pin_reorder_target(&pin_ys, pin.input, pos.y, pin_size));
+
+                if let Some((to, indicator_y)) = target {
+                    if reorder_released {
+                        snarl_state.stop_pin_reorder();
+                        if to != pin.input {
+                            viewer.reorder_input(node, pin.input, to, self);
+                        }
+                    } else {
+                        ui.painter().hline(
+                            final_rect.x_range(),
+                            indicator_y,
+                            ui.visuals().selection.stroke,
+                        );
+                    }
+                }
+            }
+        }
+
         DrawPinsResponse {
             drag_released,
             pin_hovered,
@@ -1272,6 +4476,7 @@ impl<T> Snarl<T> {
         &mut self,
         viewer: &mut V,
         node: NodeId,
+        interactive: bool,
         outputs: &[OutPin],
         pin_size: f32,
         style: &SnarlStyle,
@@ -1279,12 +4484,15 @@ impl<T> Snarl<T> {
         outputs_rect: Rect,
         clip_rect: Rect,
         viewport: Rect,
+        node_rect: Rect,
         output_x: f32,
         min_pin_y: f32,
         output_spacing: Option<f32>,
+        pins_row_y: Option<f32>,
         snarl_state: &mut SnarlState,
         input: &Input,
         output_positions: &mut HashMap<OutPinId, PinResponse>,
+        new_wires_cycles: &NewWiresCycles,
     ) -> DrawPinsResponse
     where
         V: SnarlViewer<T>,
@@ -1292,49 +4500,115 @@ impl<T> Snarl<T> {
         let mut drag_released = false;
         let mut pin_hovered = None;
 
+        // Output pins on the right, or in a row on the bottom edge.
+        let (outputs_layout, pin_layout) = match pins_row_y {
+            None => (
+                Layout::top_down(Align::Max),
+                Layout::right_to_left(Align::Min),
+            ),
+            Some(_) => (
+                Layout::left_to_right(Align::Min),
+                Layout::left_to_right(Align::Min),
+            ),
+        };
+
         let outputs_ui = &mut ui.new_child(
             UiBuilder::new()
                 .max_rect(outputs_rect)
-                .layout(Layout::top_down(Align::Max))
+                .layout(outputs_layout)
                 .id_salt("outputs"),
         );
 
         outputs_ui.set_clip_rect(clip_rect.intersect(viewport));
 
+        // Content shown outside is aligned to the edge beyond both the pins and the node frame.
+        let label_x = match (style.get_pin_label_placement(), pins_row_y) {
+            (PinLabelPlacement::Outside, None) => Some(
+                f32::max(pin_size.mul_add(0.5, output_x), node_rect.right())
+                    + ui.spacing().item_spacing.x,
+            ),
+            _ => None,
+        };
+
+        let hit_radius = style.get_pin_hit_radius(snarl_state.scale(), pin_size);
+        let mut prev_pin_y = None;
+
+        // Pins' positions for reordering.
+        let mut pin_ys = Vec::new();
+        let mut reorder_released = false;
+
         // Output pins on the right.
         for out_pin in outputs {
             // Show output pin.
-            outputs_ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+            outputs_ui.with_layout(pin_layout, |ui| {
                 // Allocate space for pin shape.
-                if let Some(output_spacing) = output_spacing {
+                if let (Some(output_spacing), None) = (output_spacing, pins_row_y) {
                     ui.allocate_space(vec2(output_spacing, pin_size));
                 }
 
+                let x0 = ui.cursor().min.x;
                 let y0 = ui.cursor().min.y;
 
                 // Show output content
-                let pin_info = viewer.show_output(out_pin, ui, snarl_state.scale(), self);
+                let pin_info = match label_x {
+                    Some(label_x) => {
+                        let mut label_ui = ui.new_child(
+                            UiBuilder::new()
+                                .max_rect(
+                                    Rect::everything_right_of(label_x)
+                                        .intersect(Rect::everything_below(y0)),
+                                )
+                                .layout(Layout::left_to_right(Align::Min))
+                                .id_salt(("outside-label", out_pin.id)),
+                        );
+                        label_ui.set_clip_rect(viewport);
+
+                        let pin_info =
+                            viewer.show_output(out_pin, &mut label_ui, snarl_state.scale(), self);
+
+                        // Row keeps height of the content, but not its width.
+                        ui.allocate_space(vec2(0.0, label_ui.min_rect().height()));
+                        pin_info
+                    }
+                    None => viewer.show_output(out_pin, ui, snarl_state.scale(), self),
+                };
                 if !self.nodes.contains(node.0) {
                     // If removed
                     return;
                 }
 
+                let x1 = ui.min_rect().max.x;
                 let y1 = ui.min_rect().max.y;
 
                 // ui.end_row();
 
-                // Centered vertically.
-                let y = min_pin_y.max((y0 + y1) * 0.5);
-
-                let pin_pos = pos2(output_x, y);
+                let pin_pos = match pins_row_y {
+                    // Centered horizontally below the content.
+                    Some(row_y) => pos2((x0 + x1) * 0.5, row_y),
+                    // Centered vertically.
+                    None => pos2(output_x, min_pin_y.max((y0 + y1) * 0.5)),
+                };
 
                 ui.set_clip_rect(viewport);
 
-                let r = ui.interact(
-                    Rect::from_center_size(pin_pos, vec2(pin_size, pin_size)),
-                    ui.next_auto_id(),
-                    Sense::click_and_drag(),
-                );
+                // Pin placed by the viewer does not limit hit rect of the next one.
+                let hit_rect = match viewer.pin_anchor(AnyPin::Out(out_pin.id), node_rect, self) {
+                    Some(anchor) => pin_hit_rect(anchor, hit_radius, None),
+                    None => pin_hit_rect(pin_pos, hit_radius, prev_pin_y),
+                };
+
+                // Pins of non-interactive node only track hover to reject dropped wires.
+                let sense = if interactive {
+                    Sense::click_and_drag()
+                } else {
+                    Sense::hover()
+                };
+                let r = ui.interact(hit_rect, ui.next_auto_id(), sense);
+
+                if pins_row_y.is_none() {
+                    prev_pin_y = Some(pin_pos.y);
+                    pin_ys.push(pin_pos.y);
+                }
 
                 ui.skip_ahead_auto_ids(1);
 
@@ -1350,10 +4624,19 @@ impl<T> Snarl<T> {
                     }
                 }
                 if r.drag_started_by(PointerButton::Primary) {
-                    if input.modifiers.command {
+                    if style.get_pin_reorder() && pins_row_y.is_none() && input.modifiers.alt {
+                        snarl_state.start_pin_reorder(AnyPin::Out(out_pin.id));
+                    } else if input.modifiers.command {
                         snarl_state.start_new_wires_in(&out_pin.remotes);
 
                         if !input.modifiers.shift {
+                            snarl_state.set_detached_wires(
+                                out_pin
+                                    .remotes
+                                    .iter()
+                                    .map(|&in_| (out_pin.id, in_))
+                                    .collect(),
+                            );
                             self.drop_outputs(out_pin.id);
                             if !self.nodes.contains(node.0) {
                                 // If removed
@@ -1366,12 +4649,13 @@ impl<T> Snarl<T> {
                 }
                 if r.drag_stopped() {
                     drag_released = true;
+                    reorder_released |= snarl_state.pin_reorder() == Some(AnyPin::Out(out_pin.id));
                 }
 
                 let mut visual_pin_size = pin_size;
                 match input.hover_pos {
                     Some(hover_pos) if r.rect.contains(hover_pos) => {
-                        if input.modifiers.shift {
+                        if interactive && input.modifiers.shift {
                             snarl_state.add_new_wire_out(out_pin.id);
                         } else if input.secondary_pressed {
                             snarl_state.remove_new_wire_out(out_pin.id);
@@ -1385,6 +4669,20 @@ impl<T> Snarl<T> {
                 let mut pin_painter = ui.painter().clone();
                 pin_painter.set_clip_rect(viewport);
 
+                if style.get_highlight_compatible_pins() && viewport.contains(r.rect.center()) {
+                    let compatible = snarl_state.new_wires().and_then(|new_wires| {
+                        self.accepts_new_wires(
+                            viewer,
+                            new_wires,
+                            new_wires_cycles,
+                            AnyPin::Out(out_pin.id),
+                            style,
+                        )
+                        .map(|ok| ok && interactive)
+                    });
+                    highlight_compatible_pin(compatible, &mut visual_pin_size, &mut pin_painter);
+                }
+
                 let pin_color = viewer.draw_output_pin(
                     out_pin,
                     &pin_info,
@@ -1403,6 +4701,7 @@ impl<T> Snarl<T> {
                         pos: r.rect.center(),
                         pin_color,
                         wire_style: pin_info.wire_style,
+                        interactive,
                     },
                 );
             });
@@ -1410,6 +4709,29 @@ impl<T> Snarl<T> {
         let final_rect = outputs_ui.min_rect();
         ui.expand_to_include_rect(final_rect.intersect(clip_rect));
 
+        if let Some(AnyPin::Out(pin)) = snarl_state.pin_reorder() {
+            if pin.node == node {
+                let target = input
+                    .hover_pos
+                    .and_then(|pos| pin_reorder_target(&pin_ys, pin.output, pos.y, pin_size));
+
+                if let Some((to, indicator_y)) = target {
+                    if reorder_released {
+                        snarl_state.stop_pin_reorder();
+                        if to != pin.output {
+                            viewer.reorder_output(node, pin.output, to, self);
+                        }
+                    } else {
+                        ui.painter().hline(
+                            final_rect.x_range(),
+                            indicator_y,
+                            ui.visuals().selection.stroke,
+                        );
+                    }
+                }
+            }
+        }
+
         DrawPinsResponse {
             drag_released,
             pin_hovered,
@@ -1429,6 +4751,9 @@ impl<T> Snarl<T> {
         clip_rect: Rect,
         viewport: Rect,
         snarl_state: &SnarlState,
+        style: &SnarlStyle,
+        node_id: Id,
+        scroll: bool,
     ) -> DrawBodyResponse
     where
         V: SnarlViewer<T>,
@@ -1441,19 +4766,87 @@ impl<T> Snarl<T> {
         );
         body_ui.set_clip_rect(clip_rect.intersect(viewport));
 
-        viewer.show_body(
-            node,
-            inputs,
-            outputs,
-            &mut body_ui,
-            snarl_state.scale(),
-            self,
-        );
+        // Scrolled and faded bodies are not cached.
+        let cache_key = if style.get_body_cache() && !scroll && body_ui.opacity() >= 1.0 {
+            viewer.body_cache_key(node, self)
+        } else {
+            None
+        };
+
+        let cached = cache_key.and_then(|key| {
+            BodyCache::load(
+                ui.ctx(),
+                node_id,
+                key,
+                body_rect.size(),
+                snarl_state.scale(),
+            )
+        });
+
+        if let Some(cache) = cached {
+            let delta = body_rect.min - cache.rect.min;
+            body_ui.painter().extend(cache.shapes.iter().map(|shape| {
+                let mut shape = shape.clone();
+                shape.translate(delta);
+                shape
+            }));
+            body_ui.advance_cursor_after_rect(cache.rect.translate(delta));
+
+            let final_rect = body_ui.min_rect();
+            ui.expand_to_include_rect(final_rect.intersect(clip_rect));
+            return DrawBodyResponse { final_rect };
+        }
+
+        let shapes_start = cache_key.map(|_| body_ui.painter().add(Shape::Noop));
+
+        if scroll {
+            // Node is resized, keep body within it and let user scroll to the rest.
+            ScrollArea::both()
+                .max_width(body_rect.width())
+                .max_height(body_rect.height())
+                .auto_shrink([false, true])
+                .show(&mut body_ui, |ui| {
+                    viewer.show_body(node, inputs, outputs, ui, snarl_state.scale(), self);
+                });
+        } else {
+            viewer.show_body(
+                node,
+                inputs,
+                outputs,
+                &mut body_ui,
+                snarl_state.scale(),
+                self,
+            );
+        }
 
         let final_rect = body_ui.min_rect();
         ui.expand_to_include_rect(final_rect.intersect(clip_rect));
         // node_state.set_body_width(body_size.x);
 
+        // Widgets skip painting outside of clip rect, so only fully visible body is recorded.
+        if let (Some(key), Some(shapes_start)) = (cache_key, shapes_start) {
+            if body_ui.clip_rect().contains_rect(final_rect) {
+                let shapes_end = body_ui.painter().add(Shape::Noop);
+                let shapes = ui.ctx().graphics_mut(|g| {
+                    g.entry(body_ui.layer_id())
+                        .all_entries()
+                        .skip(shapes_start.0 + 1)
+                        .take(shapes_end.0 - shapes_start.0 - 1)
+                        .map(|clipped| clipped.shape.clone())
+                        .collect()
+                });
+
+                BodyCache {
+                    key,
+                    max_size: body_rect.size(),
+                    scale: snarl_state.scale(),
+                    rect: final_rect,
+                    shapes,
+                }
+                .store(ui.ctx(), node_id);
+            }
+        }
+
         DrawBodyResponse { final_rect }
     }
 
@@ -1473,6 +4866,10 @@ impl<T> Snarl<T> {
         input_positions: &mut HashMap<InPinId, PinResponse>,
         input: &Input,
         output_positions: &mut HashMap<OutPinId, PinResponse>,
+        opacity: f32,
+        dimmed: bool,
+        in_cycle: bool,
+        new_wires_cycles: &NewWiresCycles,
     ) -> Option<DrawNodeResponse>
     where
         V: SnarlViewer<T>,
@@ -1480,6 +4877,7 @@ impl<T> Snarl<T> {
         let Node {
             pos,
             open,
+            size,
             ref value,
         } = self.nodes[node.0];
 
@@ -1493,23 +4891,66 @@ impl<T> Snarl<T> {
             .map(|idx| InPin::new(self, InPinId { node, input: idx }))
             .collect::<Vec<_>>();
 
-        let outputs = (0..outputs_count)
-            .map(|idx| OutPin::new(self, OutPinId { node, output: idx }))
-            .collect::<Vec<_>>();
+        let outputs = (0..outputs_count)
+            .map(|idx| OutPin::new(self, OutPinId { node, output: idx }))
+            .collect::<Vec<_>>();
+
+        let node_pos = snarl_state.graph_pos_to_screen(pos, viewport);
+
+        // Generate persistent id for the node.
+        let node_id = snarl_id.with(("snarl-node", node));
+
+        // Widgets inside the node derive their ids from stable key if viewer provides one.
+        let node_ui_id = viewer
+            .node_key(value)
+            .map_or(node_id, |key| snarl_id.with(("snarl-node-key", key)));
+
+        let openness = ui.ctx().animate_bool(node_id, open);
+
+        // Node without stored layout appears for the first time and is animated from zero.
+        let spawn = style.get_node_spawn_anim().map_or(1.0, |duration| {
+            let target = if NodeState::stored_graph_size(ui.ctx(), node_id).is_some() {
+                1.0
+            } else {
+                0.0
+            };
+            ui.ctx()
+                .animate_value_with_time(node_id.with("spawn"), target, duration)
+        });
+        let visuals = resolve_node_visuals(
+            NodeVisualState {
+                hovered: snarl_state.hovered_node() == Some(node),
+                selected: snarl_state.selected_nodes().contains(&node),
+                primary: snarl_state.primary_selection() == Some(node),
+                dimmed,
+                in_cycle,
+            },
+            snarl_state.scale(),
+            style,
+            ui.style(),
+        );
+
+        let opacity = opacity * visuals.opacity * spawn;
 
-        let node_pos = snarl_state.graph_pos_to_screen(pos, viewport);
+        // Shapes of the node are scaled by spawn animation after it is drawn.
+        let shapes_start = ui.painter().add(Shape::Noop);
 
-        // Generate persistent id for the node.
-        let node_id = snarl_id.with(("snarl-node", node));
+        let mut node_state = NodeState::load(ui.ctx(), node_id, ui.spacing(), snarl_state.scale());
 
-        let openness = ui.ctx().animate_bool(node_id, open);
+        let aspect_ratio = viewer.node_aspect_ratio(node, self);
 
-        let mut node_state = NodeState::load(ui.ctx(), node_id, ui.spacing(), snarl_state.scale());
+        // Resized node ignores size of its content.
+        let size_override =
+            size.map(|size| node_size(size, None, aspect_ratio) * snarl_state.scale());
+        if let Some(size) = size_override {
+            node_state.set_size(size);
+        }
 
         let node_rect = node_state.node_rect(node_pos, openness);
 
         let mut node_to_top = None;
         let mut node_moved = None;
+        let mut node_dropped = None;
         let mut drag_released = false;
         let mut pin_hovered = None;
 
@@ -1531,9 +4972,7 @@ impl<T> Snarl<T> {
         // Rect for node + frame margin.
         let node_frame_rect = node_rect + node_frame.total_margin();
 
-        if snarl_state.selected_nodes().contains(&node) {
-            let select_style = style.get_select_style(snarl_state.scale(), ui.style());
-
+        if let Some(select_style) = visuals.selection {
             let select_rect = node_frame_rect + select_style.margin;
 
             ui.painter().rect(
@@ -1549,12 +4988,12 @@ impl<T> Snarl<T> {
         // Side of the square or diameter of the circle.
         let pin_size = style.get_pin_size(snarl_state.scale(), ui.style()).max(0.0);
 
-        let pin_placement = style.get_pin_placement();
-
         let header_drag_space = style
             .get_header_drag_space(snarl_state.scale(), ui.style())
             .max(Vec2::ZERO);
 
+        let interactive = viewer.node_interactive(node, self);
+
         // Interact with node frame.
         let r = ui.interact(
             node_frame_rect,
@@ -1562,20 +5001,34 @@ impl<T> Snarl<T> {
             Sense::click_and_drag(),
         );
 
-        if !input.modifiers.shift
+        let hovered = r.contains_pointer();
+
+        // Node tooltip is shown after pins are drawn, as hovered pin owns the hover.
+        let frame_r = r.clone();
+
+        if interactive
+            && !input.modifiers.shift
             && !input.modifiers.command
             && r.dragged_by(PointerButton::Primary)
         {
             node_moved = Some((node, snarl_state.screen_vec_to_graph(r.drag_delta())));
         }
 
+        if interactive
+            && !input.modifiers.shift
+            && !input.modifiers.command
+            && r.drag_stopped_by(PointerButton::Primary)
+        {
+            node_dropped = Some(node);
+        }
+
         if r.clicked_by(PointerButton::Primary) || r.dragged_by(PointerButton::Primary) {
             if input.modifiers.shift {
                 snarl_state.select_one_node(input.modifiers.command, node);
             } else if input.modifiers.command {
                 snarl_state.deselect_one_node(node);
             } else if snarl_state.selected_nodes().contains(&node) {
-                if r.clicked_by(PointerButton::Primary) {
+                if r.clicked_by(PointerButton::Primary) && !style.get_click_keeps_selection() {
                     snarl_state.deselect_all_nodes();
                 }
                 snarl_state.select_one_node(input.modifiers.command, node);
@@ -1589,9 +5042,40 @@ impl<T> Snarl<T> {
             node_to_top = Some(node);
         }
 
-        if viewer.has_node_menu(&self.nodes[node.0].value) {
+        if r.double_clicked_by(PointerButton::Primary) {
+            let header_bottom =
+                node_rect.min.y + node_state.header_height() + header_frame.total_margin().bottom;
+
+            if input.interact_pos.is_some_and(|pos| pos.y > header_bottom) {
+                viewer.on_node_double_click(node, ui, self);
+            }
+        }
+
+        if !self.nodes.contains(node.0) {
+            node_state.clear(ui.ctx());
+            // If removed
+            return None;
+        }
+
+        let has_node_menu = viewer.has_node_menu(&self.nodes[node.0].value);
+        let disconnect_all =
+            style.get_node_menu_disconnect_all() && self.wires.node_wires(node).next().is_some();
+
+        if has_node_menu || disconnect_all {
             r.context_menu(|ui| {
-                viewer.show_node_menu(node, &inputs, &outputs, ui, snarl_state.scale(), self);
+                if has_node_menu {
+                    viewer.show_node_menu(node, &inputs, &outputs, ui, snarl_state.scale(), self);
+                }
+
+                if disconnect_all && self.nodes.contains(node.0) {
+                    if has_node_menu {
+                        ui.separator();
+                    }
+                    if ui.button("Disconnect all").clicked() {
+                        viewer.disconnect_node(node, self);
+                        ui.close_menu();
+                    }
+                }
             });
         }
 
@@ -1617,61 +5101,35 @@ impl<T> Snarl<T> {
             UiBuilder::new()
                 .max_rect(node_frame_rect)
                 .layout(Layout::top_down(Align::Center))
-                .id_salt(node_id),
+                .id_salt(node_ui_id),
         );
 
-        let mut new_pins_size = Vec2::ZERO;
-
-        let r = node_frame.show(node_ui, |ui| {
-            let min_pin_y = node_state.header_height().mul_add(0.5, node_rect.min.y);
+        // Opacity affects only painting, node stays interactive.
+        node_ui.multiply_opacity(opacity);
 
-            // Input pins' center side by X axis.
-            let input_x = match pin_placement {
-                PinPlacement::Inside => pin_size.mul_add(
-                    0.5,
-                    node_frame_rect.left() + node_frame.inner_margin.left as f32,
-                ),
-                PinPlacement::Edge => node_frame_rect.left(),
-                PinPlacement::Outside { margin } => pin_size.mul_add(
-                    -0.5,
-                    margin.mul_add(-snarl_state.scale(), node_frame_rect.left()),
-                ),
-            };
+        let mut parts = NodeParts::default();
 
-            // Input pins' spacing required.
-            let input_spacing = match pin_placement {
-                PinPlacement::Inside => Some(pin_size),
-                PinPlacement::Edge => Some(
-                    pin_size
-                        .mul_add(0.5, -node_frame.inner_margin.left as f32)
-                        .max(0.0),
-                ),
-                PinPlacement::Outside { .. } => None,
-            };
+        let r = node_frame.show(node_ui, |ui| {
+            if size_override.is_some() {
+                ui.set_min_size(node_rect.size());
+            }
 
-            // Output pins' center side by X axis.
-            let output_x = match pin_placement {
-                PinPlacement::Inside => pin_size.mul_add(
-                    -0.5,
-                    node_frame_rect.right() - node_frame.inner_margin.right as f32,
-                ),
-                PinPlacement::Edge => node_frame_rect.right(),
-                PinPlacement::Outside { margin } => pin_size.mul_add(
-                    0.5,
-                    margin.mul_add(snarl_state.scale(), node_frame_rect.right()),
-                ),
-            };
+            let min_pin_y = node_state.header_height().mul_add(0.5, node_rect.min.y);
 
-            // Output pins' spacing required.
-            let output_spacing = match pin_placement {
-                PinPlacement::Inside => Some(pin_size),
-                PinPlacement::Edge => Some(
-                    pin_size
-                        .mul_add(0.5, -node_frame.inner_margin.right as f32)
-                        .max(0.0),
-                ),
-                PinPlacement::Outside { .. } => None,
-            };
+            let PinColumns {
+                input_x,
+                output_x,
+                input_spacing,
+                output_spacing,
+                input_row_y,
+                output_row_y,
+            } = pin_columns(
+                style,
+                node_frame_rect,
+                node_frame.inner_margin,
+                pin_size,
+                snarl_state.scale(),
+            );
 
             // Input/output pin block
 
@@ -1692,8 +5150,41 @@ impl<T> Snarl<T> {
                 node_rect.max,
             );
 
-            let node_layout =
-                viewer.node_layout(style.get_node_layout(), node, &inputs, &outputs, self);
+            if let Some((focus_node, requested)) = snarl_state.focus_node() {
+                if focus_node == node {
+                    // Zero-sized focus anchor placed before pins and body.
+                    // Once it has focus, focus is moved to the next focusable widget,
+                    // which is the first one in the node.
+                    let anchor_id = node_id.with("focus-anchor");
+
+                    if ui.memory(|m| m.has_focus(anchor_id)) {
+                        ui.memory_mut(|m| m.move_focus(FocusDirection::Next));
+                        snarl_state.set_focus_node(None);
+                    } else if requested || ui.memory(|m| m.focused().is_some()) {
+                        // Do not steal focus from another widget.
+                        snarl_state.set_focus_node(None);
+                    }
+
+                    let anchor = ui.interact(
+                        Rect::from_min_size(payload_rect.min, Vec2::ZERO),
+                        anchor_id,
+                        Sense::focusable_noninteractive(),
+                    );
+
+                    if snarl_state.focus_node() == Some((node, false)) {
+                        anchor.request_focus();
+                        snarl_state.set_focus_node(Some((node, true)));
+                    }
+                }
+            }
+
+            // Inputs must be laid out above outputs to line them up on top and bottom edges.
+            let node_layout = match style.get_pin_sides() {
+                PinSides::LeftRight => {
+                    viewer.node_layout(style.get_node_layout(), node, &inputs, &outputs, self)
+                }
+                PinSides::TopBottom => NodeLayout::Sandwich,
+            };
 
             let payload_clip_rect =
                 Rect::from_min_max(node_rect.min, pos2(node_rect.max.x, f32::INFINITY));
@@ -1704,6 +5195,7 @@ impl<T> Snarl<T> {
                     let r = self.draw_inputs(
                         viewer,
                         node,
+                        interactive,
                         &inputs,
                         pin_size,
                         style,
@@ -1711,12 +5203,15 @@ impl<T> Snarl<T> {
                         payload_rect,
                         payload_clip_rect,
                         viewport,
+                        node_frame_rect,
                         input_x,
                         min_pin_y,
                         input_spacing,
+                        None,
                         snarl_state,
                         input,
                         input_positions,
+                        new_wires_cycles,
                     );
 
                     drag_released |= r.drag_released;
@@ -1738,6 +5233,7 @@ impl<T> Snarl<T> {
                     let r = self.draw_outputs(
                         viewer,
                         node,
+                        interactive,
                         &outputs,
                         pin_size,
                         style,
@@ -1745,12 +5241,15 @@ impl<T> Snarl<T> {
                         payload_rect,
                         payload_clip_rect,
                         viewport,
+                        node_frame_rect,
                         output_x,
                         min_pin_y,
                         output_spacing,
+                        None,
                         snarl_state,
                         input,
                         output_positions,
+                        new_wires_cycles,
                     );
 
                     drag_released |= r.drag_released;
@@ -1767,10 +5266,8 @@ impl<T> Snarl<T> {
                         return;
                     }
 
-                    new_pins_size = vec2(
-                        inputs_size.x + outputs_size.x + ui.spacing().item_spacing.x,
-                        f32::max(inputs_size.y, outputs_size.y),
-                    );
+                    parts.inputs = inputs_size;
+                    parts.outputs = outputs_size;
 
                     let mut pins_rect = inputs_rect.union(outputs_rect);
 
@@ -1796,10 +5293,12 @@ impl<T> Snarl<T> {
                             payload_clip_rect,
                             viewport,
                             snarl_state,
+                            style,
+                            node_id,
+                            size_override.is_some(),
                         );
 
-                        new_pins_size.x += r.final_rect.width() + ui.spacing().item_spacing.x;
-                        new_pins_size.y = f32::max(new_pins_size.y, r.final_rect.height());
+                        parts.body = Some(r.final_rect.size());
 
                         pins_rect = pins_rect.union(body_rect);
 
@@ -1818,6 +5317,7 @@ impl<T> Snarl<T> {
                     let r = self.draw_inputs(
                         viewer,
                         node,
+                        interactive,
                         &inputs,
                         pin_size,
                         style,
@@ -1825,12 +5325,15 @@ impl<T> Snarl<T> {
                         inputs_rect,
                         payload_clip_rect,
                         viewport,
+                        node_frame_rect,
                         input_x,
                         min_pin_y,
                         input_spacing,
+                        input_row_y,
                         snarl_state,
                         input,
                         input_positions,
+                        new_wires_cycles,
                     );
 
                     drag_released |= r.drag_released;
@@ -1841,7 +5344,7 @@ impl<T> Snarl<T> {
 
                     let inputs_rect = r.final_rect;
 
-                    new_pins_size = inputs_rect.size();
+                    parts.inputs = inputs_rect.size();
 
                     let mut next_y = inputs_rect.bottom() + ui.spacing().item_spacing.y;
 
@@ -1866,12 +5369,14 @@ impl<T> Snarl<T> {
                             payload_clip_rect,
                             viewport,
                             snarl_state,
+                            style,
+                            node_id,
+                            size_override.is_some(),
                         );
 
                         let body_rect = r.final_rect;
 
-                        new_pins_size.x = f32::max(new_pins_size.x, body_rect.width());
-                        new_pins_size.y += body_rect.height() + ui.spacing().item_spacing.y;
+                        parts.body = Some(body_rect.size());
 
                         if !self.nodes.contains(node.0) {
                             // If removed
@@ -1889,6 +5394,7 @@ impl<T> Snarl<T> {
                     let r = self.draw_outputs(
                         viewer,
                         node,
+                        interactive,
                         &outputs,
                         pin_size,
                         style,
@@ -1896,12 +5402,15 @@ impl<T> Snarl<T> {
                         outputs_rect,
                         payload_clip_rect,
                         viewport,
+                        node_frame_rect,
                         output_x,
                         min_pin_y,
                         output_spacing,
+                        output_row_y,
                         snarl_state,
                         input,
                         output_positions,
+                        new_wires_cycles,
                     );
 
                     drag_released |= r.drag_released;
@@ -1917,8 +5426,7 @@ impl<T> Snarl<T> {
                         return;
                     }
 
-                    new_pins_size.x = f32::max(new_pins_size.x, outputs_rect.width());
-                    new_pins_size.y += outputs_rect.height() + ui.spacing().item_spacing.y;
+                    parts.outputs = outputs_rect.size();
 
                     pins_rect = pins_rect.union(outputs_rect);
 
@@ -1931,6 +5439,7 @@ impl<T> Snarl<T> {
                     let r = self.draw_outputs(
                         viewer,
                         node,
+                        interactive,
                         &outputs,
                         pin_size,
                         style,
@@ -1938,12 +5447,15 @@ impl<T> Snarl<T> {
                         outputs_rect,
                         payload_clip_rect,
                         viewport,
+                        node_frame_rect,
                         output_x,
                         min_pin_y,
                         output_spacing,
+                        None,
                         snarl_state,
                         input,
                         output_positions,
+                        new_wires_cycles,
                     );
 
                     drag_released |= r.drag_released;
@@ -1954,7 +5466,7 @@ impl<T> Snarl<T> {
 
                     let outputs_rect = r.final_rect;
 
-                    new_pins_size = outputs_rect.size();
+                    parts.outputs = outputs_rect.size();
 
                     let mut next_y = outputs_rect.bottom() + ui.spacing().item_spacing.y;
 
@@ -1979,12 +5491,14 @@ impl<T> Snarl<T> {
                             payload_clip_rect,
                             viewport,
                             snarl_state,
+                            style,
+                            node_id,
+                            size_override.is_some(),
                         );
 
                         let body_rect = r.final_rect;
 
-                        new_pins_size.x = f32::max(new_pins_size.x, body_rect.width());
-                        new_pins_size.y += body_rect.height() + ui.spacing().item_spacing.y;
+                        parts.body = Some(body_rect.size());
 
                         if !self.nodes.contains(node.0) {
                             // If removed
@@ -2002,6 +5516,7 @@ impl<T> Snarl<T> {
                     let r = self.draw_inputs(
                         viewer,
                         node,
+                        interactive,
                         &inputs,
                         pin_size,
                         style,
@@ -2009,12 +5524,15 @@ impl<T> Snarl<T> {
                         inputs_rect,
                         payload_clip_rect,
                         viewport,
+                        node_frame_rect,
                         input_x,
                         min_pin_y,
                         input_spacing,
+                        None,
                         snarl_state,
                         input,
                         input_positions,
+                        new_wires_cycles,
                     );
 
                     drag_released |= r.drag_released;
@@ -2030,8 +5548,7 @@ impl<T> Snarl<T> {
                         return;
                     }
 
-                    new_pins_size.x = f32::max(new_pins_size.x, inputs_rect.width());
-                    new_pins_size.y += inputs_rect.height() + ui.spacing().item_spacing.y;
+                    parts.inputs = inputs_rect.size();
 
                     pins_rect = pins_rect.union(inputs_rect);
 
@@ -2071,8 +5588,7 @@ impl<T> Snarl<T> {
                 ui.expand_to_include_rect(final_rect.intersect(payload_clip_rect));
                 let footer_size = final_rect.size();
 
-                new_pins_size.x = f32::max(new_pins_size.x, footer_size.x);
-                new_pins_size.y += footer_size.y + ui.spacing().item_spacing.y;
+                parts.footer = Some(footer_size);
 
                 if !self.nodes.contains(node.0) {
                     // If removed
@@ -2093,8 +5609,23 @@ impl<T> Snarl<T> {
                     .id_salt("header"),
             );
 
+            let header_height = style.get_header_height(snarl_state.scale());
+
             header_frame.show(header_ui, |ui: &mut Ui| {
-                ui.with_layout(Layout::left_to_right(Align::Min), |ui| {
+                // Keep title readable on custom header color.
+                if ui.visuals().override_text_color.is_none()
+                    && header_frame.fill.a() >= 128
+                    && header_frame.fill != ui.visuals().window_fill
+                {
+                    ui.visuals_mut().override_text_color =
+                        Some(contrast_text_color(header_frame.fill));
+                }
+
+                let show_header = |ui: &mut Ui| {
+                    if let Some(header_height) = header_height {
+                        ui.set_min_height(header_height);
+                    }
+
                     if style.get_collapsible() {
                         let (_, r) = ui.allocate_exact_size(
                             vec2(ui.spacing().icon_width, ui.spacing().icon_width),
@@ -2113,7 +5644,20 @@ impl<T> Snarl<T> {
                     viewer.show_header(node, &inputs, &outputs, ui, snarl_state.scale(), self);
 
                     header_rect = ui.min_rect();
-                });
+                };
+
+                match header_height {
+                    Some(header_height) => {
+                        ui.allocate_ui_with_layout(
+                            vec2(ui.available_width(), header_height),
+                            Layout::left_to_right(Align::Center),
+                            show_header,
+                        );
+                    }
+                    None => {
+                        ui.with_layout(Layout::left_to_right(Align::Min), show_header);
+                    }
+                }
 
                 header_frame_rect = header_rect + header_frame.total_margin();
 
@@ -2127,16 +5671,28 @@ impl<T> Snarl<T> {
             });
 
             ui.expand_to_include_rect(header_rect);
-            let header_size = header_rect.size();
-            node_state.set_header_height(header_size.y);
-
-            node_state.set_size(vec2(
-                f32::max(header_size.x, new_pins_size.x),
-                header_size.y
-                    + header_frame.total_margin().bottom
-                    + ui.spacing().item_spacing.y
-                    + new_pins_size.y,
-            ));
+            parts.header = header_rect.size();
+            node_state.set_header_height(parts.header.y);
+
+            let content_size = node_content_size(
+                node_layout,
+                &parts,
+                header_frame.total_margin().bottom,
+                ui.spacing().item_spacing,
+            );
+
+            // Inline widgets in pin rows may not fit into the node from previous frame
+            // and would be clipped by its rect, so the node is laid out again with grown width.
+            if size_override.is_none() && content_size.x > node_rect.width() {
+                ui.ctx().request_discard("node grew to fit its content");
+            }
+
+            node_state.set_size(node_size(content_size, size_override, aspect_ratio));
+
+            // Node is fit to aspect ratio of its current content, not of the previous size.
+            if size_override.is_none() && aspect_ratio.is_some() {
+                ui.set_min_size(node_state.node_rect(node_pos, openness).size());
+            }
         });
 
         if !self.nodes.contains(node.0) {
@@ -2146,6 +5702,101 @@ impl<T> Snarl<T> {
             return None;
         }
 
+        // Drawn inside the frame, so that hovering does not shift the layout.
+        if let Some(stroke) = visuals.hover_stroke {
+            ui.painter().rect_stroke(
+                r.response.rect,
+                node_frame.corner_radius,
+                Stroke::new(stroke.width, stroke.color.gamma_multiply(opacity)),
+                StrokeKind::Inside,
+            );
+        }
+
+        if let Some(tint) = visuals.cycle_tint {
+            ui.painter().rect_filled(
+                r.response.rect,
+                node_frame.corner_radius,
+                tint.gamma_multiply(opacity),
+            );
+        }
+
+        if !interactive {
+            ui.painter().rect_filled(
+                r.response.rect,
+                node_frame.corner_radius,
+                ui.visuals()
+                    .fade_out_to_color()
+                    .gamma_multiply(0.3 * opacity),
+            );
+        }
+
+        if pin_hovered.is_none() && !snarl_state.is_dragging() {
+            if let Some(text) = viewer.node_tooltip(node, self) {
+                frame_r.on_hover_text(text);
+            }
+        }
+
+        if style.get_resizable_nodes() && open && interactive {
+            let corner_size = Vec2::splat(ui.spacing().resize_corner_size);
+            let corner_rect =
+                Rect::from_min_max(r.response.rect.max - corner_size, r.response.rect.max);
+
+            let corner_r = ui
+                .interact(corner_rect, node_id.with("resize"), Sense::click_and_drag())
+                .on_hover_cursor(CursorIcon::ResizeNwSe);
+
+            let min_size = style.get_node_min_size(ui.style());
+            let max_size = style.get_node_max_size(ui.style());
+            let clamp_size = |size: Vec2| match aspect_ratio {
+                Some(ratio) => fit_aspect_ratio(size, ratio, min_size, max_size),
+                None => size.clamp(min_size, max_size),
+            };
+
+            if corner_r.double_clicked_by(PointerButton::Primary) {
+                self.nodes[node.0].size = None;
+                ui.ctx().request_repaint();
+            } else if corner_r.dragged_by(PointerButton::Primary) {
+                let size = size.unwrap_or_else(|| node_rect.size() / snarl_state.scale());
+                let delta = snarl_state.screen_vec_to_graph(corner_r.drag_delta());
+
+                // Node with fixed aspect ratio is resized along its diagonal.
+                let size = match aspect_ratio {
+                    Some(ratio) => {
+                        let diagonal = vec2(ratio, 1.0);
+                        fit_aspect_ratio(size, ratio, Vec2::ZERO, Vec2::INFINITY)
+                            + diagonal * (delta.dot(diagonal) / diagonal.length_sq())
+                    }
+                    None => size + delta,
+                };
+
+                self.nodes[node.0].size = Some(clamp_size(size));
+                ui.ctx().request_repaint();
+            }
+
+            // Size is snapped on release, so that resizing is smooth.
+            if let (Some(grid), true) = (
+                style.get_snap_grid(),
+                corner_r.drag_stopped_by(PointerButton::Primary),
+            ) {
+                if let Some(size) = self.nodes[node.0].size {
+                    self.nodes[node.0].size = Some(clamp_size(grid.snap_size(size)));
+                    ui.ctx().request_repaint();
+                }
+            }
+
+            let visible = match style.get_resize_handle_visibility() {
+                HandleVisibility::Always => true,
+                HandleVisibility::OnHover => {
+                    hovered || corner_r.hovered() || corner_r.dragged_by(PointerButton::Primary)
+                }
+                HandleVisibility::Never => false,
+            };
+
+            if visible {
+                paint_resize_corner(ui, corner_rect, ui.style().interact(&corner_r).fg_stroke);
+            }
+        }
+
         let final_rect = snarl_state.screen_rect_to_graph(r.response.rect, viewport);
         viewer.final_node_rect(
             node,
@@ -2156,13 +5807,27 @@ impl<T> Snarl<T> {
             self,
         );
 
+        if spawn < 1.0 {
+            let scale = egui::lerp(NODE_SPAWN_MIN_SCALE..=1.0, spawn);
+            let center = r.response.rect.center().to_vec2();
+            let transform = TSTransform::new(center * (1.0 - scale), scale);
+
+            let shapes_end = ui.painter().add(Shape::Noop);
+            ui.ctx().graphics_mut(|g| {
+                g.entry(ui.layer_id())
+                    .transform_range(shapes_start, shapes_end, transform);
+            });
+        }
+
         node_state.store(ui.ctx());
         Some(DrawNodeResponse {
             node_moved,
+            node_dropped,
             node_to_top,
             drag_released,
             pin_hovered,
             final_rect,
+            hovered,
         })
     }
 }
@@ -2176,6 +5841,574 @@ const fn mix_colors(a: Color32, b: Color32) -> Color32 {
     )
 }
 
+/// Returns interaction rect of the pin.
+///
+/// Top edge is clamped to the midpoint with previous pin in the column.
+/// Later pin takes precedence in the rest of overlap,
+/// so the pin with nearest center is hit.
+fn pin_hit_rect(pin_pos: Pos2, radius: f32, prev_pin_y: Option<f32>) -> Rect {
+    let mut rect = Rect::from_center_size(pin_pos, vec2(radius * 2.0, radius * 2.0));
+    if let Some(prev_pin_y) = prev_pin_y {
+        rect.min.y = rect.min.y.max((prev_pin_y + pin_pos.y) * 0.5);
+    }
+    rect
+}
+
+/// Finds where pin at index `from` is moved to when dragged to `pointer_y`.
+///
+/// Returns new index of the pin and y coordinate of insertion indicator.
+/// Returns `None` if there are no other pins to reorder with.
+fn pin_reorder_target(
+    pin_ys: &[f32],
+    from: usize,
+    pointer_y: f32,
+    pin_size: f32,
+) -> Option<(usize, f32)> {
+    if from >= pin_ys.len() || pin_ys.len() < 2 {
+        return None;
+    }
+
+    let others = pin_ys
+        .iter()
+        .enumerate()
+        .filter(|&(idx, _)| idx != from)
+        .map(|(_, &y)| y)
+        .collect::<Vec<_>>();
+
+    let to = others.iter().filter(|&&y| y < pointer_y).count();
+
+    let indicator_y = match to {
+        0 => others[0] - pin_size,
+        to if to == others.len() => others[to - 1] + pin_size,
+        to => (others[to - 1] + others[to]) * 0.5,
+    };
+
+    Some((to, indicator_y))
+}
+
+/// Grows smaller dimension of `size` to match `ratio` of width to height,
+/// then scales it to fit into `min..=max`.
+///
+/// When ratio can't be kept within the bounds, size is clamped to them.
+fn fit_aspect_ratio(size: Vec2, ratio: f32, min: Vec2, max: Vec2) -> Vec2 {
+    if !ratio.is_finite() || ratio <= 0.0 {
+        return size.clamp(min, max);
+    }
+
+    let mut size = if size.x < size.y * ratio {
+        vec2(size.y * ratio, size.y)
+    } else {
+        vec2(size.x, size.x / ratio)
+    };
+
+    if size.x > 0.0 && size.y > 0.0 {
+        let grow = f32::max(min.x / size.x, min.y / size.y);
+        if grow > 1.0 {
+            size *= grow;
+        }
+
+        let shrink = f32::min(max.x / size.x, max.y / size.y);
+        if shrink < 1.0 {
+            size *= shrink;
+        }
+    }
+
+    size.clamp(min, max)
+}
+
+/// Sizes of parts of the node, without frame margins.
+#[derive(Clone, Copy, Default)]
+struct NodeParts {
+    header: Vec2,
+    inputs: Vec2,
+    outputs: Vec2,
+    body: Option<Vec2>,
+    footer: Option<Vec2>,
+}
+
+/// Computes size of the node's content from sizes of its parts.
+///
+/// Parts are separated by `spacing`, header is also followed by bottom margin of its frame.
+/// Shared by [`Snarl::show`] and [`Snarl::measure`], so that both lay out nodes alike.
+fn node_content_size(
+    layout: NodeLayout,
+    parts: &NodeParts,
+    header_margin: f32,
+    spacing: Vec2,
+) -> Vec2 {
+    let stacked = |first: Vec2, second: Vec2| {
+        vec2(f32::max(first.x, second.x), first.y + second.y + spacing.y)
+    };
+
+    let mut payload = match layout {
+        NodeLayout::Basic => {
+            let mut payload = vec2(
+                parts.inputs.x + parts.outputs.x + spacing.x,
+                f32::max(parts.inputs.y, parts.outputs.y),
+            );
+            if let Some(body) = parts.body {
+                payload.x += body.x + spacing.x;
+                payload.y = f32::max(payload.y, body.y);
+            }
+            payload
+        }
+        NodeLayout::Sandwich | NodeLayout::FlippedSandwich => {
+            let (first, last) = match layout {
+                NodeLayout::Sandwich => (parts.inputs, parts.outputs),
+                _ => (parts.outputs, parts.inputs),
+            };
+            let payload = parts.body.map_or(first, |body| stacked(first, body));
+            stacked(payload, last)
+        }
+    };
+
+    if let Some(footer) = parts.footer {
+        payload = stacked(payload, footer);
+    }
+
+    vec2(
+        f32::max(parts.header.x, payload.x),
+        parts.header.y + header_margin + spacing.y + payload.y,
+    )
+}
+
+/// Computes size of the node without frame margin.
+///
+/// Node resized to `size` ignores size of its content.
+fn node_size(content: Vec2, size: Option<Vec2>, aspect_ratio: Option<f32>) -> Vec2 {
+    let size = size.unwrap_or(content);
+    aspect_ratio.map_or(size, |ratio| {
+        fit_aspect_ratio(size, ratio, Vec2::ZERO, Vec2::INFINITY)
+    })
+}
+
+/// Placement of the node's pins.
+struct PinColumns {
+    /// Input pins' center by X axis.
+    input_x: f32,
+
+    /// Output pins' center by X axis.
+    output_x: f32,
+
+    /// Space reserved for input pins in front of their content.
+    input_spacing: Option<f32>,
+
+    /// Space reserved for output pins after their content.
+    output_spacing: Option<f32>,
+
+    /// Input pins' center by Y axis when pins are lined up on the top edge.
+    input_row_y: Option<f32>,
+
+    /// Output pins' center by Y axis when pins are lined up on the bottom edge.
+    output_row_y: Option<f32>,
+}
+
+/// Computes placement of pins for the node in `frame_rect`.
+///
+/// Shared by [`Snarl::show`] and [`Snarl::measure`], so that both place pins alike.
+fn pin_columns(
+    style: &SnarlStyle,
+    frame_rect: Rect,
+    inner_margin: Margin,
+    pin_size: f32,
+    scale: f32,
+) -> PinColumns {
+    let pin_placement = style.get_pin_placement();
+
+    let input_x = match pin_placement {
+        PinPlacement::Inside => pin_size.mul_add(0.5, frame_rect.left() + inner_margin.left as f32),
+        PinPlacement::Edge => frame_rect.left(),
+        PinPlacement::Outside { margin } => {
+            pin_size.mul_add(-0.5, margin.mul_add(-scale, frame_rect.left()))
+        }
+    };
+
+    let input_spacing = match pin_placement {
+        PinPlacement::Inside => Some(pin_size),
+        PinPlacement::Edge => Some(pin_size.mul_add(0.5, -inner_margin.left as f32).max(0.0)),
+        PinPlacement::Outside { .. } => None,
+    };
+
+    let output_x = match pin_placement {
+        PinPlacement::Inside => {
+            pin_size.mul_add(-0.5, frame_rect.right() - inner_margin.right as f32)
+        }
+        PinPlacement::Edge => frame_rect.right(),
+        PinPlacement::Outside { margin } => {
+            pin_size.mul_add(0.5, margin.mul_add(scale, frame_rect.right()))
+        }
+    };
+
+    let output_spacing = match pin_placement {
+        PinPlacement::Inside => Some(pin_size),
+        PinPlacement::Edge => Some(pin_size.mul_add(0.5, -inner_margin.right as f32).max(0.0)),
+        PinPlacement::Outside { .. } => None,
+    };
+
+    let (input_row_y, output_row_y) = match style.get_pin_sides() {
+        PinSides::LeftRight => (None, None),
+        PinSides::TopBottom => {
+            let offset = match pin_placement {
+                PinPlacement::Inside | PinPlacement::Edge => 0.0,
+                PinPlacement::Outside { margin } => pin_size.mul_add(0.5, margin * scale),
+            };
+
+            (
+                Some(frame_rect.top() - offset),
+                Some(frame_rect.bottom() + offset),
+            )
+        }
+    };
+
+    PinColumns {
+        input_x,
+        output_x,
+        input_spacing,
+        output_spacing,
+        input_row_y,
+        output_row_y,
+    }
+}
+
+/// Paints diagonal lines of the resize handle in the bottom-right corner of `rect`.
+fn paint_resize_corner(ui: &Ui, rect: Rect, stroke: Stroke) {
+    let corner = rect.max;
+    for i in 1..=3 {
+        #[allow(clippy::cast_precision_loss)]
+        let w = rect.width() * i as f32 / 3.0;
+        ui.painter().line_segment(
+            [pos2(corner.x - w, corner.y), pos2(corner.x, corner.y - w)],
+            stroke,
+        );
+    }
+}
+
+/// Returns black or white color, whichever is more readable on `bg`.
+fn contrast_text_color(bg: Color32) -> Color32 {
+    let bg = Rgba::from(bg);
+    let luminance = 0.0722f32.mul_add(bg.b(), 0.2126f32.mul_add(bg.r(), 0.7152 * bg.g()));
+
+    if luminance > 0.18 {
+        Color32::BLACK
+    } else {
+        Color32::WHITE
+    }
+}
+
+/// Length of dashes and gaps of crossing selection rect.
+const SELECT_RECT_DASH: f32 = 6.0;
+
+/// Returns `true` if current selection rect selects only nodes fully contained in it.
+fn rect_selection_contains(style: &SnarlStyle, snarl_state: &SnarlState) -> bool {
+    match style.get_box_select_mode() {
+        BoxSelectMode::Intersect => false,
+        BoxSelectMode::Contain => true,
+        BoxSelectMode::Directional => snarl_state.is_rect_selection_rightward(),
+    }
+}
+
+/// Opacity multiplier for nodes and wires outside of highlighted neighborhood.
+const DIMMED_OPACITY: f32 = 0.3;
+
+/// Opacity of warning color drawn over nodes in highlighted cycles.
+const CYCLE_TINT_OPACITY: f32 = 0.25;
+
+/// Enlarges pin that can accept new wire and dims pin that cannot.
+fn highlight_compatible_pin(compatible: Option<bool>, size: &mut f32, painter: &mut Painter) {
+    match compatible {
+        Some(true) => *size *= 1.2,
+        Some(false) => painter.multiply_opacity(DIMMED_OPACITY),
+        None => {}
+    }
+}
+
+/// Draws wire label on a chip centered at `pos`.
+///
+/// Label stays upright regardless of wire direction.
+fn draw_wire_label(ui: &Ui, shapes: &mut Vec<Shape>, text: WidgetText, pos: Pos2, opacity: f32) {
+    let galley = text.into_galley(
+        ui,
+        Some(TextWrapMode::Extend),
+        f32::INFINITY,
+        TextStyle::Small,
+    );
+
+    let padding = ui.spacing().button_padding;
+    let rect = Rect::from_center_size(pos, galley.size() + padding * 2.0);
+    if !ui.is_rect_visible(rect) {
+        return;
+    }
+
+    let visuals = ui.visuals();
+    let corner_radius = visuals.widgets.noninteractive.corner_radius;
+
+    let mut stroke = visuals.widgets.noninteractive.bg_stroke;
+    stroke.color = stroke.color.gamma_multiply(opacity);
+
+    shapes.push(Shape::rect_filled(
+        rect,
+        corner_radius,
+        visuals.extreme_bg_color.gamma_multiply(opacity),
+    ));
+    shapes.push(Shape::rect_stroke(
+        rect,
+        corner_radius,
+        stroke,
+        StrokeKind::Inside,
+    ));
+    shapes.push(Shape::galley(
+        rect.min + padding,
+        galley,
+        visuals.text_color().gamma_multiply(opacity),
+    ));
+}
+
+/// Measures time spent in phases of [`Snarl::show`] for debug overlay.
+#[derive(Clone, Copy)]
+struct DebugTimer {
+    #[cfg(not(target_arch = "wasm32"))]
+    start: std::time::Instant,
+}
+
+impl DebugTimer {
+    fn start() -> Self {
+        DebugTimer {
+            #[cfg(not(target_arch = "wasm32"))]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    /// Returns elapsed time in milliseconds.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn elapsed_ms(self) -> Option<f32> {
+        Some(self.start.elapsed().as_secs_f32() * 1000.0)
+    }
+
+    /// `std::time::Instant` is not available on the web.
+    #[cfg(target_arch = "wasm32")]
+    #[allow(clippy::unused_self)]
+    fn elapsed_ms(self) -> Option<f32> {
+        None
+    }
+}
+
+/// Draws debug overlay panel with `text` in the top-left corner of the viewport.
+fn draw_debug_overlay(ui: &Ui, viewport: Rect, text: String) {
+    let visuals = ui.visuals();
+    let galley = ui.painter().layout_no_wrap(
+        text,
+        TextStyle::Monospace.resolve(ui.style()),
+        visuals.text_color(),
+    );
+
+    let padding = ui.spacing().button_padding;
+    let rect = Rect::from_min_size(
+        viewport.min + ui.spacing().item_spacing,
+        galley.size() + padding * 2.0,
+    );
+
+    let painter = ui.painter().with_clip_rect(viewport);
+    painter.rect(
+        rect,
+        visuals.widgets.noninteractive.corner_radius,
+        visuals.extreme_bg_color.gamma_multiply(0.8),
+        visuals.widgets.noninteractive.bg_stroke,
+        StrokeKind::Inside,
+    );
+    painter.galley(rect.min + padding, galley, visuals.text_color());
+}
+
+/// Duration in seconds of easing new wire's end toward snapped pin and back.
+const WIRE_MAGNET_DURATION: f32 = 0.1;
+
+/// Returns end of new wire pulled toward `snapped_pos` with easing.
+///
+/// Last snapped position is remembered,
+/// so the end eases back to the pointer after snap is released.
+fn magnet_wire_end(
+    cx: &Context,
+    id: Id,
+    snapped_pos: Option<Pos2>,
+    hover_pos: Option<Pos2>,
+) -> Option<Pos2> {
+    let target_id = id.with("wire-magnet-target");
+    let target = match snapped_pos {
+        Some(pos) => {
+            cx.data_mut(|d| d.insert_temp(target_id, pos));
+            Some(pos)
+        }
+        None => cx.data(|d| d.get_temp::<Pos2>(target_id)),
+    };
+
+    let t = cx.animate_bool_with_time(
+        id.with("wire-magnet"),
+        snapped_pos.is_some(),
+        WIRE_MAGNET_DURATION,
+    );
+
+    match (hover_pos, target) {
+        (Some(hover_pos), Some(target)) => Some(hover_pos.lerp(target, t)),
+        (hover_pos, _) => hover_pos.or(snapped_pos),
+    }
+}
+
+/// Pans the graph by scroll delta left in the input.
+///
+/// Scroll is consumed so parent scroll area does not scroll as well.
+/// Returns `true` if the graph was panned.
+fn pan_by_scroll(ui: &Ui, snarl_state: &mut SnarlState) -> bool {
+    let scroll_delta = ui.input(|i| i.smooth_scroll_delta);
+    if scroll_delta == Vec2::ZERO {
+        return false;
+    }
+
+    snarl_state.pan(-scroll_delta);
+    ui.input_mut(|i| i.smooth_scroll_delta = Vec2::ZERO);
+    true
+}
+
+/// Pointer speed in points per second above which drag from a pin is a flick.
+const FLICK_SPEED: f32 = 1500.0;
+
+/// Checks if pointer moves fast and mostly horizontally in direction `dir_x`.
+fn is_flick(velocity: Vec2, dir_x: f32) -> bool {
+    velocity.x * dir_x > FLICK_SPEED && velocity.x.abs() > velocity.y.abs()
+}
+
+/// Scale from which node grows in spawn animation.
+const NODE_SPAWN_MIN_SCALE: f32 = 0.5;
+
+/// Distance in points within which dragged node snaps to smart guides.
+const GUIDE_SNAP_DISTANCE: f32 = 5.0;
+
+/// Margin in points around nodes framed with [`SnarlStyle::fit_selection_shortcut`].
+const FIT_SELECTION_MARGIN: f32 = 16.0;
+
+/// Finds offset that aligns edges or center of the `rect` with one of `others`.
+///
+/// Returns snapping offset and guide line for each axis.
+fn snap_to_guides(rect: Rect, others: &[Rect], threshold: f32) -> (Vec2, [Option<[Pos2; 2]>; 2]) {
+    fn best_snap(
+        values: [f32; 3],
+        others: &[Rect],
+        threshold: f32,
+        axis: impl Fn(&Rect) -> [f32; 3],
+    ) -> Option<(f32, f32, Rect)> {
+        let mut best: Option<(f32, f32, Rect)> = None;
+
+        for other in others {
+            for a in values {
+                for b in axis(other) {
+                    let offset = b - a;
+                    if offset.abs() > threshold {
+                        continue;
+                    }
+                    if matches!(best, Some((best_offset, _, _)) if best_offset.abs() <= offset.abs())
+                    {
+                        continue;
+                    }
+                    best = Some((offset, b, *other));
+                }
+            }
+        }
+
+        best
+    }
+
+    let xs = |r: &Rect| [r.min.x, r.center().x, r.max.x];
+    let ys = |r: &Rect| [r.min.y, r.center().y, r.max.y];
+
+    let snap_x = best_snap(xs(&rect), others, threshold, xs);
+    let snap_y = best_snap(ys(&rect), others, threshold, ys);
+
+    let offset = vec2(
+        snap_x.map_or(0.0, |(offset, _, _)| offset),
+        snap_y.map_or(0.0, |(offset, _, _)| offset),
+    );
+
+    let rect = rect.translate(offset);
+
+    let guide_x = snap_x.map(|(_, x, other)| {
+        [
+            pos2(x, rect.min.y.min(other.min.y)),
+            pos2(x, rect.max.y.max(other.max.y)),
+        ]
+    });
+
+    let guide_y = snap_y.map(|(_, y, other)| {
+        [
+            pos2(rect.min.x.min(other.min.x), y),
+            pos2(rect.max.x.max(other.max.x), y),
+        ]
+    });
+
+    (offset, [guide_x, guide_y])
+}
+
+/// Distance in points the pointer must move to add a point to lasso path.
+const LASSO_MIN_DISTANCE: f32 = 4.0;
+
+/// Checks if `pos` is inside of closed polygon `points`.
+///
+/// Uses even-odd rule, so areas of self-intersecting lasso
+/// enclosed an even number of times are outside.
+fn lasso_contains(points: &[Pos2], pos: Pos2) -> bool {
+    let mut inside = false;
+
+    let mut prev = match points.last() {
+        Some(&last) => last,
+        None => return false,
+    };
+
+    for &point in points {
+        if (point.y > pos.y) != (prev.y > pos.y) {
+            let x = point.x + (pos.y - point.y) * (prev.x - point.x) / (prev.y - point.y);
+            if pos.x < x {
+                inside = !inside;
+            }
+        }
+        prev = point;
+    }
+
+    inside
+}
+
+/// Returns edge panning direction for the pointer position.
+///
+/// Each component is in range `-1..=1` and is proportional to
+/// how deep the pointer is within `margin` from the viewport's edge.
+fn edge_pan(pos: Pos2, viewport: Rect, margin: f32) -> Vec2 {
+    if margin <= 0.0 {
+        return Vec2::ZERO;
+    }
+
+    let depth = |pos: f32, min: f32, max: f32| {
+        if pos < min + margin {
+            -((min + margin - pos) / margin).min(1.0)
+        } else if pos > max - margin {
+            ((pos - max + margin) / margin).min(1.0)
+        } else {
+            0.0
+        }
+    };
+
+    vec2(
+        depth(pos.x, viewport.min.x, viewport.max.x),
+        depth(pos.y, viewport.min.y, viewport.max.y),
+    )
+}
+
+fn lerp_colors(a: Color32, b: Color32, t: f32) -> Color32 {
+    let lerp = |a: u8, b: u8| (f32::from(a) + (f32::from(b) - f32::from(a)) * t).round() as u8;
+
+    Color32::from_rgba_premultiplied(
+        lerp(a.r(), b.r()),
+        lerp(a.g(), b.g()),
+        lerp(a.b(), b.b()),
+        lerp(a.a(), b.a()),
+    )
+}
+
 // fn mix_colors(mut colors: impl Iterator<Item = Color32>) -> Option<Color32> {
 //     let color = colors.next()?;
 
@@ -2249,3 +6482,10 @@ const fn snarl_style_is_send_sync() {
     const fn is_send_sync<T: Send + Sync>() {}
     is_send_sync::<SnarlStyle>();
 }
+
+#[cfg(feature = "serde")]
+#[test]
+const fn snarl_style_is_serde() {
+    const fn is_serde<T: serde::Serialize + serde::de::DeserializeOwned>() {}
+    is_serde::<SnarlStyle>();
+}
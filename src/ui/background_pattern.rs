@@ -1,8 +1,11 @@
-use egui::{emath::Rot2, vec2, Painter, Pos2, Rect, Style, Vec2};
+use egui::{emath::Rot2, pos2, vec2, Painter, Pos2, Rect, Stroke, Style, Vec2};
 
 use super::SnarlStyle;
 
 /// Viewport is a rectangle in graph space that is visible on screen.
+///
+/// Created with [`Viewport::new`], fields may be added in future versions.
+#[non_exhaustive]
 pub struct Viewport {
     /// Screen-space rectangle.
     pub rect: Rect,
@@ -11,22 +14,82 @@ pub struct Viewport {
     pub scale: f32,
 
     /// Offset of the viewport.
+    ///
+    /// Rounded to `f32` from [`Viewport::offset_f64`] that is used by position conversions.
     pub offset: Vec2,
+
+    /// Offset of the viewport in `f64`.
+    ///
+    /// Position conversions use it to stay precise in graphs spanning millions of units.
+    offset_f64: [f64; 2],
+
+    /// Origin of the background pattern in graph space.
+    ///
+    /// Position of [`SnarlStyle::pattern_anchor`] node if set, otherwise zero.
+    pub pattern_origin: Pos2,
 }
 
 impl Viewport {
+    /// Creates viewport with given screen-space rectangle, scale and offset.
+    ///
+    /// Pattern origin is set to zero.
+    #[must_use]
+    pub const fn new(rect: Rect, scale: f32, offset: [f64; 2]) -> Self {
+        Viewport {
+            rect,
+            scale,
+            offset: vec2(offset[0] as f32, offset[1] as f32),
+            offset_f64: offset,
+            pattern_origin: Pos2::ZERO,
+        }
+    }
+
+    /// Returns offset of the viewport in `f64`.
+    ///
+    /// Same as [`Viewport::offset`] without rounding to `f32`.
+    #[inline(always)]
+    #[must_use]
+    pub const fn offset_f64(&self) -> [f64; 2] {
+        self.offset_f64
+    }
+
     /// Converts screen-space position to graph-space position.
     #[inline(always)]
     #[must_use]
     pub fn screen_pos_to_graph(&self, pos: Pos2) -> Pos2 {
-        (pos + self.offset - self.rect.center().to_vec2()) / self.scale
+        let [x, y] = self.screen_pos_to_graph_f64(pos);
+        pos2(x as f32, y as f32)
+    }
+
+    /// Converts screen-space position to graph-space position in `f64`.
+    #[inline(always)]
+    #[must_use]
+    pub fn screen_pos_to_graph_f64(&self, pos: Pos2) -> [f64; 2] {
+        let pos = pos - self.rect.center();
+        let scale = f64::from(self.scale);
+        [
+            (f64::from(pos.x) + self.offset_f64[0]) / scale,
+            (f64::from(pos.y) + self.offset_f64[1]) / scale,
+        ]
     }
 
     /// Converts graph-space position to screen-space position.
     #[inline(always)]
     #[must_use]
     pub fn graph_pos_to_screen(&self, pos: Pos2) -> Pos2 {
-        pos * self.scale - self.offset + self.rect.center().to_vec2()
+        self.graph_pos_to_screen_f64([f64::from(pos.x), f64::from(pos.y)])
+    }
+
+    /// Converts graph-space position in `f64` to screen-space position.
+    ///
+    /// Only the final screen-space position is rounded to `f32`.
+    #[inline(always)]
+    #[must_use]
+    pub fn graph_pos_to_screen_f64(&self, pos: [f64; 2]) -> Pos2 {
+        let scale = f64::from(self.scale);
+        let x = pos[0].mul_add(scale, -self.offset_f64[0]) as f32;
+        let y = pos[1].mul_add(scale, -self.offset_f64[1]) as f32;
+        pos2(x, y) + self.rect.center().to_vec2()
     }
 
     /// Converts screen-space vector to graph-space vector.
@@ -58,6 +121,88 @@ impl Viewport {
     }
 }
 
+/// Shifts line so that it passes through centers of physical pixels across its direction.
+///
+/// Line is moved as a whole, keeping its direction,
+/// so its position depends only on its own screen coordinates and does not jitter when panning.
+fn align_to_pixels(points: [Pos2; 2], pixels_per_point: Option<f32>) -> [Pos2; 2] {
+    let Some(ppp) = pixels_per_point else {
+        return points;
+    };
+
+    let align = |v: f32| ((v * ppp - 0.5).round() + 0.5) / ppp - v;
+
+    let [a, b] = points;
+    let mid = a.lerp(b, 0.5);
+    let dir = b - a;
+
+    let shift = if dir.x.abs() < dir.y.abs() {
+        vec2(align(mid.x), 0.0)
+    } else {
+        vec2(0.0, align(mid.y))
+    };
+
+    [a + shift, b + shift]
+}
+
+/// Maximum number of pieces a pattern line is split into for edge fading.
+const MAX_FADE_STEPS: f32 = 256.0;
+
+/// Draws pattern line that fades out within `edge_fade` margin along edges of `rect`.
+fn draw_line(painter: &Painter, rect: Rect, points: [Pos2; 2], stroke: Stroke, edge_fade: f32) {
+    if edge_fade <= 0.0 {
+        painter.line_segment(points, stroke);
+        return;
+    }
+
+    let fade = |pos: Pos2| {
+        let distance = (pos.x - rect.min.x)
+            .min(rect.max.x - pos.x)
+            .min(pos.y - rect.min.y)
+            .min(rect.max.y - pos.y);
+        (distance / edge_fade).clamp(0.0, 1.0)
+    };
+
+    let [a, b] = points;
+
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    let steps = ((b - a).length() / (edge_fade * 0.25))
+        .ceil()
+        .clamp(1.0, MAX_FADE_STEPS) as usize;
+
+    // Consecutive pieces without fading are drawn as single segment.
+    let mut solid_start = None;
+
+    for step in 0..steps {
+        #[allow(clippy::cast_precision_loss)]
+        let (from, to) = (
+            a.lerp(b, step as f32 / steps as f32),
+            a.lerp(b, (step + 1) as f32 / steps as f32),
+        );
+
+        let alpha = fade(from.lerp(to, 0.5));
+        if alpha >= 1.0 {
+            solid_start.get_or_insert(from);
+            continue;
+        }
+
+        if let Some(start) = solid_start.take() {
+            painter.line_segment([start, from], stroke);
+        }
+
+        if alpha > 0.0 {
+            painter.line_segment(
+                [from, to],
+                Stroke::new(stroke.width, stroke.color.gamma_multiply(alpha)),
+            );
+        }
+    }
+
+    if let Some(start) = solid_start {
+        painter.line_segment([start, b], stroke);
+    }
+}
+
 ///Grid background pattern.
 ///Use `SnarlStyle::background_pattern_stroke` for change stroke options
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -102,6 +247,37 @@ impl Grid {
         Self { spacing, angle }
     }
 
+    /// Returns grid node nearest to `pos` in graph space.
+    ///
+    /// Grid is built from `origin`, same as drawn with [`Viewport::pattern_origin`].
+    #[must_use]
+    pub fn snap(&self, pos: Pos2, origin: Pos2) -> Pos2 {
+        let spacing = vec2(self.spacing.x.max(1.0), self.spacing.y.max(1.0));
+
+        let rot = Rot2::from_angle(self.angle);
+        let local = rot.inverse() * (pos - origin);
+
+        let snapped = vec2(
+            (local.x / spacing.x).round() * spacing.x,
+            (local.y / spacing.y).round() * spacing.y,
+        );
+
+        origin + rot * snapped
+    }
+
+    /// Returns size nearest to `size` that is a multiple of grid spacing.
+    ///
+    /// Grid angle is ignored, as nodes are not rotated with the grid.
+    #[must_use]
+    pub fn snap_size(&self, size: Vec2) -> Vec2 {
+        let spacing = vec2(self.spacing.x.max(1.0), self.spacing.y.max(1.0));
+
+        vec2(
+            (size.x / spacing.x).round() * spacing.x,
+            (size.y / spacing.y).round() * spacing.y,
+        )
+    }
+
     fn draw(
         &self,
         viewport: &Viewport,
@@ -110,16 +286,24 @@ impl Grid {
         painter: &Painter,
     ) {
         let bg_stroke = snarl_style.get_bg_pattern_stroke(viewport.scale, style);
+        let edge_fade = snarl_style.get_bg_pattern_edge_fade();
+        let pixels_per_point = snarl_style
+            .get_crisp_grid()
+            .then(|| painter.pixels_per_point());
 
         let spacing = vec2(self.spacing.x.max(1.0), self.spacing.y.max(1.0));
 
         let rot = Rot2::from_angle(self.angle);
         let rot_inv = rot.inverse();
 
+        // Pattern is built relative to its origin.
+        let origin = viewport.pattern_origin.to_vec2();
+
         let graph_viewport = Rect::from_min_max(
             viewport.screen_pos_to_graph(viewport.rect.min),
             viewport.screen_pos_to_graph(viewport.rect.max),
-        );
+        )
+        .translate(-origin);
 
         let pattern_bounds = graph_viewport.rotate_bb(rot_inv);
 
@@ -130,13 +314,14 @@ impl Grid {
             #[allow(clippy::cast_precision_loss)]
             let x = (x as f32 + min_x) * spacing.x;
 
-            let top = (rot * vec2(x, pattern_bounds.min.y)).to_pos2();
-            let bottom = (rot * vec2(x, pattern_bounds.max.y)).to_pos2();
+            let top = (rot * vec2(x, pattern_bounds.min.y)).to_pos2() + origin;
+            let bottom = (rot * vec2(x, pattern_bounds.max.y)).to_pos2() + origin;
 
             let top = viewport.graph_pos_to_screen(top);
             let bottom = viewport.graph_pos_to_screen(bottom);
+            let [top, bottom] = align_to_pixels([top, bottom], pixels_per_point);
 
-            painter.line_segment([top, bottom], bg_stroke);
+            draw_line(painter, viewport.rect, [top, bottom], bg_stroke, edge_fade);
         }
 
         let min_y = (pattern_bounds.min.y / spacing.y).ceil();
@@ -146,13 +331,14 @@ impl Grid {
             #[allow(clippy::cast_precision_loss)]
             let y = (y as f32 + min_y) * spacing.y;
 
-            let top = (rot * vec2(pattern_bounds.min.x, y)).to_pos2();
-            let bottom = (rot * vec2(pattern_bounds.max.x, y)).to_pos2();
+            let top = (rot * vec2(pattern_bounds.min.x, y)).to_pos2() + origin;
+            let bottom = (rot * vec2(pattern_bounds.max.x, y)).to_pos2() + origin;
 
             let top = viewport.graph_pos_to_screen(top);
             let bottom = viewport.graph_pos_to_screen(bottom);
+            let [top, bottom] = align_to_pixels([top, bottom], pixels_per_point);
 
-            painter.line_segment([top, bottom], bg_stroke);
+            draw_line(painter, viewport.rect, [top, bottom], bg_stroke, edge_fade);
         }
     }
 }
@@ -4,9 +4,13 @@ use crate::{InPinId, OutPinId};
 
 use super::{zoom::Zoom, SnarlStyle, WireStyle};
 
+/// Either output or input pin.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum AnyPin {
+    /// Output pin.
     Out(OutPinId),
+
+    /// Input pin.
     In(InPinId),
 }
 
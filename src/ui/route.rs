@@ -0,0 +1,293 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    hash::{DefaultHasher, Hash, Hasher},
+};
+
+use egui::{pos2, Pos2, Rect};
+
+use crate::Wire;
+
+/// Maximum number of obstacles considered by the router.
+const MAX_OBSTACLES: usize = 32;
+
+/// Maximum number of visited grid points before router gives up.
+const MAX_VISITED: usize = 4096;
+
+/// Entry of the open set ordered by estimated total cost, lowest first.
+struct Open {
+    estimate: f32,
+    state: usize,
+}
+
+impl PartialEq for Open {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Open {}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+/// Sorts coordinates and removes ones that are too close to each other.
+fn grid_lines(mut lines: Vec<f32>) -> Vec<f32> {
+    lines.sort_by(f32::total_cmp);
+    lines.dedup_by(|a, b| (*a - *b).abs() < 0.5);
+    lines
+}
+
+/// Finds path of horizontal and vertical segments from `from` to `to`
+/// that keeps `margin` away from `obstacles`.
+///
+/// Path leaves `from` to the right and enters `to` from the left.
+/// Only obstacles that intersect bounding box of the endpoints are avoided.
+///
+/// Returns `None` if obstacles block the endpoints,
+/// or if routing exceeds complexity budget.
+pub fn route_wire(from: Pos2, to: Pos2, obstacles: &[Rect], margin: f32) -> Option<Vec<Pos2>> {
+    let margin = margin.max(1.0);
+    let start = pos2(from.x + margin, from.y);
+    let goal = pos2(to.x - margin, to.y);
+
+    let bb = Rect::from_two_pos(from, to).expand(margin);
+
+    // Obstacles are blocked a bit less than margin,
+    // so paths along grid lines around them are not blocked.
+    let blocked = obstacles
+        .iter()
+        .filter(|rect| rect.intersects(bb))
+        .map(|rect| rect.expand(margin * 0.5))
+        .collect::<Vec<_>>();
+
+    if blocked.len() > MAX_OBSTACLES {
+        return None;
+    }
+
+    let segment_free = |a: Pos2, b: Pos2| {
+        let segment = Rect::from_two_pos(a, b);
+        !blocked.iter().any(|rect| rect.intersects(segment))
+    };
+
+    if !segment_free(from, start) || !segment_free(goal, to) {
+        return None;
+    }
+
+    let mut xs = vec![start.x, goal.x];
+    let mut ys = vec![start.y, goal.y];
+    for rect in &blocked {
+        xs.push(rect.min.x - margin * 0.5);
+        xs.push(rect.max.x + margin * 0.5);
+        ys.push(rect.min.y - margin * 0.5);
+        ys.push(rect.max.y + margin * 0.5);
+    }
+    let xs = grid_lines(xs);
+    let ys = grid_lines(ys);
+
+    let nearest = |lines: &[f32], v: f32| {
+        lines
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| (*a - v).abs().total_cmp(&(*b - v).abs()))
+            .map_or(0, |(idx, _)| idx)
+    };
+
+    let point = |state: usize| {
+        let cell = state / 2;
+        pos2(xs[cell % xs.len()], ys[cell / xs.len()])
+    };
+
+    // State is grid point and direction in which the path arrived to it,
+    // so that turns can be penalized.
+    let state_of =
+        |x: usize, y: usize, vertical: bool| (y * xs.len() + x) * 2 + usize::from(vertical);
+
+    let start_state = state_of(nearest(&xs, start.x), nearest(&ys, start.y), false);
+    let goal_cell = state_of(nearest(&xs, goal.x), nearest(&ys, goal.y), false) / 2;
+
+    let turn_cost = margin * 2.0;
+    let heuristic = |pos: Pos2| (pos.x - goal.x).abs() + (pos.y - goal.y).abs();
+
+    let mut costs = vec![f32::INFINITY; xs.len() * ys.len() * 2];
+    let mut came_from = vec![usize::MAX; costs.len()];
+    let mut open = BinaryHeap::new();
+
+    costs[start_state] = 0.0;
+    open.push(Open {
+        estimate: heuristic(point(start_state)),
+        state: start_state,
+    });
+
+    let mut visited = 0;
+    let mut reached = None;
+
+    while let Some(Open { state, estimate }) = open.pop() {
+        let cost = costs[state];
+        if estimate > cost + heuristic(point(state)) {
+            // Stale entry.
+            continue;
+        }
+
+        if state / 2 == goal_cell {
+            reached = Some(state);
+            break;
+        }
+
+        visited += 1;
+        if visited > MAX_VISITED {
+            return None;
+        }
+
+        let cell = state / 2;
+        let (x, y) = (cell % xs.len(), cell / xs.len());
+        let vertical = state % 2 == 1;
+
+        let neighbors = [
+            (x.checked_sub(1).map(|x| (x, y)), false),
+            ((x + 1 < xs.len()).then_some((x + 1, y)), false),
+            (y.checked_sub(1).map(|y| (x, y)), true),
+            ((y + 1 < ys.len()).then_some((x, y + 1)), true),
+        ];
+
+        for (next, next_vertical) in neighbors {
+            let Some((nx, ny)) = next else {
+                continue;
+            };
+
+            let next_state = state_of(nx, ny, next_vertical);
+            let a = point(state);
+            let b = point(next_state);
+
+            if !segment_free(a, b) {
+                continue;
+            }
+
+            let mut next_cost = cost + (b - a).length();
+            if vertical != next_vertical {
+                next_cost += turn_cost;
+            }
+
+            if next_cost < costs[next_state] {
+                costs[next_state] = next_cost;
+                came_from[next_state] = state;
+                open.push(Open {
+                    estimate: next_cost + heuristic(b),
+                    state: next_state,
+                });
+            }
+        }
+    }
+
+    let mut state = reached?;
+    let mut points = vec![to, goal];
+    while state != start_state {
+        points.push(point(state));
+        state = came_from[state];
+    }
+    points.push(start);
+    points.push(from);
+    points.reverse();
+
+    Some(simplify(points))
+}
+
+/// Removes repeated points and points in the middle of straight segments.
+fn simplify(points: Vec<Pos2>) -> Vec<Pos2> {
+    let mut result: Vec<Pos2> = Vec::with_capacity(points.len());
+
+    for point in points {
+        if result
+            .last()
+            .is_some_and(|last| (*last - point).length() < 0.5)
+        {
+            continue;
+        }
+
+        if let [.., a, b] = result[..] {
+            let collinear = ((a.x - b.x).abs() < 0.5 && (b.x - point.x).abs() < 0.5)
+                || ((a.y - b.y).abs() < 0.5 && (b.y - point.y).abs() < 0.5);
+
+            if collinear {
+                result.pop();
+            }
+        }
+
+        result.push(point);
+    }
+
+    result
+}
+
+#[derive(Clone)]
+struct CachedRoute {
+    key: u64,
+    route: Option<Vec<Pos2>>,
+    used: bool,
+}
+
+/// Routes of wires in graph space cached between frames.
+#[derive(Clone, Default)]
+pub struct WireRoutes {
+    routes: HashMap<Wire, CachedRoute>,
+}
+
+impl WireRoutes {
+    /// Returns route of the wire.
+    ///
+    /// Cached route is reused unless endpoints, margin
+    /// or obstacles around the wire have changed.
+    pub fn route(
+        &mut self,
+        wire: Wire,
+        from: Pos2,
+        to: Pos2,
+        obstacles: impl Iterator<Item = Rect>,
+        margin: f32,
+    ) -> Option<&[Pos2]> {
+        let bb = Rect::from_two_pos(from, to).expand(margin);
+        let obstacles = obstacles
+            .filter(|rect| rect.intersects(bb))
+            .collect::<Vec<_>>();
+
+        let mut hasher = DefaultHasher::new();
+        for v in [from.x, from.y, to.x, to.y, margin] {
+            v.to_bits().hash(&mut hasher);
+        }
+        for rect in &obstacles {
+            for v in [rect.min.x, rect.min.y, rect.max.x, rect.max.y] {
+                v.to_bits().hash(&mut hasher);
+            }
+        }
+        let key = hasher.finish();
+
+        let cached = self.routes.entry(wire).or_insert_with(|| CachedRoute {
+            key: !key,
+            route: None,
+            used: false,
+        });
+
+        if cached.key != key {
+            cached.key = key;
+            cached.route = route_wire(from, to, &obstacles, margin);
+        }
+        cached.used = true;
+
+        cached.route.as_deref()
+    }
+
+    /// Drops routes of wires that were not routed since last call.
+    pub fn retain_used(&mut self) {
+        self.routes
+            .retain(|_, cached| std::mem::take(&mut cached.used));
+    }
+}
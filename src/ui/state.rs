@@ -1,10 +1,20 @@
-use std::hash::Hash;
+use std::{hash::Hash, sync::Arc};
 
-use egui::{ahash::HashSet, style::Spacing, Context, Id, Pos2, Rect, Ui, Vec2};
+use egui::{
+    ahash::{HashMap, HashSet},
+    pos2,
+    style::Spacing,
+    vec2, Context, Id, Pos2, Rect, Shape, Ui, Vec2,
+};
 
-use crate::{InPinId, NodeId, OutPinId, Snarl};
+use crate::{InPinId, NodeId, OutPinId, Snarl, Wire};
 
-use super::SnarlStyle;
+use super::{
+    pin::AnyPin,
+    wire::{WireGeometries, WirePath},
+    zoom::Zoom,
+    NodeAnchor, SnarlStyle, WireStyle,
+};
 
 /// Node UI state.
 pub struct NodeState {
@@ -24,6 +34,35 @@ struct NodeData {
     unscaled_header_height: f32,
 }
 
+/// Shapes painted by node's body, replayed while its cache key is unchanged.
+///
+/// See [`SnarlViewer::body_cache_key`](super::SnarlViewer::body_cache_key).
+#[derive(Clone)]
+pub struct BodyCache {
+    pub key: u64,
+
+    /// Space available to the body when shapes were recorded.
+    pub max_size: Vec2,
+    pub scale: f32,
+
+    /// Rect occupied by the body when shapes were recorded.
+    pub rect: Rect,
+    pub shapes: Arc<[Shape]>,
+}
+
+impl BodyCache {
+    /// Returns cache of the node's body if it is still valid for given key and layout.
+    #[allow(clippy::float_cmp)]
+    pub fn load(cx: &Context, id: Id, key: u64, max_size: Vec2, scale: f32) -> Option<Self> {
+        cx.data(|d| d.get_temp::<BodyCache>(id))
+            .filter(|cache| cache.key == key && cache.max_size == max_size && cache.scale == scale)
+    }
+
+    pub fn store(self, cx: &Context, id: Id) {
+        cx.data_mut(|d| d.insert_temp(id, self));
+    }
+}
+
 impl NodeState {
     pub fn load(cx: &Context, id: Id, spacing: &Spacing, scale: f32) -> Self {
         cx.data_mut(|d| d.get_temp::<NodeData>(id)).map_or_else(
@@ -41,8 +80,35 @@ impl NodeState {
         )
     }
 
+    /// Returns node size in graph space stored in previous frames.
+    pub fn stored_graph_size(cx: &Context, id: Id) -> Option<Vec2> {
+        cx.data_mut(|d| d.get_temp::<NodeData>(id))
+            .map(|data| data.unscaled_size)
+    }
+
+    /// Returns node rect in graph space stored in previous frames.
+    /// Collapsed node is as tall as its header.
+    pub fn stored_graph_rect(cx: &Context, id: Id, pos: Pos2, open: bool) -> Option<Rect> {
+        cx.data_mut(|d| d.get_temp::<NodeData>(id)).map(|data| {
+            let height = if open {
+                data.unscaled_size.y
+            } else {
+                data.unscaled_header_height
+            };
+            Rect::from_min_size(pos, egui::vec2(data.unscaled_size.x, height))
+        })
+    }
+
     pub fn clear(self, cx: &Context) {
-        cx.data_mut(|d| d.remove::<Self>(self.id));
+        Self::forget(cx, self.id);
+    }
+
+    /// Removes stored layout of the node, e.g. when node is removed from the graph.
+    pub fn forget(cx: &Context, id: Id) {
+        cx.data_mut(|d| {
+            d.remove::<NodeData>(id);
+            d.remove::<BodyCache>(id);
+        });
     }
 
     pub fn store(&self, cx: &Context) {
@@ -104,7 +170,7 @@ impl NodeState {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub enum NewWires {
     In(Vec<InPinId>),
     Out(Vec<OutPinId>),
@@ -116,15 +182,28 @@ struct RectSelect {
     current: Pos2,
 }
 
+/// Path of lasso selection in graph space.
+#[derive(Clone)]
+struct LassoSelect(Vec<Pos2>);
+
+/// Maximum number of points kept in lasso path.
+/// Path is thinned out when it grows beyond this limit.
+const MAX_LASSO_POINTS: usize = 512;
+
 pub struct SnarlState {
     /// Where viewport's center in graph's space.
-    offset: Vec2,
+    ///
+    /// Kept in `f64`, so that view panned far from the origin does not jitter.
+    offset: [f64; 2],
 
     /// Scale of the viewport.
     scale: f32,
 
     target_scale: f32,
 
+    /// Whether scale animates around viewport center instead of the pointer.
+    centered_zoom: bool,
+
     new_wires: Option<NewWires>,
 
     id: Id,
@@ -135,14 +214,114 @@ pub struct SnarlState {
     /// Flag indicating that the link menu is open.
     is_link_menu_open: bool,
 
+    /// Wire for which context menu is open.
+    wire_menu: Option<(OutPinId, InPinId)>,
+
+    /// Offset applied to dragged nodes by snapping to smart guides.
+    guide_snap: Vec2,
+
+    /// Node hovered in previous frame.
+    hovered_node: Option<NodeId>,
+
+    /// Pin being dragged to reorder it.
+    pin_reorder: Option<AnyPin>,
+
+    /// Newly created node that should receive keyboard focus
+    /// and whether focus was already requested for it.
+    focus_node: Option<(NodeId, bool)>,
+
     /// Order of nodes to draw.
     draw_order: Vec<NodeId>,
 
     /// Active rect selection.
     rect_selection: Option<RectSelect>,
 
+    /// Active lasso selection.
+    lasso_selection: Option<Vec<Pos2>>,
+
     /// List of currently selected nodes.
     selected_nodes: Vec<NodeId>,
+
+    /// Activity of wires in range `0..=1`.
+    /// Decays over time.
+    wire_activity: HashMap<(OutPinId, InPinId), f32>,
+
+    /// State before current drag, used to roll it back when drag is canceled.
+    drag_origin: DragOrigin,
+
+    /// Index of wire style chosen with [`SnarlStyle::wire_style_shortcut`].
+    /// Unlike other state it is persisted.
+    wire_style_override: Option<u8>,
+}
+
+/// Default wire styles cycled with [`SnarlStyle::wire_style_shortcut`].
+const WIRE_STYLES_CYCLED: u8 = 4;
+
+/// Corner radius of cycled axis aligned wires,
+/// unless [`SnarlStyle::wire_style`] specifies one.
+const CYCLED_CORNER_RADIUS: f32 = 10.0;
+
+/// Returns wire style at `idx` in the cycle.
+fn cycled_wire_style(idx: u8, style: &SnarlStyle) -> WireStyle {
+    let corner_radius = match style.wire_style {
+        Some(WireStyle::AxisAligned { corner_radius } | WireStyle::Avoid { corner_radius }) => {
+            corner_radius
+        }
+        _ => CYCLED_CORNER_RADIUS,
+    };
+
+    match idx % WIRE_STYLES_CYCLED {
+        0 => WireStyle::Bezier5,
+        1 => WireStyle::Bezier3,
+        2 => WireStyle::AxisAligned { corner_radius },
+        _ => WireStyle::Avoid { corner_radius },
+    }
+}
+
+/// Returns index of wire style in the cycle.
+const fn wire_style_index(style: WireStyle) -> u8 {
+    match style {
+        WireStyle::Bezier5 => 0,
+        WireStyle::Bezier3 => 1,
+        WireStyle::AxisAligned { .. } => 2,
+        WireStyle::Avoid { .. } => 3,
+    }
+}
+
+/// Zoom change requested with [`Snarl::zoom_to`] and related methods.
+#[derive(Clone, Copy, Debug)]
+pub enum ZoomRequest {
+    /// Zoom to given scale.
+    To(f32),
+
+    /// Zoom to scale `1.0` and center the graph.
+    Reset,
+
+    /// Zoom by given number of [`ZOOM_STEP`]s, in if positive and out if negative.
+    Steps(i32),
+
+    /// Zoom and pan to fit selected nodes with given margin in points.
+    FitSelection(f32),
+}
+
+impl ZoomRequest {
+    /// Combines request with the `next` one.
+    ///
+    /// Zoom steps are added up, other requests are replaced with the `next` one.
+    #[must_use]
+    pub const fn then(self, next: ZoomRequest) -> ZoomRequest {
+        match (self, next) {
+            (ZoomRequest::Steps(a), ZoomRequest::Steps(b)) => ZoomRequest::Steps(a + b),
+            (_, next) => next,
+        }
+    }
+}
+
+/// Factor by which scale is multiplied or divided on zoom steps.
+pub const ZOOM_STEP: f32 = 1.25;
+
+fn load_wire_style_override(cx: &Context, id: Id) -> Option<u8> {
+    cx.data_mut(|d| d.get_persisted::<u8>(id.with("wire-style")))
 }
 
 #[derive(Clone)]
@@ -151,23 +330,83 @@ struct DrawOrder(Vec<NodeId>);
 #[derive(Clone)]
 struct SelectedNodes(Vec<NodeId>);
 
+/// Offsets of pins' centers from position of their nodes, in graph space.
+///
+/// Offsets of nodes that are not drawn in a frame are kept from previous frames.
+#[derive(Clone, Default)]
+pub struct PinOffsets {
+    pub inputs: HashMap<InPinId, Vec2>,
+    pub outputs: HashMap<OutPinId, Vec2>,
+}
+
+/// State of the graph before current drag.
+#[derive(Clone, Default)]
+pub struct DragOrigin {
+    /// Wires disconnected when new wire drag started.
+    pub wires: Vec<(OutPinId, InPinId)>,
+
+    /// Positions of dragged nodes.
+    pub nodes: Vec<(NodeId, Pos2)>,
+
+    /// Total graph-space delta of current node drag.
+    pub delta: Vec2,
+}
+
+impl DragOrigin {
+    fn is_empty(&self) -> bool {
+        self.wires.is_empty() && self.nodes.is_empty()
+    }
+}
+
+#[derive(Clone)]
+struct WireActivity(HashMap<(OutPinId, InPinId), f32>);
+
+/// Wires flashed since last frame.
+/// Kept separately from [`WireActivity`] so that flashing during `Snarl::show` is not lost.
+#[derive(Clone, Default)]
+struct WireFlashes(Vec<(OutPinId, InPinId)>);
+
+/// Innermost snarl under the pointer.
+/// Used to route zooming to nested snarls only.
+#[derive(Clone, Copy)]
+struct HoveredSnarl {
+    pass: u64,
+    current: Option<Id>,
+    previous: Option<Id>,
+}
+
 struct SnarlStateData {
-    offset: Vec2,
+    offset: [f64; 2],
     scale: f32,
     target_scale: f32,
+    centered_zoom: bool,
     is_link_menu_open: bool,
+    wire_menu: Option<(OutPinId, InPinId)>,
+    guide_snap: Vec2,
+    hovered_node: Option<NodeId>,
+    pin_reorder: Option<AnyPin>,
+    focus_node: Option<(NodeId, bool)>,
     draw_order: Vec<NodeId>,
     new_wires: Option<NewWires>,
     rect_selection: Option<RectSelect>,
+    lasso_selection: Option<Vec<Pos2>>,
     selected_nodes: Vec<NodeId>,
+    wire_activity: HashMap<(OutPinId, InPinId), f32>,
+    drag_origin: DragOrigin,
 }
 
 #[derive(Clone)]
 struct SnarlStateDataHeader {
-    offset: Vec2,
+    offset: [f64; 2],
     scale: f32,
     target_scale: f32,
+    centered_zoom: bool,
     is_link_menu_open: bool,
+    wire_menu: Option<(OutPinId, InPinId)>,
+    guide_snap: Vec2,
+    hovered_node: Option<NodeId>,
+    pin_reorder: Option<AnyPin>,
+    focus_node: Option<(NodeId, bool)>,
 }
 
 impl SnarlStateData {
@@ -179,7 +418,13 @@ impl SnarlStateData {
                     offset: self.offset,
                     scale: self.scale,
                     target_scale: self.target_scale,
+                    centered_zoom: self.centered_zoom,
                     is_link_menu_open: self.is_link_menu_open,
+                    wire_menu: self.wire_menu,
+                    guide_snap: self.guide_snap,
+                    hovered_node: self.hovered_node,
+                    pin_reorder: self.pin_reorder,
+                    focus_node: self.focus_node,
                 },
             );
 
@@ -195,6 +440,12 @@ impl SnarlStateData {
                 d.remove::<RectSelect>(id);
             }
 
+            if let Some(lasso_selection) = self.lasso_selection {
+                d.insert_temp::<LassoSelect>(id, LassoSelect(lasso_selection));
+            } else {
+                d.remove::<LassoSelect>(id);
+            }
+
             if self.selected_nodes.is_empty() {
                 d.remove::<SelectedNodes>(id);
             } else {
@@ -206,6 +457,18 @@ impl SnarlStateData {
             } else {
                 d.insert_temp::<DrawOrder>(id, DrawOrder(self.draw_order));
             }
+
+            if self.wire_activity.is_empty() {
+                d.remove::<WireActivity>(id);
+            } else {
+                d.insert_temp::<WireActivity>(id, WireActivity(self.wire_activity));
+            }
+
+            if self.drag_origin.is_empty() {
+                d.remove::<DragOrigin>(id);
+            } else {
+                d.insert_temp::<DragOrigin>(id, self.drag_origin);
+            }
         });
     }
 
@@ -214,19 +477,31 @@ impl SnarlStateData {
             let small = d.get_temp::<SnarlStateDataHeader>(id)?;
             let new_wires = d.get_temp(id);
             let rect_selection = d.get_temp(id);
+            let lasso_selection = d.get_temp::<LassoSelect>(id).map(|lasso| lasso.0);
 
             let selected_nodes = d.get_temp(id).unwrap_or(SelectedNodes(Vec::new())).0;
             let draw_order = d.get_temp(id).unwrap_or(DrawOrder(Vec::new())).0;
+            let wire_activity = d.get_temp(id).unwrap_or(WireActivity(HashMap::default())).0;
+            let drag_origin = d.get_temp(id).unwrap_or_default();
 
             Some(SnarlStateData {
                 offset: small.offset,
                 scale: small.scale,
                 target_scale: small.target_scale,
+                centered_zoom: small.centered_zoom,
                 is_link_menu_open: small.is_link_menu_open,
+                wire_menu: small.wire_menu,
+                guide_snap: small.guide_snap,
+                hovered_node: small.hovered_node,
+                pin_reorder: small.pin_reorder,
+                focus_node: small.focus_node,
                 new_wires,
                 rect_selection,
+                lasso_selection,
                 selected_nodes,
                 draw_order,
+                wire_activity,
+                drag_origin,
             })
         })
     }
@@ -248,7 +523,15 @@ impl SnarlState {
         style: &SnarlStyle,
     ) -> Self {
         let Some(mut data) = SnarlStateData::load(cx, id) else {
-            return Self::initial(id, viewport, snarl, style);
+            return Self::initial(cx, id, viewport, snarl, style);
+        };
+
+        let flashes = cx.data_mut(|d| d.remove_temp::<WireFlashes>(id));
+
+        let pivot = if data.centered_zoom {
+            viewport.center()
+        } else {
+            pivot
         };
 
         let new_scale = cx.animate_value_with_time(id.with("zoom-scale"), data.target_scale, 0.1);
@@ -257,37 +540,70 @@ impl SnarlState {
         let mut dirty = if new_scale == data.scale {
             false
         } else {
-            let a = pivot + data.offset - viewport.center().to_vec2();
+            let ratio = f64::from(new_scale) / f64::from(data.scale);
+            let pivot = pivot - viewport.center();
 
-            data.offset += a * new_scale / data.scale - a;
+            for (offset, pivot) in data.offset.iter_mut().zip([pivot.x, pivot.y]) {
+                let a = f64::from(pivot) + *offset;
+                *offset += a.mul_add(ratio, -a);
+            }
             data.scale = new_scale;
             true
         };
 
+        #[allow(clippy::float_cmp)]
+        if data.centered_zoom && data.scale == data.target_scale {
+            data.centered_zoom = false;
+            dirty = true;
+        }
+
         dirty |= prune_selected_nodes(&mut data.selected_nodes, snarl);
 
+        if let Some(flashes) = flashes {
+            for wire in flashes.0 {
+                data.wire_activity.insert(wire, 1.0);
+            }
+            dirty = true;
+        }
+
         SnarlState {
             offset: data.offset,
             scale: data.scale,
             target_scale: data.target_scale,
+            centered_zoom: data.centered_zoom,
             new_wires: data.new_wires,
             is_link_menu_open: data.is_link_menu_open,
+            wire_menu: data.wire_menu,
+            guide_snap: data.guide_snap,
+            hovered_node: data.hovered_node,
+            pin_reorder: data.pin_reorder,
+            focus_node: data.focus_node,
             id,
             dirty,
             draw_order: data.draw_order,
             rect_selection: data.rect_selection,
+            lasso_selection: data.lasso_selection,
             selected_nodes: data.selected_nodes,
+            wire_activity: data.wire_activity,
+            drag_origin: data.drag_origin,
+            wire_style_override: load_wire_style_override(cx, id),
         }
     }
 
-    fn initial<T>(id: Id, viewport: Rect, snarl: &Snarl<T>, style: &SnarlStyle) -> Self {
+    fn initial<T>(
+        cx: &Context,
+        id: Id,
+        viewport: Rect,
+        snarl: &Snarl<T>,
+        style: &SnarlStyle,
+    ) -> Self {
         let mut bb = Rect::NOTHING;
 
         for (_, node) in &snarl.nodes {
             bb.extend_with(node.pos);
         }
 
-        let mut offset = Vec2::ZERO;
+        let mut offset = [0.0; 2];
         let mut scale = 1.0f32.clamp(style.get_min_scale(), style.get_max_scale());
 
         if bb.is_positive() {
@@ -302,20 +618,34 @@ impl SnarlState {
                 .min(style.get_max_scale())
                 .max(style.get_min_scale());
 
-            offset = bb.center().to_vec2() * scale;
+            let center = bb.center();
+            offset = [
+                f64::from(center.x) * f64::from(scale),
+                f64::from(center.y) * f64::from(scale),
+            ];
         }
 
         SnarlState {
             offset,
             scale,
             target_scale: scale,
+            centered_zoom: false,
+            wire_style_override: load_wire_style_override(cx, id),
             new_wires: None,
             is_link_menu_open: false,
+            wire_menu: None,
+            guide_snap: Vec2::ZERO,
+            hovered_node: None,
+            pin_reorder: None,
+            focus_node: None,
             id,
             dirty: true,
             draw_order: Vec::new(),
             rect_selection: None,
+            lasso_selection: None,
             selected_nodes: Vec::new(),
+            wire_activity: HashMap::default(),
+            drag_origin: DragOrigin::default(),
         }
     }
 
@@ -328,19 +658,35 @@ impl SnarlState {
                 offset: self.offset,
                 scale: self.scale,
                 target_scale: self.target_scale,
+                centered_zoom: self.centered_zoom,
                 new_wires: self.new_wires,
                 is_link_menu_open: self.is_link_menu_open,
+                wire_menu: self.wire_menu,
+                guide_snap: self.guide_snap,
+                hovered_node: self.hovered_node,
+                pin_reorder: self.pin_reorder,
+                focus_node: self.focus_node,
                 draw_order: self.draw_order,
                 rect_selection: self.rect_selection,
+                lasso_selection: self.lasso_selection,
                 selected_nodes: self.selected_nodes,
+                wire_activity: self.wire_activity,
+                drag_origin: self.drag_origin,
             };
             data.save(cx, self.id);
+
+            let wire_style_id = self.id.with("wire-style");
+            cx.data_mut(|d| match self.wire_style_override {
+                Some(idx) => d.insert_persisted(wire_style_id, idx),
+                None => d.remove::<u8>(wire_style_id),
+            });
         }
     }
 
     #[inline(always)]
     pub fn pan(&mut self, delta: Vec2) {
-        self.offset += delta;
+        self.offset[0] += f64::from(delta.x);
+        self.offset[1] += f64::from(delta.y);
         self.dirty = true;
     }
 
@@ -350,24 +696,57 @@ impl SnarlState {
     }
 
     #[inline(always)]
-    pub const fn offset(&self) -> Vec2 {
+    pub const fn offset_f64(&self) -> [f64; 2] {
         self.offset
     }
 
+    #[inline(always)]
+    pub const fn target_scale(&self) -> f32 {
+        self.target_scale
+    }
+
     #[inline(always)]
     pub fn set_scale(&mut self, scale: f32) {
         self.target_scale = scale;
+        self.centered_zoom = false;
+        self.dirty = true;
+    }
+
+    /// Sets target scale to be reached by zooming around viewport center.
+    #[inline(always)]
+    pub fn set_scale_centered(&mut self, scale: f32) {
+        self.target_scale = scale;
+        self.centered_zoom = true;
         self.dirty = true;
     }
 
     #[inline(always)]
     pub fn screen_pos_to_graph(&self, pos: Pos2, viewport: Rect) -> Pos2 {
-        (pos + self.offset - viewport.center().to_vec2()) / self.scale
+        let [x, y] = self.screen_pos_to_graph_f64(pos, viewport);
+        pos2(x as f32, y as f32)
+    }
+
+    #[inline(always)]
+    pub fn screen_pos_to_graph_f64(&self, pos: Pos2, viewport: Rect) -> [f64; 2] {
+        let pos = pos - viewport.center();
+        let scale = f64::from(self.scale);
+        [
+            (f64::from(pos.x) + self.offset[0]) / scale,
+            (f64::from(pos.y) + self.offset[1]) / scale,
+        ]
     }
 
     #[inline(always)]
     pub fn graph_pos_to_screen(&self, pos: Pos2, viewport: Rect) -> Pos2 {
-        pos * self.scale - self.offset + viewport.center().to_vec2()
+        self.graph_pos_to_screen_f64([f64::from(pos.x), f64::from(pos.y)], viewport)
+    }
+
+    #[inline(always)]
+    pub fn graph_pos_to_screen_f64(&self, pos: [f64; 2], viewport: Rect) -> Pos2 {
+        let scale = f64::from(self.scale);
+        let x = pos[0].mul_add(scale, -self.offset[0]) as f32;
+        let y = pos[1].mul_add(scale, -self.offset[1]) as f32;
+        pos2(x, y) + viewport.center().to_vec2()
     }
 
     #[inline(always)]
@@ -408,22 +787,66 @@ impl SnarlState {
 
     pub fn start_new_wire_in(&mut self, pin: InPinId) {
         self.new_wires = Some(NewWires::In(vec![pin]));
+        self.drag_origin.wires.clear();
         self.dirty = true;
     }
 
     pub fn start_new_wire_out(&mut self, pin: OutPinId) {
         self.new_wires = Some(NewWires::Out(vec![pin]));
+        self.drag_origin.wires.clear();
         self.dirty = true;
     }
 
     pub fn start_new_wires_in(&mut self, pins: &[InPinId]) {
         self.new_wires = Some(NewWires::In(pins.to_vec()));
+        self.drag_origin.wires.clear();
         self.dirty = true;
     }
 
     pub fn start_new_wires_out(&mut self, pins: &[OutPinId]) {
         self.new_wires = Some(NewWires::Out(pins.to_vec()));
+        self.drag_origin.wires.clear();
+        self.dirty = true;
+    }
+
+    /// Remembers wires disconnected by starting new wire drag.
+    pub fn set_detached_wires(&mut self, wires: Vec<(OutPinId, InPinId)>) {
+        self.drag_origin.wires = wires;
+        self.dirty = true;
+    }
+
+    /// Remembers positions of nodes at the start of node drag.
+    /// Does nothing if drag is already in progress.
+    pub fn record_drag_origin(&mut self, nodes: impl Iterator<Item = (NodeId, Pos2)>) {
+        if self.drag_origin.nodes.is_empty() {
+            self.drag_origin.nodes.extend(nodes);
+            self.dirty = true;
+        }
+    }
+
+    /// Positions of nodes at the start of current node drag.
+    pub fn drag_origin_nodes(&self) -> &[(NodeId, Pos2)] {
+        &self.drag_origin.nodes
+    }
+
+    /// Adds frame delta to current node drag and returns total delta since drag start.
+    pub fn advance_node_drag(&mut self, delta: Vec2) -> Vec2 {
+        self.drag_origin.delta += delta;
         self.dirty = true;
+        self.drag_origin.delta
+    }
+
+    pub fn clear_drag_origin_nodes(&mut self) {
+        if !self.drag_origin.nodes.is_empty() {
+            self.drag_origin.nodes.clear();
+            self.drag_origin.delta = Vec2::ZERO;
+            self.dirty = true;
+        }
+    }
+
+    pub fn take_drag_origin(&mut self) -> DragOrigin {
+        self.dirty |= !self.drag_origin.is_empty();
+        std::mem::take(&mut self.drag_origin)
     }
 
     pub fn add_new_wire_in(&mut self, pin: InPinId) {
@@ -494,6 +917,69 @@ impl SnarlState {
         self.is_link_menu_open
     }
 
+    pub(crate) fn open_wire_menu(&mut self, from: OutPinId, to: InPinId) {
+        self.wire_menu = Some((from, to));
+        self.dirty = true;
+    }
+
+    pub(crate) fn close_wire_menu(&mut self) {
+        if self.wire_menu.is_some() {
+            self.wire_menu = None;
+            self.dirty = true;
+        }
+    }
+
+    pub(crate) const fn wire_menu(&self) -> Option<(OutPinId, InPinId)> {
+        self.wire_menu
+    }
+
+    pub(crate) const fn guide_snap(&self) -> Vec2 {
+        self.guide_snap
+    }
+
+    pub(crate) const fn hovered_node(&self) -> Option<NodeId> {
+        self.hovered_node
+    }
+
+    pub(crate) fn set_hovered_node(&mut self, node: Option<NodeId>) {
+        if self.hovered_node != node {
+            self.hovered_node = node;
+            self.dirty = true;
+        }
+    }
+
+    pub(crate) fn start_pin_reorder(&mut self, pin: AnyPin) {
+        self.pin_reorder = Some(pin);
+        self.dirty = true;
+    }
+
+    pub(crate) fn stop_pin_reorder(&mut self) {
+        self.dirty |= self.pin_reorder.is_some();
+        self.pin_reorder = None;
+    }
+
+    pub(crate) const fn pin_reorder(&self) -> Option<AnyPin> {
+        self.pin_reorder
+    }
+
+    pub(crate) fn set_focus_node(&mut self, node: Option<(NodeId, bool)>) {
+        if self.focus_node != node {
+            self.focus_node = node;
+            self.dirty = true;
+        }
+    }
+
+    pub(crate) const fn focus_node(&self) -> Option<(NodeId, bool)> {
+        self.focus_node
+    }
+
+    pub(crate) fn set_guide_snap(&mut self, snap: Vec2) {
+        if self.guide_snap != snap {
+            self.guide_snap = snap;
+            self.dirty = true;
+        }
+    }
+
     pub(crate) fn update_draw_order<T>(&mut self, snarl: &Snarl<T>) -> Vec<NodeId> {
         let mut node_ids = snarl
             .nodes
@@ -525,7 +1011,7 @@ impl SnarlState {
     }
 
     pub fn set_offset(&mut self, offset: Vec2) {
-        self.offset = offset;
+        self.offset = [f64::from(offset.x), f64::from(offset.y)];
         self.dirty = true;
     }
 
@@ -533,6 +1019,11 @@ impl SnarlState {
         &self.selected_nodes
     }
 
+    /// Most recently selected node.
+    pub fn primary_selection(&self) -> Option<NodeId> {
+        self.selected_nodes.last().copied()
+    }
+
     pub fn select_one_node(&mut self, reset: bool, node: NodeId) {
         if reset {
             if self.selected_nodes[..] == [node] {
@@ -598,6 +1089,15 @@ impl SnarlState {
         self.rect_selection.is_some()
     }
 
+    /// Returns `true` if node, new wire, rect or lasso selection or pin reorder drag is in progress.
+    pub fn is_dragging(&self) -> bool {
+        (self.new_wires.is_some() && !self.is_link_menu_open)
+            || self.rect_selection.is_some()
+            || self.lasso_selection.is_some()
+            || !self.drag_origin.nodes.is_empty()
+            || self.pin_reorder.is_some()
+    }
+
     pub fn update_rect_selection(&mut self, pos: Pos2) {
         if let Some(rect_selection) = &mut self.rect_selection {
             rect_selection.current = pos;
@@ -605,10 +1105,144 @@ impl SnarlState {
         }
     }
 
+    /// Returns `true` if rect selection is dragged from left to right.
+    pub fn is_rect_selection_rightward(&self) -> bool {
+        self.rect_selection
+            .is_some_and(|rect| rect.current.x >= rect.origin.x)
+    }
+
     pub fn rect_selection(&self) -> Option<Rect> {
         let rect = self.rect_selection?;
         Some(Rect::from_two_pos(rect.origin, rect.current))
     }
+
+    pub fn start_lasso_selection(&mut self, pos: Pos2) {
+        self.dirty |= self.lasso_selection.is_none();
+        self.lasso_selection = Some(vec![pos]);
+    }
+
+    pub fn stop_lasso_selection(&mut self) {
+        self.dirty |= self.lasso_selection.is_some();
+        self.lasso_selection = None;
+    }
+
+    pub const fn is_lasso_selection(&self) -> bool {
+        self.lasso_selection.is_some()
+    }
+
+    /// Appends point to lasso path.
+    ///
+    /// Points closer than `min_distance` to the last one are skipped,
+    /// and points continuing straight segment replace its end.
+    pub fn update_lasso_selection(&mut self, pos: Pos2, min_distance: f32) {
+        let Some(points) = &mut self.lasso_selection else {
+            return;
+        };
+
+        if points
+            .last()
+            .is_some_and(|last| last.distance(pos) < min_distance)
+        {
+            return;
+        }
+
+        if let [.., a, b] = points[..] {
+            let straight = (b - a).normalized().dot((pos - b).normalized()) > 0.999;
+            if straight {
+                points.pop();
+            }
+        }
+
+        points.push(pos);
+
+        if points.len() > MAX_LASSO_POINTS {
+            let last = points.len() - 1;
+            let mut idx = 0;
+            points.retain(|_| {
+                let keep = idx % 2 == 0 || idx == last;
+                idx += 1;
+                keep
+            });
+        }
+
+        self.dirty = true;
+    }
+
+    pub fn lasso_selection(&self) -> Option<&[Pos2]> {
+        self.lasso_selection.as_deref()
+    }
+
+    /// Records whether pointer is over this snarl's viewport in current pass.
+    ///
+    /// Nested snarls are shown after their parent,
+    /// so the innermost hovered snarl is recorded last.
+    /// Returns `true` if this snarl was the innermost hovered snarl in previous pass.
+    pub fn update_hovered(&self, cx: &Context, hovered: bool) -> bool {
+        let pass = cx.cumulative_pass_nr();
+
+        cx.data_mut(|d| {
+            let state = d.get_temp_mut_or_insert_with(Id::NULL, || HoveredSnarl {
+                pass,
+                current: None,
+                previous: None,
+            });
+
+            if state.pass != pass {
+                state.pass = pass;
+                state.previous = state.current.take();
+            }
+
+            if hovered {
+                state.current = Some(self.id);
+            }
+
+            state.previous.is_none() || state.previous == Some(self.id)
+        })
+    }
+
+    /// Returns activity of the wire in range `0..=1`.
+    pub fn wire_activity(&self, out_pin: OutPinId, in_pin: InPinId) -> f32 {
+        self.wire_activity
+            .get(&(out_pin, in_pin))
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// Returns default style of wires zoomed to current scale.
+    ///
+    /// Style chosen with [`SnarlStyle::wire_style_shortcut`] overrides [`SnarlStyle::wire_style`].
+    pub fn wire_style(&self, style: &SnarlStyle) -> WireStyle {
+        match self.wire_style_override {
+            Some(idx) => cycled_wire_style(idx, style).zoomed(self.scale),
+            None => style.get_wire_style(self.scale),
+        }
+    }
+
+    /// Switches default style of wires to the next one in the cycle.
+    pub fn cycle_wire_style(&mut self, style: &SnarlStyle) {
+        let idx = self
+            .wire_style_override
+            .unwrap_or_else(|| wire_style_index(style.get_wire_style(1.0)));
+
+        self.wire_style_override = Some((idx + 1) % WIRE_STYLES_CYCLED);
+        self.dirty = true;
+    }
+
+    /// Decays activity of all wires by `amount`.
+    /// Returns `true` if some wires are still active.
+    pub fn decay_wire_activity(&mut self, amount: f32) -> bool {
+        if self.wire_activity.is_empty() {
+            return false;
+        }
+
+        self.wire_activity.retain(|_, activity| {
+            *activity -= amount;
+            *activity > 0.0
+        });
+        self.dirty = true;
+
+        !self.wire_activity.is_empty()
+    }
 }
 
 impl<T> Snarl<T> {
@@ -633,4 +1267,301 @@ impl<T> Snarl<T> {
                 .0
         })
     }
+
+    /// Returns primary selected node in the UI.
+    /// Primary node is the most recently selected one.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::get_primary_selection_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn get_primary_selection(id_salt: impl Hash, ui: &mut Ui) -> Option<NodeId> {
+        Self::get_primary_selection_at(id_salt, ui.id(), ui.ctx())
+    }
+
+    /// Returns primary selected node in the UI.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn get_primary_selection_at(id_salt: impl Hash, id: Id, cx: &Context) -> Option<NodeId> {
+        let snarl_id = id.with(id_salt);
+
+        cx.data(|d| {
+            d.get_temp::<SelectedNodes>(snarl_id)
+                .and_then(|selected| selected.0.last().copied())
+        })
+    }
+
+    /// Returns position of the node's anchor in graph space.
+    ///
+    /// Anchors are computed from layout of the last frame.
+    /// Nodes that were not drawn in it use size and pin positions cached earlier.
+    ///
+    /// Returns `None` if node does not exist or was never laid out.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::node_anchor_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn node_anchor(
+        &self,
+        id_salt: impl Hash,
+        ui: &Ui,
+        node: NodeId,
+        anchor: NodeAnchor,
+    ) -> Option<Pos2> {
+        self.node_anchor_at(id_salt, ui.id(), ui.ctx(), node, anchor)
+    }
+
+    /// Returns position of the node's anchor in graph space.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn node_anchor_at(
+        &self,
+        id_salt: impl Hash,
+        id: Id,
+        cx: &Context,
+        node: NodeId,
+        anchor: NodeAnchor,
+    ) -> Option<Pos2> {
+        let snarl_id = id.with(id_salt);
+        let node_info = self.nodes.get(node.0)?;
+
+        let rect = NodeState::stored_graph_rect(
+            cx,
+            snarl_id.with(("snarl-node", node)),
+            node_info.pos,
+            node_info.open,
+        )?;
+
+        match anchor {
+            NodeAnchor::TopLeft => Some(rect.left_top()),
+            NodeAnchor::TopRight => Some(rect.right_top()),
+            NodeAnchor::BottomLeft => Some(rect.left_bottom()),
+            NodeAnchor::BottomRight => Some(rect.right_bottom()),
+            NodeAnchor::Center => Some(rect.center()),
+            NodeAnchor::Input(input) => {
+                let pin = InPinId { node, input };
+                let offset = cx.data(|d| {
+                    d.get_temp::<PinOffsets>(snarl_id.with("pin-offsets"))
+                        .and_then(|offsets| offsets.inputs.get(&pin).copied())
+                })?;
+                Some(node_info.pos + offset)
+            }
+            NodeAnchor::Output(output) => {
+                let pin = OutPinId { node, output };
+                let offset = cx.data(|d| {
+                    d.get_temp::<PinOffsets>(snarl_id.with("pin-offsets"))
+                        .and_then(|offsets| offsets.outputs.get(&pin).copied())
+                })?;
+                Some(node_info.pos + offset)
+            }
+        }
+    }
+
+    /// Returns path of the wire in graph space.
+    ///
+    /// Path is built from geometry of the last frame,
+    /// following wire style and route around nodes the wire was drawn with.
+    /// Use it to place labels, arrowheads and other decorations on wires.
+    ///
+    /// Returns `None` if wire does not exist or was not drawn in the last frame.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::wire_path_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn wire_path(
+        &self,
+        id_salt: impl Hash,
+        ui: &Ui,
+        from: OutPinId,
+        to: InPinId,
+    ) -> Option<WirePath> {
+        self.wire_path_at(id_salt, ui.id(), ui.ctx(), from, to)
+    }
+
+    /// Returns path of the wire in graph space.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn wire_path_at(
+        &self,
+        id_salt: impl Hash,
+        id: Id,
+        cx: &Context,
+        from: OutPinId,
+        to: InPinId,
+    ) -> Option<WirePath> {
+        let snarl_id = id.with(id_salt);
+        let wire = Wire {
+            out_pin: from,
+            in_pin: to,
+        };
+
+        if !self.wires.contains(&wire) {
+            return None;
+        }
+
+        cx.data(|d| {
+            d.get_temp::<Arc<WireGeometries>>(snarl_id.with("wire-geometries"))
+                .and_then(|geometries| geometries.get(&wire).map(|geometry| geometry.path()))
+        })
+    }
+
+    /// Marks the wire as active.
+    /// Active wires are drawn brighter and thicker,
+    /// activity decays over time with [`SnarlStyle::wire_activity_decay`] rate.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::flash_wire_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn flash_wire(id_salt: impl Hash, ui: &Ui, from: OutPinId, to: InPinId) {
+        Self::flash_wire_at(id_salt, ui.id(), ui.ctx(), from, to);
+    }
+
+    /// Marks the wire as active.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn flash_wire_at(id_salt: impl Hash, id: Id, cx: &Context, from: OutPinId, to: InPinId) {
+        let snarl_id = id.with(id_salt);
+
+        cx.data_mut(|d| {
+            d.get_temp_mut_or_default::<WireFlashes>(snarl_id)
+                .0
+                .push((from, to));
+        });
+        cx.request_repaint();
+    }
+
+    /// Pans the view so that its center is at `center` in graph space.
+    ///
+    /// Applied on the next [`Snarl::show`] call, e.g. when external scrollbar is dragged.
+    /// Center is clamped to bounds of the graph, see [`SnarlResponse::graph_bounds`](super::SnarlResponse::graph_bounds).
+    /// Zoom is not changed.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::set_view_center_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn set_view_center(id_salt: impl Hash, ui: &Ui, center: Pos2) {
+        Self::set_view_center_at(id_salt, ui.id(), ui.ctx(), center);
+    }
+
+    /// Pans the view so that its center is at `center` in graph space.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn set_view_center_at(id_salt: impl Hash, id: Id, cx: &Context, center: Pos2) {
+        let snarl_id = id.with(id_salt);
+
+        cx.data_mut(|d| d.insert_temp(snarl_id.with("view-center"), center));
+        cx.request_repaint();
+    }
+
+    /// Zooms the view to `scale` around its center.
+    ///
+    /// Applied on the next [`Snarl::show`] call.
+    /// Scale is clamped to [`SnarlStyle::min_scale`] and [`SnarlStyle::max_scale`]
+    /// and animated the same way as zooming with mouse wheel.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::zoom_to_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn zoom_to(id_salt: impl Hash, ui: &Ui, scale: f32) {
+        Self::zoom_to_at(id_salt, ui.id(), ui.ctx(), scale);
+    }
+
+    /// Zooms the view to `scale` around its center.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn zoom_to_at(id_salt: impl Hash, id: Id, cx: &Context, scale: f32) {
+        request_zoom(id.with(id_salt), cx, ZoomRequest::To(scale));
+    }
+
+    /// Zooms the view to scale `1.0` and centers it on the graph.
+    ///
+    /// Applied on the next [`Snarl::show`] call.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::zoom_reset_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn zoom_reset(id_salt: impl Hash, ui: &Ui) {
+        Self::zoom_reset_at(id_salt, ui.id(), ui.ctx());
+    }
+
+    /// Zooms the view to scale `1.0` and centers it on the graph.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn zoom_reset_at(id_salt: impl Hash, id: Id, cx: &Context) {
+        request_zoom(id.with(id_salt), cx, ZoomRequest::Reset);
+    }
+
+    /// Zooms the view in by one step around its center.
+    ///
+    /// Applied on the next [`Snarl::show`] call.
+    /// Steps requested before it are added up,
+    /// and steps requested while zoom is animated are accumulated.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::zoom_in_step_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn zoom_in_step(id_salt: impl Hash, ui: &Ui) {
+        Self::zoom_in_step_at(id_salt, ui.id(), ui.ctx());
+    }
+
+    /// Zooms the view in by one step around its center.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn zoom_in_step_at(id_salt: impl Hash, id: Id, cx: &Context) {
+        request_zoom(id.with(id_salt), cx, ZoomRequest::Steps(1));
+    }
+
+    /// Zooms the view out by one step around its center.
+    ///
+    /// Applied on the next [`Snarl::show`] call.
+    /// Steps requested before it are added up,
+    /// and steps requested while zoom is animated are accumulated.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::zoom_out_step_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn zoom_out_step(id_salt: impl Hash, ui: &Ui) {
+        Self::zoom_out_step_at(id_salt, ui.id(), ui.ctx());
+    }
+
+    /// Zooms the view out by one step around its center.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn zoom_out_step_at(id_salt: impl Hash, id: Id, cx: &Context) {
+        request_zoom(id.with(id_salt), cx, ZoomRequest::Steps(-1));
+    }
+
+    /// Zooms and pans the view to frame bounding box of selected nodes,
+    /// leaving `margin` points around it.
+    ///
+    /// Applied on the next [`Snarl::show`] call.
+    /// Zoom is animated and clamped the same way as in [`Snarl::zoom_to`].
+    /// If no nodes are selected, whole graph is framed
+    /// unless [`SnarlStyle::fit_all_without_selection`] is disabled.
+    ///
+    /// Use `id_salt` and [`Ui`] that were used in [`Snarl::show`] method.
+    ///
+    /// If same [`Ui`] is not available, use [`Snarl::fit_to_selection_at`] and provide `id` of the [`Ui`] used in [`Snarl::show`] method.
+    pub fn fit_to_selection(id_salt: impl Hash, ui: &Ui, margin: f32) {
+        Self::fit_to_selection_at(id_salt, ui.id(), ui.ctx(), margin);
+    }
+
+    /// Zooms and pans the view to frame bounding box of selected nodes,
+    /// leaving `margin` points around it.
+    ///
+    /// Use `id_salt` as well as [`Id`] and [`Context`] of the [`Ui`] that were used in [`Snarl::show`] method.
+    pub fn fit_to_selection_at(id_salt: impl Hash, id: Id, cx: &Context, margin: f32) {
+        request_zoom(id.with(id_salt), cx, ZoomRequest::FitSelection(margin));
+    }
+}
+
+/// Requests zoom on the next frame, adding steps to ones requested before.
+fn request_zoom(snarl_id: Id, cx: &Context, request: ZoomRequest) {
+    let id = snarl_id.with("zoom-request");
+    cx.data_mut(|d| {
+        let request = d
+            .get_temp::<ZoomRequest>(id)
+            .map_or(request, |prev| prev.then(request));
+        d.insert_temp(id, request);
+    });
+    cx.request_repaint();
 }
@@ -1,12 +1,23 @@
 use crate::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
-use egui::{Color32, Painter, Pos2, Rect, Style, Ui};
+use egui::{Color32, Id, Painter, Pos2, Rect, Style, Ui, WidgetText};
 
-use super::{pin::AnyPins, BackgroundPattern, NodeLayout, PinInfo, SnarlStyle, Viewport};
+use super::{
+    pin::{AnyPin, AnyPins},
+    BackgroundPattern, NodeLayout, PinInfo, SnarlStyle, Viewport,
+};
 
 /// `SnarlViewer` is a trait for viewing a Snarl.
 ///
 /// It can extract necessary data from the nodes and controls their
 /// response to certain events.
+///
+/// Methods that change the graph, like [`SnarlViewer::connect`], [`SnarlViewer::remove_node`]
+/// or [`SnarlViewer::on_nodes_moved`], are called synchronously during [`Snarl::show`],
+/// so external stores can be updated as soon as graph changes.
+/// Within a frame they are called in the order [`Snarl::show`] renders the graph,
+/// e.g. node's input pins before its body and output pins,
+/// which is not necessarily the order of user interactions.
+/// They must not call [`Snarl::show`] again.
 pub trait SnarlViewer<T> {
     /// Returns title of the node.
     fn title(&mut self, node: &T) -> String;
@@ -75,6 +86,10 @@ pub trait SnarlViewer<T> {
     fn inputs(&mut self, node: &T) -> usize;
 
     /// Renders the node's input.
+    ///
+    /// `pin` lists wires connected to the input at the start of the frame,
+    /// so widgets that edit default value of the input can be skipped
+    /// when [`InPin::is_connected`] returns `true`.
     fn show_input(&mut self, pin: &InPin, ui: &mut Ui, scale: f32, snarl: &mut Snarl<T>)
         -> PinInfo;
 
@@ -84,6 +99,8 @@ pub trait SnarlViewer<T> {
     fn outputs(&mut self, node: &T) -> usize;
 
     /// Renders the node's output.
+    ///
+    /// `pin` lists wires connected to the output at the start of the frame.
     fn show_output(
         &mut self,
         pin: &OutPin,
@@ -113,6 +130,43 @@ pub trait SnarlViewer<T> {
         let _ = (node, inputs, outputs, ui, scale, snarl);
     }
 
+    /// Returns key of the node's body content to cache its rendering.
+    ///
+    /// While key stays the same and node is not resized or zoomed, shapes painted by
+    /// [`SnarlViewer::show_body`] are replayed instead of calling it.
+    /// Return new key whenever body's content changes.
+    /// Body is recorded only when it is fully visible and not faded.
+    ///
+    /// Cached body has no widgets, so it can't be interacted with.
+    /// Return `None` for bodies with interactive widgets.
+    /// Shapes of each cached body are kept in egui's memory until key changes or node is removed,
+    /// trading memory for time of [`SnarlViewer::show_body`] calls.
+    /// Painting to other layers, e.g. popups, is not recorded.
+    ///
+    /// Ignored when [`SnarlStyle::body_cache`] is disabled.
+    /// By default returns `None`.
+    #[inline]
+    fn body_cache_key(&mut self, node: NodeId, snarl: &Snarl<T>) -> Option<u64> {
+        let _ = (node, snarl);
+        None
+    }
+
+    /// Checks if node's body must be shown even when the node is not drawn in the frame.
+    ///
+    /// Nodes are skipped when there are more of them than [`SnarlStyle::max_nodes_per_frame`].
+    /// If this returns `true` for such node, [`SnarlViewer::show_body`] is still called
+    /// with invisible, non-interactive [`Ui`] of zero size,
+    /// so viewers that update their state in it keep working.
+    ///
+    /// This is called for every skipped node each frame and each `true` costs a full body layout,
+    /// undoing savings of skipping the node. Return `true` only for nodes that really need it.
+    /// By default returns `false`.
+    #[inline]
+    fn body_needs_update(&mut self, node: NodeId, snarl: &Snarl<T>) -> bool {
+        let _ = (node, snarl);
+        false
+    }
+
     /// Checks if node has something to show in footer - below pins and body.
     #[inline]
     fn has_footer(&mut self, node: &T) -> bool {
@@ -151,6 +205,132 @@ pub trait SnarlViewer<T> {
         let _ = (node, ui_rect, graph_rect, ui, scale, snarl);
     }
 
+    /// Returns stable key of the node used to derive [`Id`]s of widgets inside of it.
+    ///
+    /// By default ids are derived from [`NodeId`], which may be reassigned when graph is saved and loaded.
+    /// Return key stored in the node value, e.g. UUID, to keep state of widgets
+    /// like collapsing headers, combo boxes and scroll areas across sessions.
+    ///
+    /// Keys must be unique among nodes of the graph and must not change while node exists.
+    #[inline]
+    fn node_key(&mut self, node: &T) -> Option<Id> {
+        let _ = node;
+        None
+    }
+
+    /// Returns position of the pin's wire attachment point in screen space.
+    ///
+    /// `node_rect` is the screen rect of the node's frame.
+    /// Override to attach wires to nodes of custom shape, e.g. to the rim of a circular node.
+    /// Pin shape, wires and pin hit-testing all use returned position.
+    ///
+    /// By default, returns `None` and pins are placed on the edges of the node.
+    #[inline]
+    fn pin_anchor(&mut self, pin: AnyPin, node_rect: Rect, snarl: &Snarl<T>) -> Option<Pos2> {
+        let _ = (pin, node_rect, snarl);
+        None
+    }
+
+    /// Returns opacity of the node in range `0..=1`.
+    ///
+    /// Opacity multiplies alpha of the node's frame, header, body, pins and wires.
+    /// Wires use average opacity of the nodes they connect.
+    /// It affects only painting, dimmed nodes are still interactive.
+    #[inline]
+    fn node_opacity(&mut self, node: NodeId, snarl: &Snarl<T>) -> f32 {
+        let _ = (node, snarl);
+        1.0
+    }
+
+    /// Checks if user can interact with the node.
+    ///
+    /// Non-interactive node can't be moved or resized,
+    /// wires can't be started from or connected to its pins.
+    /// Its pins are still drawn and reject dropped wires.
+    /// Node is drawn with a subtle tint and its content stays interactive.
+    ///
+    /// By default returns `true`.
+    #[inline]
+    fn node_interactive(&mut self, node: NodeId, snarl: &Snarl<T>) -> bool {
+        let _ = (node, snarl);
+        true
+    }
+
+    /// Returns fixed ratio of width to height of the node.
+    ///
+    /// Node is grown to keep the ratio after its content is laid out,
+    /// and resize handle keeps it while dragged.
+    /// Resized node is clamped to [`SnarlStyle::node_min_size`] and [`SnarlStyle::node_max_size`]
+    /// keeping the ratio where possible.
+    ///
+    /// By default returns `None` - node is resized freely.
+    #[inline]
+    fn node_aspect_ratio(&mut self, node: NodeId, snarl: &Snarl<T>) -> Option<f32> {
+        let _ = (node, snarl);
+        None
+    }
+
+    /// Called when node's body is double-clicked.
+    ///
+    /// Double-clicks on the node's header are not reported.
+    /// Can be used to open node's properties or to enter a subgraph.
+    #[inline]
+    fn on_node_double_click(&mut self, node: NodeId, ui: &mut Ui, snarl: &mut Snarl<T>) {
+        let _ = (node, ui, snarl);
+    }
+
+    /// Called when dragged nodes are dropped at new positions.
+    ///
+    /// Receives moved nodes with their positions before the drag,
+    /// new positions, snapped to grid if enabled, are already set in `snarl`.
+    /// Not called when drag is canceled or nodes are returned to their positions.
+    ///
+    /// Nodes moved by the application, e.g. animated with [`Snarl::animate_to_layout`],
+    /// are not reported, as their new positions are already known to it.
+    #[inline]
+    fn on_nodes_moved(&mut self, moved: &[(NodeId, Pos2)], snarl: &mut Snarl<T>) {
+        let _ = (moved, snarl);
+    }
+
+    /// Called when payload of egui's drag-and-drop is released over the graph.
+    ///
+    /// `pos` is the drop position in graph space.
+    /// Payload is not consumed by the graph,
+    /// take it with [`egui::DragAndDrop::take_payload`] to create a node from it.
+    ///
+    /// # Examples
+    ///
+    /// Palette beside the graph sets payload when its entry is dragged,
+    /// and viewer creates node from it.
+    ///
+    /// ```
+    /// # use egui_snarl::Snarl;
+    /// #[derive(Clone)]
+    /// enum Kind {
+    ///     Number,
+    ///     Sink,
+    /// }
+    ///
+    /// fn show_palette(ui: &mut egui::Ui) {
+    ///     for (name, kind) in [("Number", Kind::Number), ("Sink", Kind::Sink)] {
+    ///         ui.dnd_drag_source(egui::Id::new(name), kind, |ui| {
+    ///             ui.label(name);
+    ///         });
+    ///     }
+    /// }
+    ///
+    /// // Body of `SnarlViewer::on_drop_payload`.
+    /// fn on_drop_payload(pos: egui::Pos2, ui: &mut egui::Ui, snarl: &mut Snarl<Kind>) {
+    ///     if let Some(kind) = egui::DragAndDrop::take_payload::<Kind>(ui.ctx()) {
+    ///         snarl.insert_node(pos, (*kind).clone());
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    fn on_drop_payload(&mut self, pos: Pos2, ui: &mut Ui, snarl: &mut Snarl<T>) {
+        let _ = (pos, ui, snarl);
+    }
+
     /// Checks if node has something to show in on-hover popup.
     #[inline]
     fn has_on_hover_popup(&mut self, node: &T) -> bool {
@@ -172,6 +352,17 @@ pub trait SnarlViewer<T> {
         let _ = (node, inputs, outputs, ui, scale, snarl);
     }
 
+    /// Returns tooltip text for the hovered node.
+    ///
+    /// Tooltip is shown when pointer rests on node's frame,
+    /// not on its pins or widgets, and is not shown while dragging.
+    /// By default returns `None` and no tooltip is shown.
+    #[inline]
+    fn node_tooltip(&mut self, node: NodeId, snarl: &Snarl<T>) -> Option<WidgetText> {
+        let _ = (node, snarl);
+        None
+    }
+
     /// Checks if wire has something to show in widget.
     /// This may not be called if wire is invisible.
     #[inline]
@@ -254,6 +445,108 @@ pub trait SnarlViewer<T> {
         let _ = (node, inputs, outputs, ui, scale, snarl);
     }
 
+    /// Returns tooltip text for the hovered wire.
+    ///
+    /// Tooltip is not shown while dragging.
+    /// By default returns `None` and no tooltip is shown.
+    #[inline]
+    fn wire_tooltip(
+        &mut self,
+        from: &OutPinId,
+        to: &InPinId,
+        snarl: &Snarl<T>,
+    ) -> Option<WidgetText> {
+        let _ = (from, to, snarl);
+        None
+    }
+
+    /// Returns label drawn at the middle of the wire.
+    ///
+    /// Label is drawn upright on a small chip above wires
+    /// and is hidden when graph is zoomed out below [`SnarlStyle::wire_label_min_scale`].
+    /// By default returns `None` and no label is drawn.
+    #[inline]
+    fn wire_label(
+        &mut self,
+        from: &OutPinId,
+        to: &InPinId,
+        snarl: &Snarl<T>,
+    ) -> Option<WidgetText> {
+        let _ = (from, to, snarl);
+        None
+    }
+
+    /// Returns stacking order of the wire.
+    ///
+    /// Wires with greater values are drawn above wires with lower ones.
+    /// Hovered wire and wires of selected, highlighted or active nodes
+    /// are still drawn above the rest.
+    /// By default returns `0`.
+    #[inline]
+    fn wire_z(&mut self, from: &OutPinId, to: &InPinId, snarl: &Snarl<T>) -> i32 {
+        let _ = (from, to, snarl);
+        0
+    }
+
+    /// Called when wire is double-clicked.
+    ///
+    /// `pos` is the clicked position on the wire in graph space.
+    /// Can be used to insert a node into the wire.
+    /// Not called while dragging or for single clicks.
+    #[inline]
+    fn on_wire_double_click(
+        &mut self,
+        from: OutPinId,
+        to: InPinId,
+        pos: Pos2,
+        ui: &mut Ui,
+        snarl: &mut Snarl<T>,
+    ) {
+        let _ = (from, to, pos, ui, snarl);
+    }
+
+    /// Checks if wire has a context menu.
+    ///
+    /// If this returns `false`, right-clicking the wire disconnects it.
+    #[inline]
+    fn has_wire_menu(&mut self, from: &OutPinId, to: &InPinId, snarl: &Snarl<T>) -> bool {
+        let _ = (from, to, snarl);
+        true
+    }
+
+    /// Show context menu for the wire.
+    ///
+    /// By default this shows single "Disconnect" item.
+    #[inline]
+    fn show_wire_menu(
+        &mut self,
+        from: OutPinId,
+        to: InPinId,
+        ui: &mut Ui,
+        scale: f32,
+        snarl: &mut Snarl<T>,
+    ) {
+        let _ = scale;
+        if ui.button("Disconnect").clicked() {
+            let out_pin = OutPin::new(snarl, from);
+            let in_pin = InPin::new(snarl, to);
+            self.disconnect(&out_pin, &in_pin, snarl);
+            ui.close_menu();
+        }
+    }
+
+    /// Checks if new wire can connect `from` pin to `to` pin.
+    ///
+    /// Called while new wire is dragged to highlight compatible pins and to pick pin to snap to,
+    /// and when it is dropped, before [`SnarlViewer::connect`].
+    /// Rejected pins get the same feedback as pins that break rules set in the style.
+    /// By default every connection allowed by the style is accepted.
+    #[inline]
+    fn can_connect(&mut self, from: &OutPin, to: &InPin, snarl: &Snarl<T>) -> bool {
+        let _ = (from, to, snarl);
+        true
+    }
+
     /// Asks the viewer to connect two pins.
     ///
     /// This is usually happens when user drags a wire from one node's output pin to another node's input pin or vice versa.
@@ -269,6 +562,27 @@ pub trait SnarlViewer<T> {
         snarl.disconnect(from.id, to.id);
     }
 
+    /// Asks the viewer to remove the node.
+    ///
+    /// This is usually happens when selected nodes are deleted with keyboard,
+    /// see [`SnarlStyle::keyboard_delete`].
+    /// By default this method calls [`Snarl::remove_node`], removing wires of the node as well.
+    #[inline]
+    fn remove_node(&mut self, node: NodeId, snarl: &mut Snarl<T>) {
+        snarl.remove_node(node);
+    }
+
+    /// Asks the viewer to disconnect all wires of the node.
+    ///
+    /// This is usually happens when "Disconnect all" is clicked in node's context menu,
+    /// see [`SnarlStyle::node_menu_disconnect_all`].
+    /// By default this method calls [`Snarl::disconnect_node`].
+    /// Override to inspect severed wires it returns.
+    #[inline]
+    fn disconnect_node(&mut self, node: NodeId, snarl: &mut Snarl<T>) {
+        snarl.disconnect_node(node);
+    }
+
     /// Asks the viewer to disconnect all wires from the output pin.
     ///
     /// This is usually happens when right-clicking on an output pin.
@@ -287,6 +601,30 @@ pub trait SnarlViewer<T> {
         snarl.drop_inputs(pin.id);
     }
 
+    /// Called when input pin is dragged to another place in its node's inputs.
+    ///
+    /// Input at index `from` should be moved to index `to`, shifting inputs in between.
+    /// This is called only if [`SnarlStyle::pin_reorder`] is enabled
+    /// and pin is dragged with `Alt` held.
+    /// Viewer is responsible for reordering node's data together with wires of the moved inputs.
+    /// Does nothing by default.
+    #[inline]
+    fn reorder_input(&mut self, node: NodeId, from: usize, to: usize, snarl: &mut Snarl<T>) {
+        let _ = (node, from, to, snarl);
+    }
+
+    /// Called when output pin is dragged to another place in its node's outputs.
+    ///
+    /// Output at index `from` should be moved to index `to`, shifting outputs in between.
+    /// This is called only if [`SnarlStyle::pin_reorder`] is enabled
+    /// and pin is dragged with `Alt` held.
+    /// Viewer is responsible for reordering node's data together with wires of the moved outputs.
+    /// Does nothing by default.
+    #[inline]
+    fn reorder_output(&mut self, node: NodeId, from: usize, to: usize, snarl: &mut Snarl<T>) {
+        let _ = (node, from, to, snarl);
+    }
+
     /// Draws the node's input pin.
     ///
     /// This method is called after [`SnarlViewer::show_input`] and can be used to draw the pin shape.
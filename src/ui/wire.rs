@@ -1,6 +1,16 @@
-use std::f32;
+use std::{
+    collections::HashMap,
+    f32,
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Arc,
+};
 
-use egui::{epaint::PathShape, pos2, Color32, Pos2, Rect, Shape, Stroke, Ui};
+use egui::{
+    epaint::{PathShape, Tessellator},
+    pos2, Color32, Mesh, Pos2, Rect, Shape, Stroke, Ui, Vec2,
+};
+
+use crate::{InPinId, OutPinId, Wire};
 
 /// Layer where wires are rendered.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -36,6 +46,18 @@ pub enum WireStyle {
         /// Radius of corners in wire.
         corner_radius: f32,
     },
+
+    /// Draw wire as straight lines with 90 degree turns
+    /// routed around nodes on the way.
+    /// Distance to nodes is controlled by [`SnarlStyle::wire_avoidance_margin`].
+    ///
+    /// Wire is drawn as [`WireStyle::AxisAligned`] if route is not found.
+    ///
+    /// [`SnarlStyle::wire_avoidance_margin`]: crate::ui::SnarlStyle::wire_avoidance_margin
+    Avoid {
+        /// Radius of corners in wire.
+        corner_radius: f32,
+    },
 }
 
 pub fn pick_wire_style(
@@ -49,6 +71,14 @@ pub fn pick_wire_style(
         (Some(WireStyle::Bezier5), Some(WireStyle::Bezier5)) => WireStyle::Bezier5,
         (Some(WireStyle::Bezier3 | WireStyle::Bezier5), Some(WireStyle::Bezier3))
         | (Some(WireStyle::Bezier3), Some(WireStyle::Bezier5)) => WireStyle::Bezier3,
+        (
+            Some(WireStyle::Avoid { corner_radius: a }),
+            Some(WireStyle::Avoid { corner_radius: b }),
+        ) => WireStyle::Avoid {
+            corner_radius: a.max(b),
+        },
+        (Some(WireStyle::Avoid { corner_radius }), Some(_))
+        | (Some(_), Some(WireStyle::Avoid { corner_radius })) => WireStyle::Avoid { corner_radius },
         (
             Some(WireStyle::AxisAligned { corner_radius: a }),
             Some(WireStyle::AxisAligned { corner_radius: b }),
@@ -79,6 +109,14 @@ fn adjust_frame_size(
     frame_size
 }
 
+/// Swaps coordinates of the point.
+///
+/// Wires between pins on top and bottom edges of nodes
+/// are built as horizontal wires in transposed space.
+pub const fn transpose(pos: Pos2) -> Pos2 {
+    pos2(pos.y, pos.x)
+}
+
 /// Returns 5th degree bezier curve control points for the wire
 fn wire_bezier_5(frame_size: f32, from: Pos2, to: Pos2) -> [Pos2; 6] {
     let from_norm_x = frame_size;
@@ -211,20 +249,24 @@ pub fn draw_wire(
     frame_size: f32,
     upscale: bool,
     downscale: bool,
+    vertical: bool,
     from: Pos2,
     to: Pos2,
     mut stroke: Stroke,
     style: WireStyle,
 ) {
+    // Keep very thin wires visible, fading them instead.
     if stroke.width < 1.0 {
         stroke.color = stroke.color.gamma_multiply(stroke.width);
         stroke.width = 1.0;
     }
 
+    let axes = |pos: Pos2| if vertical { transpose(pos) } else { pos };
+
     let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to);
     match style {
         WireStyle::Bezier3 => {
-            let [a, _, b, c, _, d] = wire_bezier_5(frame_size, from, to);
+            let [a, _, b, c, _, d] = wire_bezier_5(frame_size, axes(from), axes(to)).map(axes);
             let points = [a, b, c, d];
 
             let bb = Rect::from_points(&points);
@@ -234,7 +276,7 @@ pub fn draw_wire(
         }
 
         WireStyle::Bezier5 => {
-            let points = wire_bezier_5(frame_size, from, to);
+            let points = wire_bezier_5(frame_size, axes(from), axes(to)).map(axes);
 
             let bb = Rect::from_points(&points);
             if ui.is_rect_visible(bb) {
@@ -242,10 +284,330 @@ pub fn draw_wire(
             }
         }
 
+        WireStyle::AxisAligned { corner_radius } | WireStyle::Avoid { corner_radius } => {
+            draw_axis_aligned(
+                ui,
+                shapes,
+                corner_radius,
+                frame_size,
+                vertical,
+                from,
+                to,
+                stroke,
+            );
+        }
+    }
+}
+
+/// Returns points of the wire polyline, same as drawn by [`draw_wire`].
+///
+/// Curves and turns are sampled with step of about `threshold`.
+#[allow(clippy::too_many_arguments)]
+fn wire_path_points(
+    frame_size: f32,
+    upscale: bool,
+    downscale: bool,
+    vertical: bool,
+    from: Pos2,
+    to: Pos2,
+    threshold: f32,
+    style: WireStyle,
+) -> Vec<Pos2> {
+    let axes = |pos: Pos2| if vertical { transpose(pos) } else { pos };
+
+    let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to);
+    match style {
+        WireStyle::Bezier3 => {
+            let [a, _, b, c, _, d] = wire_bezier_5(frame_size, axes(from), axes(to)).map(axes);
+            bezier_path(&[a, b, c, d], threshold)
+        }
+        WireStyle::Bezier5 => {
+            let points = wire_bezier_5(frame_size, axes(from), axes(to)).map(axes);
+            bezier_path(&points, threshold)
+        }
+        WireStyle::AxisAligned { corner_radius } | WireStyle::Avoid { corner_radius } => {
+            axis_aligned_path(corner_radius, frame_size, vertical, from, to, threshold)
+        }
+    }
+}
+
+/// Step of sampling curves of [`WirePath`] in graph units.
+const WIRE_PATH_THRESHOLD: f32 = 1.0;
+
+/// Geometry of the wire drawn in the last frame.
+///
+/// All values are in graph space.
+#[derive(Clone)]
+pub struct WireGeometry {
+    pub from: Pos2,
+    pub to: Pos2,
+    pub frame_size: f32,
+    pub upscale: bool,
+    pub downscale: bool,
+    pub vertical: bool,
+    pub style: WireStyle,
+    pub route: Option<Vec<Pos2>>,
+}
+
+impl WireGeometry {
+    /// Builds path of the wire.
+    pub fn path(&self) -> WirePath {
+        let points = match (&self.route, self.style) {
+            (Some(route), WireStyle::Avoid { corner_radius }) => {
+                route_path(route, corner_radius, WIRE_PATH_THRESHOLD)
+            }
+            _ => wire_path_points(
+                self.frame_size,
+                self.upscale,
+                self.downscale,
+                self.vertical,
+                self.from,
+                self.to,
+                WIRE_PATH_THRESHOLD,
+                self.style,
+            ),
+        };
+
+        WirePath::new(points)
+    }
+}
+
+/// Geometry of wires drawn in the last frame.
+pub type WireGeometries = HashMap<Wire, WireGeometry>;
+
+/// Path of the wire in graph space as a polyline
+/// going from output pin to input pin.
+///
+/// Curves and rounded corners are sampled with step of about one graph unit.
+///
+/// See [`Snarl::wire_path`](crate::Snarl::wire_path).
+#[derive(Clone, Debug, PartialEq)]
+pub struct WirePath {
+    points: Vec<Pos2>,
+
+    /// Length of the path from the start to each point.
+    distances: Vec<f32>,
+}
+
+impl WirePath {
+    fn new(mut points: Vec<Pos2>) -> Self {
+        points.dedup();
+
+        let mut distances = Vec::with_capacity(points.len());
+        let mut distance = 0.0;
+        for (i, point) in points.iter().enumerate() {
+            if i > 0 {
+                distance += (*point - points[i - 1]).length();
+            }
+            distances.push(distance);
+        }
+
+        WirePath { points, distances }
+    }
+
+    /// Returns points of the polyline.
+    #[must_use]
+    pub fn points(&self) -> &[Pos2] {
+        &self.points
+    }
+
+    /// Returns length of the path.
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.distances.last().copied().unwrap_or(0.0)
+    }
+
+    /// Returns position and unit tangent at distance `t * length` along the path.
+    ///
+    /// `t` is clamped to `0.0..=1.0`, where `0.0` is output pin
+    /// and `1.0` is input pin.
+    /// Tangent points towards the input pin.
+    #[must_use]
+    pub fn sample(&self, t: f32) -> (Pos2, Vec2) {
+        match self.points[..] {
+            [] => return (Pos2::ZERO, Vec2::ZERO),
+            [point] => return (point, Vec2::ZERO),
+            _ => {}
+        }
+
+        let distance = t.clamp(0.0, 1.0) * self.length();
+
+        let idx = self
+            .distances
+            .partition_point(|&d| d < distance)
+            .clamp(1, self.points.len() - 1);
+
+        let a = self.points[idx - 1];
+        let b = self.points[idx];
+        let (da, db) = (self.distances[idx - 1], self.distances[idx]);
+
+        let s = if db > da {
+            (distance - da) / (db - da)
+        } else {
+            0.0
+        };
+
+        (a.lerp(b, s), (b - a).normalized())
+    }
+}
+
+#[derive(Clone)]
+struct CachedWire {
+    key: u64,
+
+    /// Wire tessellated with white color.
+    mesh: Arc<Mesh>,
+
+    /// Wire tinted with `color`.
+    tinted: Arc<Mesh>,
+    color: Color32,
+    used: bool,
+}
+
+/// Meshes of wires cached between frames.
+///
+/// Wire is tessellated again only when its geometry key changes.
+/// Color is not part of the key, changing it only tints cached mesh.
+#[derive(Clone, Default)]
+pub struct WireCache {
+    wires: HashMap<(OutPinId, InPinId), CachedWire>,
+
+    /// Number of wires drawn from cache since [`WireCache::start_frame`].
+    pub hits: usize,
+
+    /// Number of wires tessellated since [`WireCache::start_frame`].
+    pub misses: usize,
+}
+
+impl WireCache {
+    /// Resets counters of cache hits and misses.
+    pub fn start_frame(&mut self) {
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    /// Appends mesh of the wire to `shapes`.
+    ///
+    /// Cached mesh is reused if `key` matches the one it was tessellated with,
+    /// otherwise `tessellate` is called to build white mesh anew.
+    pub fn draw(
+        &mut self,
+        wire: (OutPinId, InPinId),
+        key: u64,
+        color: Color32,
+        shapes: &mut Vec<Shape>,
+        tessellate: impl FnOnce() -> Mesh,
+    ) {
+        match self.wires.get_mut(&wire) {
+            Some(cached) if cached.key == key => {
+                self.hits += 1;
+                if cached.color != color {
+                    cached.color = color;
+                    cached.tinted = tint_mesh(&cached.mesh, color);
+                }
+                cached.used = true;
+                shapes.push(Shape::mesh(Arc::clone(&cached.tinted)));
+            }
+            _ => {
+                self.misses += 1;
+                let mesh = Arc::new(tessellate());
+                let tinted = tint_mesh(&mesh, color);
+                shapes.push(Shape::mesh(Arc::clone(&tinted)));
+                self.wires.insert(
+                    wire,
+                    CachedWire {
+                        key,
+                        mesh,
+                        tinted,
+                        color,
+                        used: true,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Drops wires that were not drawn since last call.
+    pub fn retain_used(&mut self) {
+        self.wires
+            .retain(|_, cached| std::mem::take(&mut cached.used));
+    }
+}
+
+/// Multiplies colors of white `mesh` by `color`.
+fn tint_mesh(mesh: &Arc<Mesh>, color: Color32) -> Arc<Mesh> {
+    if color == Color32::WHITE {
+        return Arc::clone(mesh);
+    }
+
+    let mut tinted = Mesh::clone(mesh);
+    for vertex in &mut tinted.vertices {
+        vertex.color = vertex.color * color;
+    }
+    Arc::new(tinted)
+}
+
+/// Computes key of wire geometry from values that affect its mesh.
+///
+/// Color of the wire is not included, see [`WireCache`].
+pub fn wire_geometry_key(
+    floats: impl IntoIterator<Item = f32>,
+    style: WireStyle,
+    flags: &[bool],
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for v in floats {
+        v.to_bits().hash(&mut hasher);
+    }
+    match style {
+        WireStyle::Bezier3 => 0u8.hash(&mut hasher),
+        WireStyle::Bezier5 => 1u8.hash(&mut hasher),
         WireStyle::AxisAligned { corner_radius } => {
-            draw_axis_aligned(ui, shapes, corner_radius, frame_size, from, to, stroke);
+            2u8.hash(&mut hasher);
+            corner_radius.to_bits().hash(&mut hasher);
+        }
+        WireStyle::Avoid { corner_radius } => {
+            3u8.hash(&mut hasher);
+            corner_radius.to_bits().hash(&mut hasher);
         }
     }
+    flags.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Tessellates shapes into single mesh.
+///
+/// If `feathering` is set, it overrides feathering size in pixels,
+/// other tessellation options are taken from the context.
+pub fn tessellate_mesh(ui: &Ui, shapes: Vec<Shape>, feathering: Option<f32>) -> Mesh {
+    let cx = ui.ctx();
+
+    let mut options = cx.tessellation_options(|options| *options);
+    if let Some(feathering) = feathering {
+        options.feathering = feathering > 0.0;
+        options.feathering_size_in_pixels = feathering;
+    }
+
+    let mut tessellator = Tessellator::new(
+        cx.pixels_per_point(),
+        options,
+        cx.fonts(|fonts| fonts.font_image_size()),
+        Vec::new(),
+    );
+
+    let mut mesh = Mesh::default();
+    for shape in shapes {
+        tessellator.tessellate_shape(shape, &mut mesh);
+    }
+
+    mesh
+}
+
+/// Tessellates wire shapes into single mesh with given feathering size in pixels.
+///
+/// Other tessellation options are taken from the context.
+pub fn tessellate_wires(ui: &Ui, shapes: Vec<Shape>, feathering: f32) -> Shape {
+    Shape::mesh(tessellate_mesh(ui, shapes, Some(feathering)))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -254,11 +616,18 @@ pub fn hit_wire(
     frame_size: f32,
     upscale: bool,
     downscale: bool,
+    vertical: bool,
     from: Pos2,
     to: Pos2,
     threshold: f32,
     style: WireStyle,
 ) -> bool {
+    let (pos, from, to) = if vertical {
+        (transpose(pos), transpose(from), transpose(to))
+    } else {
+        (pos, from, to)
+    };
+
     let frame_size = adjust_frame_size(frame_size, upscale, downscale, from, to);
     match style {
         WireStyle::Bezier3 => {
@@ -270,12 +639,84 @@ pub fn hit_wire(
             let points = wire_bezier_5(frame_size, from, to);
             hit_bezier_5(pos, &points, threshold)
         }
-        WireStyle::AxisAligned { corner_radius } => {
+        WireStyle::AxisAligned { corner_radius } | WireStyle::Avoid { corner_radius } => {
             hit_axis_aligned(pos, corner_radius, frame_size, from, to, threshold)
         }
     }
 }
 
+/// Draws wire along routed path with rounded corners.
+pub fn draw_wire_route(
+    ui: &Ui,
+    shapes: &mut Vec<Shape>,
+    route: &[Pos2],
+    corner_radius: f32,
+    mut stroke: Stroke,
+) {
+    if stroke.width < 1.0 {
+        stroke.color = stroke.color.gamma_multiply(stroke.width);
+        stroke.width = 1.0;
+    }
+
+    if !ui.is_rect_visible(Rect::from_points(route)) {
+        return;
+    }
+
+    shapes.push(Shape::Path(PathShape {
+        points: route_path(route, corner_radius, stroke.width),
+        closed: false,
+        fill: Color32::TRANSPARENT,
+        stroke: stroke.into(),
+    }));
+}
+
+/// Returns points of routed path with corners rounded to `corner_radius`.
+///
+/// Corners are sampled with step of about `threshold`.
+fn route_path(route: &[Pos2], corner_radius: f32, threshold: f32) -> Vec<Pos2> {
+    let mut path = Vec::new();
+
+    for (i, &point) in route.iter().enumerate() {
+        let prev = i.checked_sub(1).and_then(|i| route.get(i));
+        let (Some(&prev), Some(&next)) = (prev, route.get(i + 1)) else {
+            path.push(point);
+            continue;
+        };
+
+        let radius = corner_radius
+            .min((prev - point).length() * 0.5)
+            .min((next - point).length() * 0.5);
+
+        if radius <= 0.0 {
+            path.push(point);
+            continue;
+        }
+
+        let to_prev = (prev - point).normalized();
+        let to_next = (next - point).normalized();
+        let center = point + (to_prev + to_next) * radius;
+
+        let samples = turn_samples_number(radius, threshold).max(1);
+        for j in 0..=samples {
+            #[allow(clippy::cast_precision_loss)]
+            let a = std::f32::consts::FRAC_PI_2 * (j as f32 / samples as f32);
+            let (sin_a, cos_a) = a.sin_cos();
+            path.push(center - to_next * (radius * cos_a) - to_prev * (radius * sin_a));
+        }
+    }
+
+    path
+}
+
+/// Checks if position is within `threshold` distance from routed path.
+pub fn hit_wire_route(pos: Pos2, route: &[Pos2], threshold: f32) -> bool {
+    route.windows(2).any(|segment| {
+        Rect::from_two_pos(segment[0], segment[1])
+            .expand(threshold)
+            .contains(pos)
+    })
+}
+
 #[inline]
 fn bezier_reference_size(points: &[Pos2]) -> f32 {
     let mut size = 0.0;
@@ -295,8 +736,9 @@ fn bezier_samples_number(points: &[Pos2], threshold: f32) -> usize {
     ((reference_size / threshold).ceil().max(0.0) as usize).min(MAX_CURVE_SAMPLES)
 }
 
-fn draw_bezier_5(shapes: &mut Vec<Shape>, points: &[Pos2; 6], stroke: Stroke) {
-    let samples = bezier_samples_number(points, stroke.width);
+/// Samples bezier curve into polyline with step of about `threshold`.
+fn bezier_path(points: &[Pos2], threshold: f32) -> Vec<Pos2> {
+    let samples = bezier_samples_number(points, threshold);
 
     let mut path = Vec::new();
 
@@ -306,8 +748,12 @@ fn draw_bezier_5(shapes: &mut Vec<Shape>, points: &[Pos2; 6], stroke: Stroke) {
         path.push(sample_bezier(points, t));
     }
 
+    path
+}
+
+fn draw_bezier_5(shapes: &mut Vec<Shape>, points: &[Pos2; 6], stroke: Stroke) {
     let shape = Shape::Path(PathShape {
-        points: path,
+        points: bezier_path(points, stroke.width),
         closed: false,
         fill: Color32::TRANSPARENT,
         stroke: stroke.into(),
@@ -317,18 +763,8 @@ fn draw_bezier_5(shapes: &mut Vec<Shape>, points: &[Pos2; 6], stroke: Stroke) {
 }
 
 fn draw_bezier_3(shapes: &mut Vec<Shape>, points: &[Pos2; 4], stroke: Stroke) {
-    let samples = bezier_samples_number(points, stroke.width);
-
-    let mut path = Vec::new();
-
-    for i in 0..samples {
-        #[allow(clippy::cast_precision_loss)]
-        let t = i as f32 / (samples - 1) as f32;
-        path.push(sample_bezier(points, t));
-    }
-
     let shape = Shape::Path(PathShape {
-        points: path,
+        points: bezier_path(points, stroke.width),
         closed: false,
         fill: Color32::TRANSPARENT,
         stroke: stroke.into(),
@@ -708,11 +1144,13 @@ fn turn_samples_number(radius: f32, threshold: f32) -> usize {
     ((reference_size / threshold).ceil().max(0.0) as usize).min(MAX_CURVE_SAMPLES / 4)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_axis_aligned(
     ui: &Ui,
     shapes: &mut Vec<Shape>,
     corner_radius: f32,
     frame_size: f32,
+    vertical: bool,
     from: Pos2,
     to: Pos2,
     stroke: Stroke,
@@ -723,6 +1161,31 @@ fn draw_axis_aligned(
         return;
     }
 
+    let shape = Shape::Path(PathShape {
+        points: axis_aligned_path(corner_radius, frame_size, vertical, from, to, stroke.width),
+        closed: false,
+        fill: Color32::TRANSPARENT,
+        stroke: stroke.into(),
+    });
+
+    shapes.push(shape);
+}
+
+/// Returns points of axis aligned wire with turns sampled with step of about `threshold`.
+fn axis_aligned_path(
+    corner_radius: f32,
+    frame_size: f32,
+    vertical: bool,
+    from: Pos2,
+    to: Pos2,
+    threshold: f32,
+) -> Vec<Pos2> {
+    let (from, to) = if vertical {
+        (transpose(from), transpose(to))
+    } else {
+        (from, to)
+    };
+
     let wire = wire_axis_aligned(corner_radius, frame_size, from, to);
 
     let mut path = Vec::new();
@@ -736,7 +1199,7 @@ fn draw_axis_aligned(
             continue;
         }
 
-        let samples = turn_samples_number(radius, stroke.width);
+        let samples = turn_samples_number(radius, threshold);
 
         for j in 1..samples {
             #[allow(clippy::cast_precision_loss)]
@@ -760,12 +1223,11 @@ fn draw_axis_aligned(
         path.push(wire.points[i + 1]);
     }
 
-    let shape = Shape::Path(PathShape {
-        points: path,
-        closed: false,
-        fill: Color32::TRANSPARENT,
-        stroke: stroke.into(),
-    });
+    if vertical {
+        for point in &mut path {
+            *point = transpose(*point);
+        }
+    }
 
-    shapes.push(shape);
+    path
 }
@@ -229,7 +229,7 @@ impl Zoom for WireStyle {
     fn zoom(&mut self, zoom: f32) {
         match self {
             WireStyle::Bezier3 | WireStyle::Bezier5 => {}
-            WireStyle::AxisAligned { corner_radius } => {
+            WireStyle::AxisAligned { corner_radius } | WireStyle::Avoid { corner_radius } => {
                 corner_radius.zoom(zoom);
             }
         }